@@ -7,11 +7,48 @@
 //! Cargo re-run the build script whenever `memory.x` is changed,
 //! updating `memory.x` ensures a rebuild of the application with the
 //! new memory settings.
+//!
+//! It also converts every image in `assets/` into the compact 1-bit format
+//! decoded by `src/assets.rs`, and generates a typed handle for each one.
+//!
+//! Finally it reads the badge owner's profile (`profiles/lynix.toml`, or the
+//! file named by `BADGE_PROFILE`) into `src/profile.rs`, measuring every string
+//! with the firmware's own fonts so text that wouldn't fit fails the build.
+//! Its keyboard macros are checked with `duckyscript` the same way.
 
 use std::env;
-use std::fs::File;
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use badge_barcode::Symbology;
+use embedded_graphics::{
+    mono_font::MonoFont,
+    prelude::*,
+    pixelcolor::BinaryColor,
+    text::{renderer::TextRenderer, Baseline},
+};
+use profont::*;
+
+// The firmware's proportional text metrics, so measurements here match the screen.
+#[allow(dead_code)]
+#[path = "src/font.rs"]
+mod font;
+
+// Schedule rules are parsed here too, so a typo fails the build instead of never matching.
+#[allow(dead_code)]
+#[path = "src/calendar.rs"]
+mod calendar;
+#[allow(dead_code)]
+#[path = "src/schedule.rs"]
+mod schedule;
+#[allow(dead_code)]
+#[path = "src/slide.rs"]
+mod slide;
+#[allow(dead_code)]
+#[path = "src/event.rs"]
+mod event;
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -28,4 +65,735 @@ fn main() {
     // here, we ensure the build script is only re-run when
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
+
+    // Images listed in `assets/manifest.txt` are checked against the screen
+    // layout and converted for `src/assets.rs`. Without the
+    // `placeholder-assets` feature any missing or broken image fails the build.
+    let placeholders = env::var_os("CARGO_FEATURE_PLACEHOLDER_ASSETS").is_some();
+    let specs = match convert_assets(Path::new("assets"), out, placeholders) {
+        Ok(specs) => specs,
+        Err(e) => panic!("\n\n{}\n", e),
+    };
+    println!("cargo:rerun-if-changed=assets");
+
+    // Owner profile, `BADGE_PROFILE=profiles/someone.toml cargo build` picks another one.
+    let profile = env::var("BADGE_PROFILE").unwrap_or_else(|_| "profiles/lynix.toml".to_string());
+    if let Err(e) = generate_profile(Path::new(&profile), &specs, out) {
+        panic!("\n\n{}\n", e);
+    }
+    println!("cargo:rerun-if-env-changed=BADGE_PROFILE");
+    println!("cargo:rerun-if-changed={}", profile);
+}
+
+/// One line of `assets/manifest.txt`.
+struct AssetSpec {
+    handle: String,
+    file: String,
+    width: u32,
+    height: u32,
+    // Also build a 2-bit `HANDLE_GRAY` image for the grayscale drawing path.
+    gray: bool,
+    placements: Vec<(u32, u32)>,
+}
+
+/// A decoded image, one 8-bit luminance value per pixel (255 = white).
+struct Bitmap {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+fn parse_size(text: &str, separator: char) -> Option<(u32, u32)> {
+    let (a, b) = text.split_once(separator)?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+fn parse_manifest(text: &str) -> Result<Vec<AssetSpec>, String> {
+    let mut specs = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let error = |what: &str| format!("assets/manifest.txt:{}: {}", number + 1, what);
+        if fields.len() < 4 {
+            return Err(error("expected `HANDLE file WxH [gray] x,y [x,y ...]`"));
+        }
+        let (width, height) = parse_size(fields[2], 'x').ok_or_else(|| error("size must look like 32x32"))?;
+        let gray = fields[3] == "gray";
+        let placements = fields[if gray { 4 } else { 3 }..]
+            .iter()
+            .map(|p| parse_size(p, ',').ok_or_else(|| error("position must look like 140,100")))
+            .collect::<Result<_, _>>()?;
+
+        specs.push(AssetSpec {
+            handle: fields[0].to_string(),
+            file: fields[1].to_string(),
+            width,
+            height,
+            gray,
+            placements,
+        });
+    }
+    Ok(specs)
+}
+
+fn convert_assets(dir: &Path, out: &Path, placeholders: bool) -> Result<Vec<AssetSpec>, String> {
+    let manifest = fs::read_to_string(dir.join("manifest.txt"))
+        .map_err(|e| format!("can't read assets/manifest.txt: {}", e))?;
+    let specs = parse_manifest(&manifest)?;
+
+    // Report every broken asset at once rather than one per build.
+    let mut errors = Vec::new();
+    let mut handles = String::new();
+    for spec in &specs {
+        let bitmap = match load_asset(dir, spec, placeholders) {
+            Ok(bitmap) => bitmap,
+            Err(e) => {
+                errors.push(format!("assets/{}: {}", spec.file, e));
+                continue;
+            }
+        };
+
+        for &(x, y) in &spec.placements {
+            if x + bitmap.width > DISPLAY_WIDTH || y + bitmap.height > DISPLAY_HEIGHT {
+                errors.push(format!(
+                    "assets/{}: {}x{} image drawn at {},{} runs off the {}x{} display",
+                    spec.file, bitmap.width, bitmap.height, x, y, DISPLAY_WIDTH, DISPLAY_HEIGHT
+                ));
+            }
+        }
+
+        let packed = pack(&bitmap);
+        let rle = run_length(&bitmap);
+        let (encoding, bytes) = if rle.len() < packed.len() {
+            ("Rle", rle)
+        } else {
+            ("Packed", packed)
+        };
+
+        let file = format!("{}.bin", spec.handle.to_lowercase());
+        fs::write(out.join(&file), &bytes).map_err(|e| e.to_string())?;
+
+        let _ = writeln!(
+            handles,
+            "/// `assets/{}`, {}x{}, {} bytes\npub const {}: Asset = Asset::new({}, {}, Encoding::{}, include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{}\")));",
+            spec.file,
+            bitmap.width,
+            bitmap.height,
+            bytes.len(),
+            spec.handle,
+            bitmap.width,
+            bitmap.height,
+            encoding,
+            file
+        );
+
+        if spec.gray {
+            let bytes = pack_gray(&bitmap);
+            let file = format!("{}_gray.bin", spec.handle.to_lowercase());
+            fs::write(out.join(&file), &bytes).map_err(|e| e.to_string())?;
+            let _ = writeln!(
+                handles,
+                "/// `assets/{}`, {}x{} at 2 bits per pixel\npub const {}_GRAY: GrayAsset = GrayAsset::new({}, {}, include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{}\")));",
+                spec.file,
+                bitmap.width,
+                bitmap.height,
+                spec.handle,
+                bitmap.width,
+                bitmap.height,
+                file
+            );
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(format!("asset validation failed:\n  {}", errors.join("\n  ")));
+    }
+
+    // Name lookup for images referenced at runtime (stored screens).
+    let _ = writeln!(handles, "pub static CATALOG: [Entry; {}] = [", specs.len());
+    for spec in &specs {
+        let gray = if spec.gray { format!("Some(&{}_GRAY)", spec.handle) } else { "None".to_string() };
+        let _ = writeln!(handles, "    Entry {{ name: \"{}\", image: &{}, gray: {} }},", spec.handle, spec.handle, gray);
+    }
+    let _ = writeln!(handles, "];");
+
+    fs::write(out.join("assets.rs"), handles).map_err(|e| e.to_string())?;
+    Ok(specs)
+}
+
+const DISPLAY_WIDTH: u32 = 296;
+const DISPLAY_HEIGHT: u32 = 128;
+
+fn load_asset(dir: &Path, spec: &AssetSpec, placeholders: bool) -> Result<Bitmap, String> {
+    let data = fs::read(dir.join(&spec.file)).unwrap_or_default();
+
+    let problem = if data.is_empty() {
+        Some("file is missing or empty")
+    } else if data.starts_with(b"version https://git-lfs") {
+        Some("file is a Git LFS pointer")
+    } else {
+        None
+    };
+    if let Some(problem) = problem {
+        if placeholders {
+            println!("cargo:warning=assets/{}: {}, using a placeholder", spec.file, problem);
+            return Ok(placeholder(spec));
+        }
+        return Err(format!(
+            "{} (run `git lfs pull`, or build with `--features placeholder-assets`)",
+            problem
+        ));
+    }
+
+    let bitmap = decode_bmp(&data)?;
+    if bitmap.width != spec.width || bitmap.height != spec.height {
+        return Err(format!(
+            "image is {}x{} but the manifest lays it out as {}x{}",
+            bitmap.width, bitmap.height, spec.width, spec.height
+        ));
+    }
+    Ok(bitmap)
+}
+
+// 3x5 glyphs for placeholder labels, top row in the high bits.
+const LABEL_FONT: [(char, u16); 37] = [
+    ('A', 0b010_101_111_101_101), ('B', 0b110_101_110_101_110), ('C', 0b011_100_100_100_011),
+    ('D', 0b110_101_101_101_110), ('E', 0b111_100_110_100_111), ('F', 0b111_100_110_100_100),
+    ('G', 0b011_100_101_101_011), ('H', 0b101_101_111_101_101), ('I', 0b111_010_010_010_111),
+    ('J', 0b001_001_001_101_010), ('K', 0b101_101_110_101_101), ('L', 0b100_100_100_100_111),
+    ('M', 0b101_111_111_101_101), ('N', 0b110_101_101_101_101), ('O', 0b010_101_101_101_010),
+    ('P', 0b110_101_110_100_100), ('Q', 0b010_101_101_110_011), ('R', 0b110_101_110_101_101),
+    ('S', 0b011_100_010_001_110), ('T', 0b111_010_010_010_010), ('U', 0b101_101_101_101_111),
+    ('V', 0b101_101_101_101_010), ('W', 0b101_101_111_111_101), ('X', 0b101_101_010_101_101),
+    ('Y', 0b101_101_010_010_010), ('Z', 0b111_001_010_100_111), ('0', 0b111_101_101_101_111),
+    ('1', 0b010_110_010_010_111), ('2', 0b110_001_010_100_111), ('3', 0b110_001_010_001_110),
+    ('4', 0b101_101_111_001_001), ('5', 0b111_100_110_001_110), ('6', 0b011_100_111_101_111),
+    ('7', 0b111_001_010_010_010), ('8', 0b111_101_111_101_111), ('9', 0b111_101_111_001_110),
+    ('_', 0b000_000_000_000_111),
+];
+
+// A crossed-out box the size of the real image, labelled with its handle when there's room.
+fn placeholder(spec: &AssetSpec) -> Bitmap {
+    let (width, height) = (spec.width, spec.height);
+    let mut pixels = vec![255u8; (width * height) as usize];
+    let mut set = |x: u32, y: u32| {
+        if x < width && y < height {
+            pixels[(y * width + x) as usize] = 0;
+        }
+    };
+
+    for x in 0..width {
+        set(x, 0);
+        set(x, height - 1);
+    }
+    for y in 0..height {
+        set(0, y);
+        set(width - 1, y);
+    }
+    let steps = width.max(height);
+    for i in 0..steps {
+        let x = i * width / steps;
+        let y = i * height / steps;
+        set(x, y);
+        set(width - 1 - x, y);
+    }
+
+    let label: Vec<u16> = spec
+        .handle
+        .chars()
+        .filter_map(|c| LABEL_FONT.iter().find(|(g, _)| *g == c).map(|(_, bits)| *bits))
+        .collect();
+    let label_width = label.len() as u32 * 4;
+    if label_width + 4 <= width && height >= 11 {
+        let left = (width - label_width) / 2;
+        let top = (height - 7) / 2;
+        // Clear a box behind the text so the cross doesn't run through it.
+        for y in top..top + 7 {
+            for x in left - 1..left + label_width + 1 {
+                pixels[(y * width + x) as usize] = 255;
+            }
+        }
+        for (i, glyph) in label.iter().enumerate() {
+            for row in 0..5 {
+                for col in 0..3 {
+                    if glyph & (1 << (14 - row * 3 - col)) != 0 {
+                        pixels[((top + 1 + row) * width + left + i as u32 * 4 + col) as usize] = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    Bitmap { width, height, pixels }
+}
+
+fn is_light(luma: u8) -> bool {
+    luma >= 128
+}
+
+fn pack(bitmap: &Bitmap) -> Vec<u8> {
+    let mut bytes = vec![0u8; bitmap.pixels.len().div_ceil(8)];
+    for (i, _) in bitmap.pixels.iter().enumerate().filter(|(_, luma)| is_light(**luma)) {
+        bytes[i / 8] |= 0x80 >> (i % 8);
+    }
+    bytes
+}
+
+// Four levels (0 = black .. 3 = white), four pixels per byte, MSB first.
+fn pack_gray(bitmap: &Bitmap) -> Vec<u8> {
+    let mut bytes = vec![0u8; bitmap.pixels.len().div_ceil(4)];
+    for (i, &luma) in bitmap.pixels.iter().enumerate() {
+        let level = (luma as u32 * 3 + 127) / 255;
+        bytes[i / 4] |= (level as u8) << (6 - (i % 4) * 2);
+    }
+    bytes
+}
+
+// Alternating runs starting with black (`Off`), each length as a LEB128 varint.
+fn run_length(bitmap: &Bitmap) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut color = false;
+    let mut run = 0usize;
+    for pixel in bitmap.pixels.iter().map(|luma| is_light(*luma)) {
+        if pixel != color {
+            push_varint(&mut bytes, run);
+            color = pixel;
+            run = 0;
+        }
+        run += 1;
+    }
+    push_varint(&mut bytes, run);
+    bytes
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn read_u16(data: &[u8], at: usize) -> u32 {
+    u16::from_le_bytes([data[at], data[at + 1]]) as u32
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+// Uncompressed Windows bitmaps: 1, 4 and 8 bit palettized, 24 and 32 bit colour.
+fn decode_bmp(data: &[u8]) -> Result<Bitmap, String> {
+    if data.len() < 54 || &data[0..2] != b"BM" {
+        return Err("not a BMP file".into());
+    }
+
+    let pixel_offset = read_u32(data, 10) as usize;
+    let header_size = read_u32(data, 14) as usize;
+    let width = read_u32(data, 18) as i32;
+    let raw_height = read_u32(data, 22) as i32;
+    let bpp = read_u16(data, 28);
+    let compression = read_u32(data, 30);
+    let colors_used = read_u32(data, 46) as usize;
+
+    if compression != 0 {
+        return Err("compressed BMPs are not supported".into());
+    }
+    if width <= 0 || raw_height == 0 {
+        return Err("image has no pixels".into());
+    }
+    if !matches!(bpp, 1 | 4 | 8 | 24 | 32) {
+        return Err(format!("unsupported bit depth {}", bpp));
+    }
+
+    let width = width as u32;
+    let height = raw_height.unsigned_abs();
+    let bottom_up = raw_height > 0;
+
+    let palette_start = 14 + header_size;
+    let palette_len = if bpp <= 8 {
+        if colors_used == 0 { 1 << bpp } else { colors_used }
+    } else {
+        0
+    };
+    let palette: Vec<u8> = (0..palette_len)
+        .map(|i| {
+            let at = palette_start + i * 4;
+            if at + 3 > data.len() {
+                return Err("palette is truncated".to_string());
+            }
+            Ok(luma(data[at + 2], data[at + 1], data[at]))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let stride = ((width * bpp).div_ceil(32) * 4) as usize;
+    if pixel_offset + stride * height as usize > data.len() {
+        return Err("pixel data is truncated".into());
+    }
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        let row = if bottom_up { height - 1 - y } else { y };
+        let row = &data[pixel_offset + row as usize * stride..][..stride];
+        for x in 0..width as usize {
+            let lookup = |index: u8| palette.get(index as usize).copied().unwrap_or(0);
+            let pixel = match bpp {
+                1 => lookup((row[x / 8] >> (7 - x % 8)) & 1),
+                4 => lookup((row[x / 2] >> if x % 2 == 0 { 4 } else { 0 }) & 0xf),
+                8 => lookup(row[x]),
+                24 => luma(row[x * 3 + 2], row[x * 3 + 1], row[x * 3]),
+                _ => luma(row[x * 4 + 2], row[x * 4 + 1], row[x * 4]),
+            };
+            pixels.push(pixel);
+        }
+    }
+
+    Ok(Bitmap { width, height, pixels })
+}
+
+// Rec. 601 luma.
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+/// `key = "value"` and `key = ["a", "b"]` lines, the part of TOML profiles need.
+fn parse_profile(text: &str, file: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |what: &str| format!("{}:{}: {}", file, number + 1, what);
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected `key = \"value\"`"))?;
+        let value = value.trim();
+
+        let values = if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let mut values = Vec::new();
+            let mut rest = list.trim();
+            while !rest.is_empty() {
+                let (value, after) = parse_string(rest).ok_or_else(|| error("list items must be quoted strings"))?;
+                values.push(value);
+                rest = after.trim_start().trim_start_matches(',').trim_start();
+            }
+            values
+        } else {
+            match parse_string(value) {
+                Some((value, after)) if after.trim().is_empty() || after.trim().starts_with('#') => vec![value],
+                _ => return Err(error("value must be a quoted string or a list of them")),
+            }
+        };
+        entries.push((key.trim().to_string(), values));
+    }
+    Ok(entries)
+}
+
+// One basic TOML string off the front of `text`, returns it and what follows.
+fn parse_string(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                '"' => value.push('"'),
+                '\\' => value.push('\\'),
+                _ => return None,
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+// Mirrors `src/layout.rs`: space between a picture and its text, and between stacked blocks.
+const LAYOUT_GAP: u32 = 10;
+const LAYOUT_LINE_GAP: u32 = 4;
+// Mirrors `draw::draw_textbox`: extra space after every newline.
+const PARAGRAPH_SPACING: u32 = 6;
+
+/// A text box on one of the profile screens.
+struct TextBox {
+    field: &'static str,
+    font: MonoFont<'static>,
+    width: u32,
+    height: u32,
+}
+
+// Lines `text` wraps to in a box `width` wide. Profile text can't have words too wide for a line,
+// the badge would break them up.
+fn wrapped_lines(text: &str, font: &MonoFont, width: u32) -> Result<u32, String> {
+    let style = font::TextStyle::new(font, BinaryColor::Off);
+    let measure = |s: &str| style.measure_string(s, Point::zero(), Baseline::Top).next_position.x as u32;
+
+    if let Some(word) = text.split(['\n', ' ']).find(|word| measure(word) > width) {
+        return Err(format!("\"{}\" is {}px wide, the box is {}px", word, measure(word), width));
+    }
+    Ok(style.wrapped_lines(text, width))
+}
+
+// Whether `text` fits `text_box` as `draw::draw_textbox` lays it out, or why not.
+fn fits(text_box: &TextBox, text: &str) -> Result<(), String> {
+    let line_height = text_box.font.character_size.height;
+    let lines = wrapped_lines(text, &text_box.font, text_box.width).map_err(|e| format!("doesn't fit: {}", e))?;
+    let height = lines * line_height + text.matches('\n').count() as u32 * PARAGRAPH_SPACING;
+    if height > text_box.height {
+        return Err(format!("takes {} lines, {}px, but only {}px fit", lines, height, text_box.height));
+    }
+    Ok(())
+}
+
+/// A code for the Barcodes program.
+struct Barcode {
+    label: String,
+    symbology: Symbology,
+    payload: String,
+}
+
+// A `barcodes` line: the symbology, the label, then ` = ` and the payload, e.g.
+// "code128 Ticket ID = LYNIX-2023-0042". The Barcodes program draws 1D codes one pixel a module
+// at least and 2D codes beside their label, so both are checked against the panel.
+fn parse_barcode(line: &str) -> Result<Barcode, String> {
+    let (name, rest) = line.split_once(' ').ok_or("expected a symbology, a label, then = and the payload")?;
+    let (label, payload) = rest.split_once(" = ").ok_or("expected the label, then = and the payload")?;
+    let symbology = match name {
+        "code128" => Symbology::Code128,
+        "datamatrix" => Symbology::DataMatrix,
+        "aztec" => Symbology::Aztec,
+        "qr" => Symbology::Qr,
+        _ => return Err(format!("`{}` isn't code128, datamatrix, aztec or qr", name)),
+    };
+
+    let symbol = badge_barcode::encode(symbology, payload).map_err(|e| format!("can't encode the payload: {:?}", e))?;
+    let size = symbol.width() as u32;
+    if symbol.height() == 1 && size > DISPLAY_WIDTH {
+        return Err(format!("it's {} modules wide, the screen is {}px", size, DISPLAY_WIDTH));
+    }
+    if symbol.height() > 1 && size > DISPLAY_HEIGHT - 8 {
+        return Err(format!("it's {} modules square, at most {} fit", size, DISPLAY_HEIGHT - 8));
+    }
+    Ok(Barcode { label: label.to_string(), symbology, payload: payload.to_string() })
+}
+
+fn generate_profile(path: &Path, specs: &[AssetSpec], out: &Path) -> Result<(), String> {
+    let file = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read profile {}: {}", file, e))?;
+    let entries = parse_profile(&text, &file)?;
+
+    let mut errors = Vec::new();
+    let get = |key: &str| entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+    let mut require = |key: &str| {
+        get(key).unwrap_or_else(|| {
+            errors.push(format!("{}: missing `{}`", file, key));
+            vec![String::new()]
+        })
+    };
+
+    let name = require("name").join(" ");
+    let title = require("title");
+    let photo = require("photo").join("");
+    let badges = get("badges").unwrap_or_default();
+    let socials = require("socials");
+    let socials_qr = require("socials_qr").join("");
+    let serial = require("serial").join("");
+    let pronouns = get("pronouns").map(|v| v.join("")).unwrap_or_default();
+    let model = get("model").map(|v| v.join("")).unwrap_or_else(|| "Lynix E-Ink Badge".to_string());
+    let rules = get("schedule").unwrap_or_default();
+    let utc_offset = get("utc_offset").map(|v| v.join("")).unwrap_or_else(|| "+00:00".to_string());
+    let dst = get("dst").map(|v| v.join("")).unwrap_or_else(|| "none".to_string());
+    let events = get("events").unwrap_or_default();
+    let privacy = get("privacy").map(|v| v.join("")).unwrap_or_else(|| "off".to_string());
+    let macro_files = get("macros").unwrap_or_default();
+    let keyboard = get("keyboard").map(|v| v.join("")).unwrap_or_else(|| "us".to_string());
+    let barcode_lines = get("barcodes").unwrap_or_default();
+
+    for (key, _) in &entries {
+        if !["name", "pronouns", "title", "photo", "badges", "socials", "socials_qr", "serial", "model", "schedule", "utc_offset", "dst", "events", "privacy", "macros", "keyboard", "barcodes"].contains(&key.as_str()) {
+            errors.push(format!("{}: unknown key `{}`", file, key));
+        }
+    }
+
+    for rule in &rules {
+        if let Err(e) = schedule::Rule::parse(rule) {
+            errors.push(format!("{}: schedule rule \"{}\": {}", file, rule, e));
+        }
+    }
+
+    let mut event_names = Vec::new();
+    for line in &events {
+        match event::Event::parse(line) {
+            Ok(event) => event_names.push(event.name.to_string()),
+            Err(e) => errors.push(format!("{}: event \"{}\": {}", file, line, e)),
+        }
+    }
+
+    let offset = calendar::UtcOffset::parse(&utc_offset);
+    if offset.is_none() {
+        errors.push(format!("{}: `utc_offset` is \"{}\", expected something like \"-04:00\"", file, utc_offset));
+    }
+    let dst_rules = calendar::Dst::parse(&dst);
+    if dst_rules.is_none() {
+        errors.push(format!("{}: `dst` is \"{}\", expected none, north-america or europe", file, dst));
+    }
+
+    if !["on", "off"].contains(&privacy.as_str()) {
+        errors.push(format!("{}: `privacy` is \"{}\", expected on or off", file, privacy));
+    }
+
+    let layout = duckyscript::Layout::from_name(&keyboard);
+    if layout.is_none() {
+        errors.push(format!("{}: `keyboard` is \"{}\", expected us, uk or de", file, keyboard));
+    }
+    if macro_files.len() > 3 {
+        errors.push(format!("{}: `macros` has {} scripts, but only A, Up and Down type them", file, macro_files.len()));
+    }
+    let mut macros = Vec::new();
+    for path in &macro_files {
+        println!("cargo:rerun-if-changed={}", path);
+        let name = Path::new(path).file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+        match fs::read_to_string(path) {
+            Ok(script) => {
+                if let Err(e) = duckyscript::check(&script, layout.unwrap_or_default()) {
+                    errors.push(format!("{}: macro {}, {}", file, path, e));
+                }
+                macros.push((name, script));
+            }
+            Err(e) => errors.push(format!("{}: can't read macro {}: {}", file, path, e)),
+        }
+    }
+
+    let mut barcodes = Vec::new();
+    for line in &barcode_lines {
+        match parse_barcode(line) {
+            Ok(barcode) => barcodes.push(barcode),
+            Err(e) => errors.push(format!("{}: barcode \"{}\": {}", file, line, e)),
+        }
+    }
+
+    let mut image = |key: &str, handle: &str| -> Option<&AssetSpec> {
+        let spec = specs.iter().find(|spec| spec.handle == handle);
+        if spec.is_none() {
+            errors.push(format!("{}: `{}` is \"{}\", which isn't in assets/manifest.txt", file, key, handle));
+        }
+        spec
+    };
+    let photo_spec = image("photo", &photo);
+    let qr_spec = image("socials_qr", &socials_qr);
+    let badge_specs: Vec<&AssetSpec> = badges.iter().filter_map(|badge| image("badges", badge)).collect();
+
+    let mut details: Vec<String> = Vec::new();
+    if !pronouns.is_empty() {
+        details.push(pronouns.clone());
+    }
+    details.extend(title.iter().cloned());
+    let details = details.join("\n");
+    let socials = socials.join("\n");
+    let info = format!("FW Version: v{}\nSerial #: {}\n{}", env::var("CARGO_PKG_VERSION").unwrap(), serial, model);
+
+    // Boxes as laid out by `Layout::Card`, `Layout::Qr` and `Layout::App`.
+    let title_height = PROFONT_24_POINT.character_size.height;
+    let heading_height = PROFONT_18_POINT.character_size.height;
+    let mut checks = Vec::new();
+    if let Some(photo) = photo_spec {
+        let width = DISPLAY_WIDTH.saturating_sub(photo.width + LAYOUT_GAP + 5);
+        checks.push((TextBox { field: "name", font: PROFONT_24_POINT, width, height: title_height }, name.clone()));
+        let top = title_height + LAYOUT_LINE_GAP;
+        // Badges sit in a row along the bottom of the same column, under the details.
+        let row = badge_specs.iter().map(|spec| spec.height).max().map_or(0, |row| row + LAYOUT_LINE_GAP);
+        let row_width = badge_specs.iter().map(|spec| spec.width).sum::<u32>() + LAYOUT_GAP * (badge_specs.len() as u32).saturating_sub(1);
+        if row_width > width || top + row > DISPLAY_HEIGHT {
+            errors.push(format!("{}: `badges` take {}x{}px, only {}x{}px fit", file, row_width, row, width, DISPLAY_HEIGHT - top));
+        }
+        checks.push((TextBox { field: "pronouns/title", font: PROFONT_14_POINT, width, height: DISPLAY_HEIGHT.saturating_sub(top + row) }, details.clone()));
+    }
+    if let Some(qr) = qr_spec {
+        let width = DISPLAY_WIDTH.saturating_sub(qr.width + LAYOUT_GAP + 5);
+        let top = LAYOUT_LINE_GAP + heading_height + LAYOUT_LINE_GAP * 2;
+        checks.push((TextBox { field: "socials", font: PROFONT_14_POINT, width, height: DISPLAY_HEIGHT - top }, socials.clone()));
+    }
+    // Device Info's header icon.
+    let icon = specs.iter().find(|spec| spec.handle == "OPTIONS").map_or(32, |spec| spec.height);
+    let header = icon.max((icon.saturating_sub(title_height)) / 2 + title_height) + LAYOUT_LINE_GAP + 2;
+    checks.push((TextBox { field: "serial/model", font: PROFONT_14_POINT, width: DISPLAY_WIDTH, height: DISPLAY_HEIGHT - header }, info.clone()));
+
+    // Countdown puts an event's name on one line under the header.
+    for name in event_names {
+        checks.push((TextBox { field: "events", font: PROFONT_18_POINT, width: DISPLAY_WIDTH, height: heading_height }, name));
+    }
+    // Keyboard Macros lists a macro's name after its button, one to a line.
+    for (name, _) in &macros {
+        let line_height = PROFONT_14_POINT.character_size.height;
+        checks.push((TextBox { field: "macros", font: PROFONT_14_POINT, width: DISPLAY_WIDTH, height: line_height }, format!("Down: {}", name)));
+    }
+
+    for (text_box, text) in &checks {
+        if let Err(e) = fits(text_box, text) {
+            errors.push(format!("{}: `{}` {}", file, text_box.field, e));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(format!("profile validation failed:\n  {}", errors.join("\n  ")));
+    }
+
+    let gray = photo_spec.is_some_and(|spec| spec.gray);
+    let mut code = String::new();
+    let _ = writeln!(code, "// Generated by build.rs from `{}`.", file);
+    let _ = writeln!(code, "pub const NAME: &str = {:?};", name);
+    let _ = writeln!(code, "pub const DETAILS: &str = {:?};", details);
+    let _ = writeln!(code, "pub const PRONOUNS: &str = {:?};", pronouns);
+    let _ = writeln!(code, "pub const PHOTO: &Asset = &assets::{};", photo);
+    if gray {
+        let _ = writeln!(code, "pub const PHOTO_GRAY: Option<&GrayAsset> = Some(&assets::{}_GRAY);", photo);
+    } else {
+        let _ = writeln!(code, "pub const PHOTO_GRAY: Option<&GrayAsset> = None;");
+    }
+    let badges: Vec<String> = badges.iter().map(|badge| format!("&assets::{}", badge)).collect();
+    let _ = writeln!(code, "pub const BADGES: &[&Asset] = &[{}];", badges.join(", "));
+    let _ = writeln!(code, "pub const SOCIALS: &str = {:?};", socials);
+    let _ = writeln!(code, "pub const SOCIALS_QR: &Asset = &assets::{};", socials_qr);
+    let _ = writeln!(code, "pub const DEVICE_INFO: &str = {:?};", info);
+    let _ = writeln!(code, "pub const UTC_OFFSET: UtcOffset = UtcOffset({});", offset.map_or(0, |offset| offset.0));
+    let _ = writeln!(code, "pub const DST: Dst = Dst::{:?};", dst_rules.unwrap_or(calendar::Dst::None));
+    let _ = writeln!(code, "pub const SCHEDULE: &[&str] = &{:?};", rules);
+    let _ = writeln!(code, "pub const EVENTS: &[&str] = &{:?};", events);
+    let _ = writeln!(code, "pub const BARCODES: &[(&str, Symbology, &str)] = &[");
+    for barcode in &barcodes {
+        let _ = writeln!(code, "    ({:?}, Symbology::{:?}, {:?}),", barcode.label, barcode.symbology, barcode.payload);
+    }
+    let _ = writeln!(code, "];");
+    let _ = writeln!(code, "pub const PRIVACY: bool = {};", privacy == "on");
+    // Only builds with the keyboard have anything to type macros with.
+    if env::var_os("CARGO_FEATURE_KEYBOARD_MACROS").is_some() {
+        let _ = writeln!(code, "pub const MACROS: &[(&str, &str)] = &{:?};", macros);
+        let _ = writeln!(code, "pub const KEYBOARD: &str = {:?};", keyboard);
+    }
+    fs::write(out.join("profile.rs"), code).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paragraph_spacing_counts() {
+        let line_height = PROFONT_14_POINT.character_size.height;
+        let two_lines = TextBox { field: "title", font: PROFONT_14_POINT, width: DISPLAY_WIDTH, height: line_height * 2 };
+        assert_eq!(wrapped_lines("Student\nCanadian", &two_lines.font, two_lines.width), Ok(2));
+        assert!(fits(&two_lines, "Student Canadian").is_ok());
+        // Two lines of text, but the paragraph break between them needs room too.
+        assert!(fits(&two_lines, "Student\nCanadian").is_err());
+
+        let spaced = TextBox { height: line_height * 2 + PARAGRAPH_SPACING, ..two_lines };
+        assert!(fits(&spaced, "Student\nCanadian").is_ok());
+    }
 }
@@ -0,0 +1,785 @@
+//! Barcode encoders for the badge: Code 128, Data Matrix, Aztec and QR codes, as grids of
+//! modules for the firmware to draw.
+//!
+//! Everything here is `no_std` and allocation free, so it's tested on the host.
+
+#![cfg_attr(not(test), no_std)]
+
+use qrcodegen_no_heap::{QrCode, QrCodeEcc, Version};
+
+// Largest symbol we keep in RAM: a version 10 QR code (57x57), or a Code 128 row of the same
+// module count.
+const MAX_MODULES: usize = 57 * 57;
+const MAX_QR_VERSION: u8 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symbology {
+    Code128,
+    DataMatrix,
+    Aztec,
+    Qr,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BarcodeError {
+    // Payload does not fit in the largest symbol we support.
+    TooLong,
+    // Payload has a character the symbology can't encode.
+    UnsupportedCharacter,
+}
+
+/// A grid of dark/light modules. 1D symbols (Code 128) are a single row.
+pub struct Symbol {
+    width: usize,
+    height: usize,
+    modules: [u8; MAX_MODULES.div_ceil(8)],
+}
+
+impl Symbol {
+    fn new(width: usize, height: usize) -> Self {
+        Symbol { width, height, modules: [0; MAX_MODULES.div_ceil(8)] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let i = y * self.width + x;
+        self.modules[i / 8] & (1 << (i % 8)) != 0
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        let i = y * self.width + x;
+        if dark {
+            self.modules[i / 8] |= 1 << (i % 8);
+        } else {
+            self.modules[i / 8] &= !(1 << (i % 8));
+        }
+    }
+}
+
+pub fn encode(symbology: Symbology, payload: &str) -> Result<Symbol, BarcodeError> {
+    match symbology {
+        Symbology::Code128 => encode_code128(payload),
+        Symbology::DataMatrix => encode_datamatrix(payload.as_bytes()),
+        Symbology::Aztec => encode_aztec(payload.as_bytes()),
+        Symbology::Qr => encode_qr(payload),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Code 128
+// ---------------------------------------------------------------------------
+
+// Bar/space patterns for symbol values 0..=105, 11 modules each, MSB first.
+const CODE128_PATTERNS: [u16; 106] = [
+    0x6cc, 0x66c, 0x666, 0x498, 0x48c, 0x44c, 0x4c8, 0x4c4, 0x464, 0x648, 0x644, 0x624, 0x59c, 0x4dc,
+    0x4ce, 0x5cc, 0x4ec, 0x4e6, 0x672, 0x65c, 0x64e, 0x6e4, 0x674, 0x76e, 0x74c, 0x72c, 0x726, 0x764,
+    0x734, 0x732, 0x6d8, 0x6c6, 0x636, 0x518, 0x458, 0x446, 0x588, 0x468, 0x462, 0x688, 0x628, 0x622,
+    0x5b8, 0x58e, 0x46e, 0x5d8, 0x5c6, 0x476, 0x776, 0x68e, 0x62e, 0x6e8, 0x6e2, 0x6ee, 0x758, 0x746,
+    0x716, 0x768, 0x762, 0x71a, 0x77a, 0x642, 0x78a, 0x530, 0x50c, 0x4b0, 0x486, 0x42c, 0x426, 0x590,
+    0x584, 0x4d0, 0x4c2, 0x434, 0x432, 0x612, 0x650, 0x7ba, 0x614, 0x47a, 0x53c, 0x4bc, 0x49e, 0x5e4,
+    0x4f4, 0x4f2, 0x7a4, 0x794, 0x792, 0x6de, 0x6f6, 0x7b6, 0x578, 0x51e, 0x45e, 0x5e8, 0x5e2, 0x7a8,
+    0x7a2, 0x5de, 0x5ee, 0x75e, 0x7ae, 0x684, 0x690, 0x69c,
+];
+const CODE128_STOP: u16 = 0x18eb; // 13 modules
+const CODE128_START_B: u8 = 104;
+const CODE128_START_C: u8 = 105;
+const CODE128_CODE_B: u8 = 100;
+const CODE128_CODE_C: u8 = 99;
+
+fn digit_run(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+fn encode_code128(payload: &str) -> Result<Symbol, BarcodeError> {
+    let bytes = payload.as_bytes();
+    if bytes.iter().any(|&b| !(32..127).contains(&b)) {
+        return Err(BarcodeError::UnsupportedCharacter);
+    }
+
+    let mut values: heapless::Vec<u8, 128> = heapless::Vec::new();
+    fn push(values: &mut heapless::Vec<u8, 128>, v: u8) -> Result<(), BarcodeError> {
+        values.push(v).map_err(|_| BarcodeError::TooLong)
+    }
+
+    // Code set C packs digit pairs, so switch to it for runs long enough to pay for the shift.
+    let leading = digit_run(bytes);
+    let mut in_c = leading >= 4 || (leading == bytes.len() && leading == 2);
+    push(&mut values, if in_c { CODE128_START_C } else { CODE128_START_B })?;
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = digit_run(&bytes[i..]);
+        if in_c {
+            if run >= 2 {
+                push(&mut values, (bytes[i] - b'0') * 10 + (bytes[i + 1] - b'0'))?;
+                i += 2;
+                continue;
+            }
+            push(&mut values, CODE128_CODE_B)?;
+            in_c = false;
+        } else if run >= 6 || (run >= 4 && run == bytes.len() - i) {
+            // Keep an odd leading digit in set B so the rest pairs up.
+            if run % 2 == 1 {
+                push(&mut values, bytes[i] - 32)?;
+                i += 1;
+            }
+            push(&mut values, CODE128_CODE_C)?;
+            in_c = true;
+            continue;
+        }
+        push(&mut values, bytes[i] - 32)?;
+        i += 1;
+    }
+
+    let checksum = values
+        .iter()
+        .enumerate()
+        .fold(0u32, |acc, (pos, &v)| acc + v as u32 * (pos as u32).max(1))
+        % 103;
+    push(&mut values, checksum as u8)?;
+
+    let width = values.len() * 11 + 13;
+    if width > MAX_MODULES {
+        return Err(BarcodeError::TooLong);
+    }
+
+    let mut symbol = Symbol::new(width, 1);
+    let mut x = 0;
+    for &value in values.iter() {
+        let pattern = CODE128_PATTERNS[value as usize];
+        for bit in (0..11).rev() {
+            symbol.set(x, 0, pattern & (1 << bit) != 0);
+            x += 1;
+        }
+    }
+    for bit in (0..13).rev() {
+        symbol.set(x, 0, CODE128_STOP & (1 << bit) != 0);
+        x += 1;
+    }
+
+    Ok(symbol)
+}
+
+// ---------------------------------------------------------------------------
+// Reed-Solomon over GF(2^m), shared by Data Matrix and Aztec
+// ---------------------------------------------------------------------------
+
+struct Galois {
+    size: usize,
+    exp: [u16; 512],
+    log: [u16; 256],
+}
+
+impl Galois {
+    fn new(primitive: u16, size: usize) -> Self {
+        let mut gf = Galois { size, exp: [0; 512], log: [0; 256] };
+        let mut x: u16 = 1;
+        for i in 0..size {
+            gf.exp[i] = x;
+            x <<= 1;
+            if x as usize >= size {
+                x ^= primitive;
+            }
+        }
+        for i in 0..size - 1 {
+            gf.log[gf.exp[i] as usize] = i as u16;
+        }
+        gf
+    }
+
+    fn mul(&self, a: u16, b: u16) -> u16 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        let l = (self.log[a as usize] as usize + self.log[b as usize] as usize) % (self.size - 1);
+        self.exp[l]
+    }
+
+    // Appends `ecc_len` check words to the first `data_len` words of `words` (generator base 1).
+    fn encode(&self, words: &mut [u16], data_len: usize, ecc_len: usize) {
+        let mut generator = [0u16; 128];
+        generator[0] = 1;
+        for i in 1..=ecc_len {
+            let root = self.exp[i % (self.size - 1)];
+            for j in (1..=i).rev() {
+                generator[j] = generator[j - 1] ^ self.mul(generator[j], root);
+            }
+            generator[0] = self.mul(generator[0], root);
+        }
+
+        let mut remainder = [0u16; 128];
+        for &word in words[..data_len].iter() {
+            let factor = word ^ remainder[ecc_len - 1];
+            for j in (1..ecc_len).rev() {
+                remainder[j] = remainder[j - 1] ^ self.mul(factor, generator[j]);
+            }
+            remainder[0] = self.mul(factor, generator[0]);
+        }
+
+        for i in 0..ecc_len {
+            words[data_len + i] = remainder[ecc_len - 1 - i];
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Data Matrix (ECC 200, square symbols with a single Reed-Solomon block)
+// ---------------------------------------------------------------------------
+
+struct DataMatrixSize {
+    size: usize,
+    region: usize,
+    regions: usize,
+    data: usize,
+    ecc: usize,
+}
+
+const DATAMATRIX_SIZES: [DataMatrixSize; 14] = [
+    DataMatrixSize { size: 10, region: 8, regions: 1, data: 3, ecc: 5 },
+    DataMatrixSize { size: 12, region: 10, regions: 1, data: 5, ecc: 7 },
+    DataMatrixSize { size: 14, region: 12, regions: 1, data: 8, ecc: 10 },
+    DataMatrixSize { size: 16, region: 14, regions: 1, data: 12, ecc: 12 },
+    DataMatrixSize { size: 18, region: 16, regions: 1, data: 18, ecc: 14 },
+    DataMatrixSize { size: 20, region: 18, regions: 1, data: 22, ecc: 18 },
+    DataMatrixSize { size: 22, region: 20, regions: 1, data: 30, ecc: 20 },
+    DataMatrixSize { size: 24, region: 22, regions: 1, data: 36, ecc: 24 },
+    DataMatrixSize { size: 26, region: 24, regions: 1, data: 44, ecc: 28 },
+    DataMatrixSize { size: 32, region: 14, regions: 2, data: 62, ecc: 36 },
+    DataMatrixSize { size: 36, region: 16, regions: 2, data: 86, ecc: 42 },
+    DataMatrixSize { size: 40, region: 18, regions: 2, data: 114, ecc: 48 },
+    DataMatrixSize { size: 44, region: 20, regions: 2, data: 144, ecc: 56 },
+    DataMatrixSize { size: 48, region: 22, regions: 2, data: 174, ecc: 68 },
+];
+
+// Placement state for the ECC 200 "utah" module layout (ISO/IEC 16022 Annex F).
+struct Placement<'a> {
+    rows: usize,
+    cols: usize,
+    codewords: &'a [u16],
+    visited: Symbol,
+    dark: Symbol,
+}
+
+impl<'a> Placement<'a> {
+    fn module(&mut self, mut row: i32, mut col: i32, chr: usize, bit: u32) {
+        let (rows, cols) = (self.rows as i32, self.cols as i32);
+        if row < 0 {
+            row += rows;
+            col += 4 - ((rows + 4) % 8);
+        }
+        if col < 0 {
+            col += cols;
+            row += 4 - ((cols + 4) % 8);
+        }
+        let (r, c) = (row as usize, col as usize);
+        self.visited.set(c, r, true);
+        self.dark.set(c, r, self.codewords[chr] & (1 << (8 - bit)) != 0);
+    }
+
+    fn utah(&mut self, row: i32, col: i32, chr: usize) {
+        self.module(row - 2, col - 2, chr, 1);
+        self.module(row - 2, col - 1, chr, 2);
+        self.module(row - 1, col - 2, chr, 3);
+        self.module(row - 1, col - 1, chr, 4);
+        self.module(row - 1, col, chr, 5);
+        self.module(row, col - 2, chr, 6);
+        self.module(row, col - 1, chr, 7);
+        self.module(row, col, chr, 8);
+    }
+
+    fn corner(&mut self, positions: [(i32, i32); 8], chr: usize) {
+        for (bit, (row, col)) in positions.iter().enumerate() {
+            self.module(*row, *col, chr, bit as u32 + 1);
+        }
+    }
+
+    fn place(&mut self) {
+        let (rows, cols) = (self.rows as i32, self.cols as i32);
+        let (mut row, mut col) = (4i32, 0i32);
+        let mut chr = 0;
+
+        loop {
+            if row == rows && col == 0 {
+                self.corner([(rows - 1, 0), (rows - 1, 1), (rows - 1, 2), (0, cols - 2),
+                             (0, cols - 1), (1, cols - 1), (2, cols - 1), (3, cols - 1)], chr);
+                chr += 1;
+            }
+            if row == rows - 2 && col == 0 && cols % 4 != 0 {
+                self.corner([(rows - 3, 0), (rows - 2, 0), (rows - 1, 0), (0, cols - 4),
+                             (0, cols - 3), (0, cols - 2), (0, cols - 1), (1, cols - 1)], chr);
+                chr += 1;
+            }
+            if row == rows - 2 && col == 0 && cols % 8 == 4 {
+                self.corner([(rows - 3, 0), (rows - 2, 0), (rows - 1, 0), (0, cols - 2),
+                             (0, cols - 1), (1, cols - 1), (2, cols - 1), (3, cols - 1)], chr);
+                chr += 1;
+            }
+            if row == rows + 4 && col == 2 && cols % 8 == 0 {
+                self.corner([(rows - 1, 0), (rows - 1, cols - 1), (0, cols - 3), (0, cols - 2),
+                             (0, cols - 1), (1, cols - 3), (1, cols - 2), (1, cols - 1)], chr);
+                chr += 1;
+            }
+
+            // Sweep up and to the right...
+            loop {
+                if row < rows && col >= 0 && !self.visited.get(col as usize, row as usize) {
+                    self.utah(row, col, chr);
+                    chr += 1;
+                }
+                row -= 2;
+                col += 2;
+                if !(row >= 0 && col < cols) {
+                    break;
+                }
+            }
+            row += 1;
+            col += 3;
+
+            // ...then down and to the left.
+            loop {
+                if row >= 0 && col < cols && !self.visited.get(col as usize, row as usize) {
+                    self.utah(row, col, chr);
+                    chr += 1;
+                }
+                row += 2;
+                col -= 2;
+                if !(row < rows && col >= 0) {
+                    break;
+                }
+            }
+            row += 3;
+            col += 1;
+
+            if !(row < rows || col < cols) {
+                break;
+            }
+        }
+
+        // Fixed pattern in the bottom-right corner when it was left untouched.
+        let (r, c) = (self.rows - 1, self.cols - 1);
+        if !self.visited.get(c, r) {
+            self.dark.set(c, r, true);
+            self.dark.set(c - 1, r - 1, true);
+        }
+    }
+}
+
+fn encode_datamatrix(payload: &[u8]) -> Result<Symbol, BarcodeError> {
+    // ASCII encodation: digit pairs share a codeword, bytes above 127 need an Upper Shift.
+    let mut codewords = [0u16; 242];
+    let mut len = 0;
+    let mut i = 0;
+    while i < payload.len() {
+        if len + 2 > codewords.len() {
+            return Err(BarcodeError::TooLong);
+        }
+        let b = payload[i];
+        if b.is_ascii_digit() && i + 1 < payload.len() && payload[i + 1].is_ascii_digit() {
+            codewords[len] = 130 + ((b - b'0') * 10 + (payload[i + 1] - b'0')) as u16;
+            i += 2;
+        } else if b < 128 {
+            codewords[len] = b as u16 + 1;
+            i += 1;
+        } else {
+            codewords[len] = 235;
+            len += 1;
+            codewords[len] = (b - 127) as u16;
+            i += 1;
+        }
+        len += 1;
+    }
+
+    let size = DATAMATRIX_SIZES
+        .iter()
+        .find(|s| s.data >= len)
+        .ok_or(BarcodeError::TooLong)?;
+
+    // Pad: the first pad is 129, the rest are scrambled by position.
+    for (pos, codeword) in codewords.iter_mut().enumerate().take(size.data).skip(len) {
+        *codeword = if pos == len {
+            129
+        } else {
+            let pseudo = 129 + ((149 * (pos + 1)) % 253 + 1);
+            (if pseudo > 254 { pseudo - 254 } else { pseudo }) as u16
+        };
+    }
+
+    let gf = Galois::new(0x12d, 256);
+    gf.encode(&mut codewords, size.data, size.ecc);
+
+    let mapping = size.region * size.regions;
+    let mut placement = Placement {
+        rows: mapping,
+        cols: mapping,
+        codewords: &codewords,
+        visited: Symbol::new(mapping, mapping),
+        dark: Symbol::new(mapping, mapping),
+    };
+    placement.place();
+
+    // Wrap each data region in its finder (solid left/bottom) and clock track (dotted top/right).
+    let block = size.region + 2;
+    let mut symbol = Symbol::new(size.size, size.size);
+    for y in 0..size.size {
+        for x in 0..size.size {
+            let (bx, by) = (x % block, y % block);
+            let dark = if bx == 0 || by == block - 1 {
+                true
+            } else if by == 0 {
+                x % 2 == 0
+            } else if bx == block - 1 {
+                y % 2 == 1
+            } else {
+                let r = (y / block) * size.region + by - 1;
+                let c = (x / block) * size.region + bx - 1;
+                placement.dark.get(c, r)
+            };
+            symbol.set(x, y, dark);
+        }
+    }
+
+    Ok(symbol)
+}
+
+// ---------------------------------------------------------------------------
+// Aztec (compact symbols, 1 to 4 layers)
+// ---------------------------------------------------------------------------
+
+// Room for a 4-layer compact symbol: 608 bits.
+const AZTEC_MAX_BITS: usize = 640;
+
+struct Bits {
+    data: [u8; AZTEC_MAX_BITS / 8],
+    len: usize,
+}
+
+impl Bits {
+    fn new() -> Self {
+        Bits { data: [0; AZTEC_MAX_BITS / 8], len: 0 }
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self.data[i / 8] & (0x80 >> (i % 8)) != 0
+    }
+
+    fn push(&mut self, value: u32, count: usize) -> Result<(), BarcodeError> {
+        if self.len + count > AZTEC_MAX_BITS {
+            return Err(BarcodeError::TooLong);
+        }
+        for shift in (0..count).rev() {
+            if value & (1 << shift) != 0 {
+                self.data[self.len / 8] |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+fn aztec_layer_bits(layers: usize) -> usize {
+    (88 + 16 * layers) * layers
+}
+
+// Splits `bits` into `word_size` words, inserting a stuffing bit wherever a word would be all
+// zeros or all ones, and pads the tail with ones.
+fn aztec_stuff_bits(bits: &Bits, word_size: usize) -> Result<Bits, BarcodeError> {
+    let mut out = Bits::new();
+    let mask = (1u32 << word_size) - 2;
+    let mut i = 0;
+    while i < bits.len {
+        let mut word = 0u32;
+        for j in 0..word_size {
+            if i + j >= bits.len || bits.get(i + j) {
+                word |= 1 << (word_size - 1 - j);
+            }
+        }
+        if word & mask == mask {
+            out.push(word & mask, word_size)?;
+            i += word_size - 1;
+        } else if word & mask == 0 {
+            out.push(word | 1, word_size)?;
+            i += word_size - 1;
+        } else {
+            out.push(word, word_size)?;
+            i += word_size;
+        }
+    }
+    Ok(out)
+}
+
+fn aztec_check_words(bits: &Bits, total_bits: usize, word_size: usize) -> Result<Bits, BarcodeError> {
+    let gf = match word_size {
+        4 => Galois::new(0x13, 16),
+        6 => Galois::new(0x43, 64),
+        _ => Galois::new(0x12d, 256),
+    };
+
+    let data_words = bits.len / word_size;
+    let total_words = total_bits / word_size;
+    let mut words = [0u16; 128];
+    for (w, word) in words.iter_mut().enumerate().take(data_words) {
+        for j in 0..word_size {
+            if bits.get(w * word_size + j) {
+                *word |= 1 << (word_size - 1 - j);
+            }
+        }
+    }
+    gf.encode(&mut words, data_words, total_words - data_words);
+
+    let mut out = Bits::new();
+    out.push(0, total_bits % word_size)?;
+    for &word in words[..total_words].iter() {
+        out.push(word as u32, word_size)?;
+    }
+    Ok(out)
+}
+
+fn encode_aztec(payload: &[u8]) -> Result<Symbol, BarcodeError> {
+    // Everything goes through a single Binary Shift from Upper mode.
+    let mut bits = Bits::new();
+    bits.push(31, 5)?;
+    if payload.len() <= 31 {
+        bits.push(payload.len() as u32, 5)?;
+    } else {
+        bits.push(0, 5)?;
+        bits.push(payload.len() as u32 - 31, 11)?;
+    }
+    for &b in payload {
+        bits.push(b as u32, 8)?;
+    }
+
+    // Aim for roughly a third of the symbol as error correction.
+    let ecc_bits = bits.len * 33 / 100 + 11;
+
+    let mut chosen = None;
+    for layers in 1..=4 {
+        let total_bits = aztec_layer_bits(layers);
+        if bits.len + ecc_bits > total_bits {
+            continue;
+        }
+        let word_size = if layers <= 2 { 6 } else { 8 };
+        let stuffed = aztec_stuff_bits(&bits, word_size)?;
+        if stuffed.len > word_size * 64 {
+            continue;
+        }
+        if stuffed.len + ecc_bits <= total_bits - total_bits % word_size {
+            chosen = Some((layers, word_size, stuffed));
+            break;
+        }
+    }
+    let (layers, word_size, stuffed) = chosen.ok_or(BarcodeError::TooLong)?;
+
+    let message = aztec_check_words(&stuffed, aztec_layer_bits(layers), word_size)?;
+
+    let mut mode = Bits::new();
+    mode.push(layers as u32 - 1, 2)?;
+    mode.push((stuffed.len / word_size) as u32 - 1, 6)?;
+    let mode = aztec_check_words(&mode, 28, 4)?;
+
+    let size = 11 + layers * 4;
+    let mut symbol = Symbol::new(size, size);
+
+    // Data layers spiral inwards from the outside edge, two modules thick.
+    let mut offset = 0;
+    for i in 0..layers {
+        let row_size = (layers - i) * 4 + 9;
+        for j in 0..row_size {
+            let column = j * 2;
+            for k in 0..2 {
+                if message.get(offset + column + k) {
+                    symbol.set(i * 2 + k, i * 2 + j, true);
+                }
+                if message.get(offset + row_size * 2 + column + k) {
+                    symbol.set(i * 2 + j, size - 1 - i * 2 - k, true);
+                }
+                if message.get(offset + row_size * 4 + column + k) {
+                    symbol.set(size - 1 - i * 2 - k, size - 1 - i * 2 - j, true);
+                }
+                if message.get(offset + row_size * 6 + column + k) {
+                    symbol.set(size - 1 - i * 2 - j, i * 2 + k, true);
+                }
+            }
+        }
+        offset += row_size * 8;
+    }
+
+    // Mode message around the bullseye.
+    let center = size / 2;
+    for i in 0..7 {
+        let o = center - 3 + i;
+        if mode.get(i) {
+            symbol.set(o, center - 5, true);
+        }
+        if mode.get(i + 7) {
+            symbol.set(center + 5, o, true);
+        }
+        if mode.get(20 - i) {
+            symbol.set(o, center + 5, true);
+        }
+        if mode.get(27 - i) {
+            symbol.set(center - 5, o, true);
+        }
+    }
+
+    // Bullseye rings and orientation marks.
+    for ring in (0..5).step_by(2) {
+        for j in center - ring..=center + ring {
+            symbol.set(j, center - ring, true);
+            symbol.set(j, center + ring, true);
+            symbol.set(center - ring, j, true);
+            symbol.set(center + ring, j, true);
+        }
+    }
+    symbol.set(center - 5, center - 5, true);
+    symbol.set(center - 4, center - 5, true);
+    symbol.set(center - 5, center - 4, true);
+    symbol.set(center + 5, center - 5, true);
+    symbol.set(center + 5, center - 4, true);
+    symbol.set(center + 5, center + 4, true);
+
+    Ok(symbol)
+}
+
+// ---------------------------------------------------------------------------
+// QR
+// ---------------------------------------------------------------------------
+
+fn encode_qr(payload: &str) -> Result<Symbol, BarcodeError> {
+    const BUFFER_LEN: usize = Version::new(MAX_QR_VERSION).buffer_len();
+    let mut temp = [0u8; BUFFER_LEN];
+    let mut out = [0u8; BUFFER_LEN];
+
+    // Medium error correction, bumped up for free when the payload leaves room.
+    let qr = QrCode::encode_text(
+        payload,
+        &mut temp,
+        &mut out,
+        QrCodeEcc::Medium,
+        Version::MIN,
+        Version::new(MAX_QR_VERSION),
+        None,
+        true,
+    )
+    .map_err(|_| BarcodeError::TooLong)?;
+
+    let size = qr.size() as usize;
+    let mut symbol = Symbol::new(size, size);
+    for y in 0..size {
+        for x in 0..size {
+            symbol.set(x, y, qr.get_module(x as i32, y as i32));
+        }
+    }
+    Ok(symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The symbols below decode to their payloads with ZXing (the rxing port), and were copied
+    // from this encoder's output: dark modules are `#`.
+    fn rows(symbol: &Symbol) -> Vec<String> {
+        (0..symbol.height())
+            .map(|y| (0..symbol.width()).map(|x| if symbol.get(x, y) { '#' } else { '.' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn code128() {
+        // Start B, "H", "i", check value 84 and stop.
+        let symbol = encode(Symbology::Code128, "Hi").unwrap();
+        assert_eq!(rows(&symbol), ["##.#..#....##...#.#...#....##.#..#..####.#..##...###.#.##"]);
+        // Digit pairs in code set C: start C, 12, 34, check value 82 and stop.
+        let symbol = encode(Symbology::Code128, "1234").unwrap();
+        assert_eq!(rows(&symbol), ["##.#..###..#.##..###..#...#.##...#..#..####.##...###.#.##"]);
+        assert_eq!(encode(Symbology::Code128, "LYNIX-2023-0042").unwrap().width(), 189);
+
+        assert_eq!(encode(Symbology::Code128, "café").err(), Some(BarcodeError::UnsupportedCharacter));
+    }
+
+    #[test]
+    fn datamatrix() {
+        let symbol = encode(Symbology::DataMatrix, "lynix").unwrap();
+        assert_eq!(rows(&symbol), [
+            "#.#.#.#.#.#.",
+            "#.#####.#.##",
+            "####.#.#.#..",
+            "#.#.####.#.#",
+            "###...#.#...",
+            "##.##..##..#",
+            "#.....#...#.",
+            "###..#######",
+            "##.#.#..#.#.",
+            "#.##.#..##.#",
+            "##.#.#....#.",
+            "############",
+        ]);
+        assert_eq!(encode(Symbology::DataMatrix, "CCNB-CYBER-4921").unwrap().width(), 18);
+    }
+
+    #[test]
+    fn aztec() {
+        let symbol = encode(Symbology::Aztec, "lynix").unwrap();
+        assert_eq!(rows(&symbol), [
+            "......#####...#",
+            "##...##..#.#.##",
+            "####....#...#..",
+            "#.#############",
+            "...#.......#..#",
+            "#..#.#####.#..#",
+            "#..#.#...#.##.#",
+            "####.#.#.#.###.",
+            ".###.#...#.###.",
+            "#.##.#####.#...",
+            "...#.......###.",
+            "##.############",
+            "##...#.#..#..##",
+            "..###.##..##.##",
+            ".##.#.#..###.#.",
+        ]);
+        assert_eq!(encode(Symbology::Aztec, "MECARD:N:Lynix;URL:https://lynix.ca;;").unwrap().width(), 27);
+    }
+
+    #[test]
+    fn qr() {
+        let symbol = encode(Symbology::Qr, "lynix").unwrap();
+        assert_eq!(rows(&symbol), [
+            "#######..#..#.#######",
+            "#.....#.##.##.#.....#",
+            "#.###.#.####..#.###.#",
+            "#.###.#.###.#.#.###.#",
+            "#.###.#.#...#.#.###.#",
+            "#.....#.##..#.#.....#",
+            "#######.#.#.#.#######",
+            ".........####........",
+            "..#..####.#.##.#####.",
+            "..###..#.##..###..###",
+            ".##...##.#....###.#.#",
+            "#...##.#..###..#.#...",
+            "#...#.#.##.##..###...",
+            "........###..#...##.#",
+            "#######.######...#..#",
+            "#.....#.##....#.##.#.",
+            "#.###.#....#.....#..#",
+            "#.###.#..#.##.....#..",
+            "#.###.#.##.######.###",
+            "#.....#..##.###..#...",
+            "#######..#..#..#....#",
+        ]);
+    }
+
+    #[test]
+    fn too_long() {
+        let long = "x".repeat(400);
+        for symbology in [Symbology::Code128, Symbology::DataMatrix, Symbology::Aztec, Symbology::Qr] {
+            assert_eq!(encode(symbology, &long).err(), Some(BarcodeError::TooLong));
+        }
+    }
+}
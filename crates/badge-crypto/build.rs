@@ -0,0 +1,57 @@
+//! Packs `data/eff_large_wordlist.txt` into the compact form `passgen` decodes.
+//!
+//! The list is sorted, so each word is stored as how many letters it shares with the previous
+//! one (4 bits), how many follow (4 bits) and those letters at 5 bits each, a-z then `-`. The
+//! whole thing is one bitstream, most significant bit first, about a fifth of the text's size.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const WORDLIST: &str = "data/eff_large_wordlist.txt";
+// Must match `passgen::MAX_WORD`, the buffer words are decoded into.
+const MAX_WORD: usize = 9;
+
+struct Bits {
+    bytes: Vec<u8>,
+    used: usize,
+}
+
+impl Bits {
+    fn push(&mut self, value: u32, count: usize) {
+        for bit in (0..count).rev() {
+            if self.used & 7 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> bit) & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.used & 7);
+            }
+            self.used += 1;
+        }
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", WORDLIST);
+    let text = fs::read_to_string(WORDLIST).expect("reading the wordlist");
+
+    let mut bits = Bits { bytes: Vec::new(), used: 0 };
+    let mut previous = "";
+    for line in text.lines() {
+        // "11111<tab>abacus"
+        let word = line.split('\t').nth(1).expect("wordlist lines are dice rolls, a tab and a word");
+        assert!(word.len() <= MAX_WORD && word.bytes().all(|c| c.is_ascii_lowercase() || c == b'-'), "can't pack {:?}", word);
+        assert!(previous < word, "the wordlist must be sorted");
+
+        let shared = word.bytes().zip(previous.bytes()).take_while(|(a, b)| a == b).count();
+        bits.push(shared as u32, 4);
+        bits.push((word.len() - shared) as u32, 4);
+        for c in word[shared..].bytes() {
+            bits.push(if c == b'-' { 26 } else { (c - b'a') as u32 }, 5);
+        }
+        previous = word;
+    }
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("wordlist.bin");
+    fs::write(out, bits.bytes).expect("writing the packed wordlist");
+}
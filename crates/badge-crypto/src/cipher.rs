@@ -0,0 +1,413 @@
+//! Classical ciphers and encodings, chained into recipes like a tiny CyberChef.
+//!
+//! A recipe is steps separated by `|`, run left to right, each one's output the next one's input:
+//!
+//! ```text
+//! rot13|to-base64
+//! from-hex|xor:secret|to-base64
+//! vigenere-decode:LEMON
+//! ```
+//!
+//! Steps that need a key take it after a `:`. Everything works on bytes, so steps like `xor` can
+//! produce binary for a later `to-hex` or `to-base64`. Letters only shift within A-Z and a-z.
+
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    UnknownStep,
+    /// The step needs a key after `:`, or the key has nothing it can use.
+    MissingKey,
+    /// `rot:N` with N that isn't a number.
+    BadShift,
+    /// The result doesn't fit in the buffer.
+    TooLong,
+    /// Not valid input for a decoder.
+    InvalidInput,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::UnknownStep => "unknown step",
+            Error::MissingKey => "step needs a key",
+            Error::BadShift => "rot takes a number, like rot:3",
+            Error::TooLong => "result too long",
+            Error::InvalidInput => "input can't be decoded",
+        })
+    }
+}
+
+/// Step names, in the order a picker offers them. Those ending in `:` take a key.
+pub const STEPS: [&str; 15] = [
+    "rot13",
+    "rot:",
+    "atbash",
+    "vigenere-encode:",
+    "vigenere-decode:",
+    "xor:",
+    "to-base64",
+    "from-base64",
+    "to-base32",
+    "from-base32",
+    "to-hex",
+    "from-hex",
+    "url-encode",
+    "url-decode",
+    "reverse",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform<'a> {
+    /// Caesar shift of letters by 0 to 25.
+    Rot(u8),
+    Atbash,
+    Vigenere { key: &'a [u8], decrypt: bool },
+    Xor(&'a [u8]),
+    ToBase64,
+    FromBase64,
+    ToBase32,
+    FromBase32,
+    ToHex,
+    FromHex,
+    UrlEncode,
+    UrlDecode,
+    Reverse,
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+impl<'a> Transform<'a> {
+    pub fn parse(step: &'a str) -> Result<Transform<'a>, Error> {
+        let (name, key) = match step.split_once(':') {
+            Some((name, key)) => (name, Some(key)),
+            None => (step, None),
+        };
+        let required_key = || key.filter(|key| !key.is_empty()).map(str::as_bytes).ok_or(Error::MissingKey);
+
+        Ok(match name {
+            "rot13" => Transform::Rot(13),
+            "rot" => {
+                let shift: u32 = key.and_then(|key| key.parse().ok()).ok_or(Error::BadShift)?;
+                Transform::Rot((shift % 26) as u8)
+            }
+            "atbash" => Transform::Atbash,
+            "vigenere-encode" | "vigenere-decode" => {
+                let key = required_key()?;
+                if !key.iter().any(u8::is_ascii_alphabetic) {
+                    return Err(Error::MissingKey);
+                }
+                Transform::Vigenere { key, decrypt: name == "vigenere-decode" }
+            }
+            "xor" => Transform::Xor(required_key()?),
+            "to-base64" => Transform::ToBase64,
+            "from-base64" => Transform::FromBase64,
+            "to-base32" => Transform::ToBase32,
+            "from-base32" => Transform::FromBase32,
+            "to-hex" => Transform::ToHex,
+            "from-hex" => Transform::FromHex,
+            "url-encode" => Transform::UrlEncode,
+            "url-decode" => Transform::UrlDecode,
+            "reverse" => Transform::Reverse,
+            _ => return Err(Error::UnknownStep),
+        })
+    }
+
+    /// Writes the transformed `input` to `output`, returns its length.
+    pub fn apply(&self, input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+        let mut out = Writer { buffer: output, len: 0 };
+        match *self {
+            Transform::Rot(shift) => input.iter().try_for_each(|&byte| out.push(shift_letter(byte, shift)))?,
+            Transform::Atbash => input.iter().try_for_each(|&byte| {
+                out.push(match byte {
+                    b'a'..=b'z' => b'z' - (byte - b'a'),
+                    b'A'..=b'Z' => b'Z' - (byte - b'A'),
+                    _ => byte,
+                })
+            })?,
+            Transform::Vigenere { key, decrypt } => {
+                // The key advances only on letters, in the text and in the key.
+                let mut shifts = key.iter().filter(|byte| byte.is_ascii_alphabetic()).map(|byte| byte.to_ascii_lowercase() - b'a').cycle();
+                for &byte in input {
+                    if byte.is_ascii_alphabetic() {
+                        let shift = shifts.next().unwrap_or(0);
+                        out.push(shift_letter(byte, if decrypt { (26 - shift) % 26 } else { shift }))?;
+                    } else {
+                        out.push(byte)?;
+                    }
+                }
+            }
+            Transform::Xor(key) => input.iter().zip(key.iter().cycle()).try_for_each(|(&byte, &key)| out.push(byte ^ key))?,
+            Transform::ToBase64 => encode_bits(input, BASE64, 6, 4, &mut out)?,
+            Transform::FromBase64 => decode_bits(input, 6, |c| BASE64.iter().position(|&b| b == c), &mut out)?,
+            Transform::ToBase32 => encode_bits(input, BASE32, 5, 8, &mut out)?,
+            Transform::FromBase32 => {
+                decode_bits(input, 5, |c| BASE32.iter().position(|&b| b == c.to_ascii_uppercase()), &mut out)?
+            }
+            Transform::ToHex => encode_bits(input, HEX, 4, 1, &mut out)?,
+            Transform::FromHex => {
+                let digits = input.iter().filter(|byte| !byte.is_ascii_whitespace()).count();
+                if digits % 2 != 0 {
+                    return Err(Error::InvalidInput);
+                }
+                decode_bits(input, 4, |c| (c as char).to_digit(16).map(|digit| digit as usize), &mut out)?
+            }
+            Transform::UrlEncode => input.iter().try_for_each(|&byte| {
+                if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+                    out.push(byte)
+                } else {
+                    out.push(b'%')?;
+                    out.push(HEX[(byte >> 4) as usize].to_ascii_uppercase())?;
+                    out.push(HEX[(byte & 0xf) as usize].to_ascii_uppercase())
+                }
+            })?,
+            Transform::UrlDecode => {
+                let mut bytes = input.iter();
+                while let Some(&byte) = bytes.next() {
+                    match byte {
+                        b'%' => {
+                            let mut digit = || bytes.next().and_then(|&c| (c as char).to_digit(16)).ok_or(Error::InvalidInput);
+                            let high = digit()?;
+                            let low = digit()?;
+                            out.push((high * 16 + low) as u8)?;
+                        }
+                        b'+' => out.push(b' ')?,
+                        _ => out.push(byte)?,
+                    }
+                }
+            }
+            Transform::Reverse => input.iter().rev().try_for_each(|&byte| out.push(byte))?,
+        }
+        Ok(out.len)
+    }
+}
+
+/// Runs `recipe` over `input` using the two buffers, returns the result.
+pub fn bake<'b, const N: usize>(recipe: &str, input: &[u8], buffers: &'b mut [[u8; N]; 2]) -> Result<&'b [u8], Error> {
+    let [from, to] = buffers;
+    let mut len = input.len();
+    from.get_mut(..len).ok_or(Error::TooLong)?.copy_from_slice(input);
+
+    let (mut from, mut to) = (from, to);
+    for step in recipe.split('|').map(str::trim).filter(|step| !step.is_empty()) {
+        len = Transform::parse(step)?.apply(&from[..len], to)?;
+        core::mem::swap(&mut from, &mut to);
+    }
+    Ok(&from[..len])
+}
+
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn push(&mut self, byte: u8) -> Result<(), Error> {
+        *self.buffer.get_mut(self.len).ok_or(Error::TooLong)? = byte;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+fn shift_letter(byte: u8, shift: u8) -> u8 {
+    match byte {
+        b'a'..=b'z' => b'a' + (byte - b'a' + shift) % 26,
+        b'A'..=b'Z' => b'A' + (byte - b'A' + shift) % 26,
+        _ => byte,
+    }
+}
+
+// Base64, base32 and hex: `bits` per character, padded with `=` to a multiple of `group`.
+fn encode_bits(input: &[u8], alphabet: &[u8], bits: u32, group: usize, out: &mut Writer) -> Result<(), Error> {
+    let mut buffer = 0u32;
+    let mut held = 0;
+    let mut written = 0;
+    for &byte in input {
+        buffer = (buffer << 8) | byte as u32;
+        held += 8;
+        while held >= bits {
+            held -= bits;
+            out.push(alphabet[((buffer >> held) & ((1 << bits) - 1)) as usize])?;
+            written += 1;
+        }
+    }
+    if held > 0 {
+        out.push(alphabet[((buffer << (bits - held)) & ((1 << bits) - 1)) as usize])?;
+        written += 1;
+    }
+    while written % group != 0 {
+        out.push(b'=')?;
+        written += 1;
+    }
+    Ok(())
+}
+
+// Reverses `encode_bits`. Whitespace is skipped and padding is optional, but leftover bits have
+// to be zero.
+fn decode_bits(input: &[u8], bits: u32, value: impl Fn(u8) -> Option<usize>, out: &mut Writer) -> Result<(), Error> {
+    let mut buffer = 0u32;
+    let mut held = 0;
+    let data = input.iter().filter(|byte| !byte.is_ascii_whitespace());
+    let mut padding = false;
+    for &c in data {
+        if c == b'=' {
+            padding = true;
+            continue;
+        }
+        if padding {
+            return Err(Error::InvalidInput);
+        }
+        let value = value(c).ok_or(Error::InvalidInput)?;
+        buffer = (buffer << bits) | value as u32;
+        held += bits;
+        if held >= 8 {
+            held -= 8;
+            out.push((buffer >> held) as u8)?;
+        }
+        buffer &= (1 << held) - 1;
+    }
+    if buffer != 0 || held >= bits {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(step: &str, input: &str) -> Result<std::string::String, Error> {
+        let mut output = [0u8; 128];
+        let len = Transform::parse(step)?.apply(input.as_bytes(), &mut output)?;
+        Ok(std::string::String::from_utf8(output[..len].to_vec()).unwrap())
+    }
+
+    fn round_trip(encode: &str, decode: &str, input: &str) {
+        let encoded = run(encode, input).unwrap();
+        assert_eq!(run(decode, &encoded).unwrap(), input, "{} then {}", encode, decode);
+    }
+
+    #[test]
+    fn rot() {
+        assert_eq!(run("rot13", "Hello, World!").unwrap(), "Uryyb, Jbeyq!");
+        assert_eq!(run("rot:3", "xyz ABC").unwrap(), "abc DEF");
+        assert_eq!(run("rot:29", "abc").unwrap(), "def");
+        assert_eq!(run("rot:0", "abc").unwrap(), "abc");
+        assert_eq!(run("rot:x", "abc"), Err(Error::BadShift));
+        assert_eq!(run("rot", "abc"), Err(Error::BadShift));
+        round_trip("rot:7", "rot:19", "The quick brown fox");
+    }
+
+    #[test]
+    fn atbash() {
+        assert_eq!(run("atbash", "Hello, World!").unwrap(), "Svool, Dliow!");
+        round_trip("atbash", "atbash", "abcxyz ABCXYZ 123");
+    }
+
+    #[test]
+    fn vigenere() {
+        assert_eq!(run("vigenere-encode:LEMON", "ATTACKATDAWN").unwrap(), "LXFOPVEFRNHR");
+        // Case is kept and anything that isn't a letter passes through without using the key.
+        assert_eq!(run("vigenere-encode:lemon", "attack at dawn!").unwrap(), "lxfopv ef rnhr!");
+        assert_eq!(run("vigenere-decode:LEMON", "LXFOPVEFRNHR").unwrap(), "ATTACKATDAWN");
+        assert_eq!(run("vigenere-encode:", "abc"), Err(Error::MissingKey));
+        assert_eq!(run("vigenere-encode:123", "abc"), Err(Error::MissingKey));
+        round_trip("vigenere-encode:Badge", "vigenere-decode:Badge", "Meet me at the CTF table");
+    }
+
+    #[test]
+    fn xor() {
+        assert_eq!(run("xor: ", "ABC").unwrap(), "abc");
+        let mut output = [0u8; 8];
+        let len = Transform::Xor(b"\x01\x02").apply(b"\x00\x00\x00", &mut output).unwrap();
+        assert_eq!(&output[..len], b"\x01\x02\x01");
+        assert_eq!(run("xor", "abc"), Err(Error::MissingKey));
+        round_trip("xor:k", "xor:k", "plain text");
+    }
+
+    // RFC 4648 section 10.
+    #[test]
+    fn base64() {
+        let vectors = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        for (plain, encoded) in vectors {
+            assert_eq!(run("to-base64", plain).unwrap(), encoded);
+            assert_eq!(run("from-base64", encoded).unwrap(), plain);
+        }
+        assert_eq!(run("from-base64", "Zm9vYg").unwrap(), "foob");
+        assert_eq!(run("from-base64", "Zm9v!"), Err(Error::InvalidInput));
+        assert_eq!(run("from-base64", "Zg==Zg=="), Err(Error::InvalidInput));
+    }
+
+    // RFC 4648 section 10.
+    #[test]
+    fn base32() {
+        let vectors = [
+            ("", ""),
+            ("f", "MY======"),
+            ("fo", "MZXQ===="),
+            ("foo", "MZXW6==="),
+            ("foob", "MZXW6YQ="),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI======"),
+        ];
+        for (plain, encoded) in vectors {
+            assert_eq!(run("to-base32", plain).unwrap(), encoded);
+            assert_eq!(run("from-base32", encoded).unwrap(), plain);
+        }
+        // Lower case and no padding, the way authenticator secrets are often written.
+        assert_eq!(run("from-base32", "mzxw6ytboi").unwrap(), "foobar");
+        assert_eq!(run("from-base32", "MZXW1"), Err(Error::InvalidInput));
+    }
+
+    // RFC 4648 section 10.
+    #[test]
+    fn hex() {
+        assert_eq!(run("to-hex", "foobar").unwrap(), "666f6f626172");
+        assert_eq!(run("from-hex", "666F6F626172").unwrap(), "foobar");
+        assert_eq!(run("from-hex", "66 6f 6f").unwrap(), "foo");
+        assert_eq!(run("from-hex", "666"), Err(Error::InvalidInput));
+        assert_eq!(run("from-hex", "zz"), Err(Error::InvalidInput));
+    }
+
+    #[test]
+    fn url() {
+        assert_eq!(run("url-encode", "a b&c/é~").unwrap(), "a%20b%26c%2F%C3%A9~");
+        assert_eq!(run("url-decode", "a%20b%26c%2f%C3%A9+d").unwrap(), "a b&c/é d");
+        assert_eq!(run("url-decode", "100%"), Err(Error::InvalidInput));
+        assert_eq!(run("url-decode", "%G0"), Err(Error::InvalidInput));
+        round_trip("url-encode", "url-decode", "https://lynix.ca/?q=badge & more");
+    }
+
+    #[test]
+    fn reverse() {
+        assert_eq!(run("reverse", "badge").unwrap(), "egdab");
+    }
+
+    #[test]
+    fn every_listed_step_parses() {
+        for step in STEPS {
+            let step = if step.ends_with(':') { std::format!("{}{}", step, if step == "rot:" { "1" } else { "key" }) } else { step.into() };
+            assert!(Transform::parse(&step).is_ok(), "{}", step);
+        }
+        assert_eq!(Transform::parse("rot14:"), Err(Error::UnknownStep));
+    }
+
+    #[test]
+    fn recipes_chain() {
+        let mut buffers = [[0u8; 64]; 2];
+        assert_eq!(bake("rot13|to-base64", b"hello", &mut buffers).unwrap(), b"dXJ5eWI=");
+        assert_eq!(bake("from-base64 | rot13", b"dXJ5eWI=", &mut buffers).unwrap(), b"hello");
+        assert_eq!(bake("xor:key|to-hex|from-hex|xor:key", b"secret", &mut buffers).unwrap(), b"secret");
+        assert_eq!(bake("", b"as is", &mut buffers).unwrap(), b"as is");
+        assert_eq!(bake("rot13|nope", b"x", &mut buffers), Err(Error::UnknownStep));
+    }
+
+    #[test]
+    fn recipes_stop_when_full() {
+        let mut buffers = [[0u8; 8]; 2];
+        assert_eq!(bake("to-hex", b"12345", &mut buffers), Err(Error::TooLong));
+        assert_eq!(bake("", b"123456789", &mut buffers), Err(Error::TooLong));
+    }
+}
@@ -0,0 +1,367 @@
+//! CTAPHID: how FIDO security keys talk over USB HID (CTAP 2.1, section 11.2).
+//!
+//! Messages are split into 64-byte packets. The first starts with the channel, the command and
+//! the length, the rest with the channel and a sequence number:
+//!
+//! ```text
+//! init  channel: u32 BE  command: u8 (top bit set)  length: u16 BE  data: 57 bytes
+//! cont  channel: u32 BE  sequence: u8 (0 to 127)    data: 59 bytes
+//! ```
+//!
+//! A host asks for a channel of its own with INIT on the broadcast channel. `Transport` answers
+//! INIT, PING and WINK itself and hands MSG, U2F requests (see `u2f`), to the badge. It puts one
+//! message together at a time: while one is arriving, other channels are told the badge is busy.
+//! One that stops arriving for longer than `TIMEOUT` is dropped, so a host that went away can't
+//! keep the others waiting.
+
+pub const PACKET_LEN: usize = 64;
+/// Longest message taken. CTAPHID allows 7609 bytes, U2F requests are much shorter.
+pub const MAX_MESSAGE: usize = 1024;
+pub const BROADCAST: u32 = 0xffff_ffff;
+/// Seconds a message can go without its next packet before it's dropped.
+pub const TIMEOUT: u64 = 1;
+
+pub const PING: u8 = 0x81;
+pub const MSG: u8 = 0x83;
+pub const INIT: u8 = 0x86;
+pub const WINK: u8 = 0x88;
+pub const ERROR: u8 = 0xbf;
+
+pub const ERR_INVALID_CMD: u8 = 0x01;
+pub const ERR_INVALID_LEN: u8 = 0x03;
+pub const ERR_INVALID_SEQ: u8 = 0x04;
+pub const ERR_MSG_TIMEOUT: u8 = 0x05;
+pub const ERR_CHANNEL_BUSY: u8 = 0x06;
+pub const ERR_INVALID_CHANNEL: u8 = 0x0b;
+
+const INIT_DATA: usize = PACKET_LEN - 7;
+const CONT_DATA: usize = PACKET_LEN - 5;
+const NONCE_LEN: usize = 8;
+// INIT response: protocol version 2, device version 1.0.0, and WINK is the only capability.
+const VERSIONS: [u8; 5] = [2, 1, 0, 0, 0x01];
+
+/// Something for the badge to do.
+#[derive(Debug, PartialEq)]
+pub enum Event<'a> {
+    /// A U2F request to answer with `reply(channel, MSG, ...)`.
+    Message { channel: u32, data: &'a [u8] },
+    /// The host wants the badge to show which one it is.
+    Wink,
+}
+
+pub struct Transport {
+    // Channels below this have been handed out.
+    next_channel: u32,
+    // Channel a message is arriving on.
+    pending: Option<u32>,
+    // When the last packet of the pending message came.
+    last_packet: u64,
+    command: u8,
+    length: usize,
+    received: usize,
+    sequence: u8,
+    data: [u8; MAX_MESSAGE],
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport { next_channel: 1, pending: None, last_packet: 0, command: 0, length: 0, received: 0, sequence: 0, data: [0; MAX_MESSAGE] }
+    }
+}
+
+/// Sends `data` as `command` on `channel`, split into packets for `send`.
+pub fn reply(channel: u32, command: u8, data: &[u8], mut send: impl FnMut(&[u8; PACKET_LEN])) {
+    let mut packet = [0; PACKET_LEN];
+    packet[..4].copy_from_slice(&channel.to_be_bytes());
+    packet[4] = command;
+    packet[5..7].copy_from_slice(&(data.len() as u16).to_be_bytes());
+    let (first, mut rest) = data.split_at(data.len().min(INIT_DATA));
+    packet[7..7 + first.len()].copy_from_slice(first);
+    send(&packet);
+
+    for sequence in 0.. {
+        if rest.is_empty() {
+            break;
+        }
+        let (chunk, remaining) = rest.split_at(rest.len().min(CONT_DATA));
+        packet[4] = sequence;
+        packet[5..].fill(0);
+        packet[5..5 + chunk.len()].copy_from_slice(chunk);
+        send(&packet);
+        rest = remaining;
+    }
+}
+
+fn error(channel: u32, code: u8, send: impl FnMut(&[u8; PACKET_LEN])) {
+    reply(channel, ERROR, &[code], send);
+}
+
+impl Transport {
+    /// Drops a message whose next packet is overdue at `now`, in seconds, telling its channel
+    /// through `send`. Called by `receive`, and worth calling while nothing arrives.
+    pub fn expire(&mut self, now: u64, send: impl FnMut(&[u8; PACKET_LEN])) {
+        if let Some(channel) = self.pending.filter(|_| now > self.last_packet + TIMEOUT) {
+            self.pending = None;
+            error(channel, ERR_MSG_TIMEOUT, send);
+        }
+    }
+
+    /// Takes a packet from the host at `now`, in seconds. Answers what it can straight away
+    /// through `send`, and returns what the badge has to handle once a message is complete.
+    pub fn receive(
+        &mut self,
+        packet: &[u8; PACKET_LEN],
+        now: u64,
+        mut send: impl FnMut(&[u8; PACKET_LEN]),
+    ) -> Option<Event<'_>> {
+        self.expire(now, &mut send);
+        let channel = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+        let kind = packet[4];
+        let known = channel != 0 && (channel < self.next_channel || channel == BROADCAST);
+
+        if kind & 0x80 != 0 {
+            let length = u16::from_be_bytes([packet[5], packet[6]]) as usize;
+            if !known {
+                error(channel, ERR_INVALID_CHANNEL, send);
+                return None;
+            }
+            if kind == INIT {
+                if length != NONCE_LEN {
+                    error(channel, ERR_INVALID_LEN, send);
+                    return None;
+                }
+                // INIT on a channel of its own gives up on whatever it was sending.
+                if self.pending == Some(channel) {
+                    self.pending = None;
+                }
+                let assigned = if channel == BROADCAST {
+                    self.next_channel += 1;
+                    self.next_channel - 1
+                } else {
+                    channel
+                };
+                let mut response = [0; NONCE_LEN + 4 + VERSIONS.len()];
+                response[..NONCE_LEN].copy_from_slice(&packet[7..7 + NONCE_LEN]);
+                response[NONCE_LEN..NONCE_LEN + 4].copy_from_slice(&assigned.to_be_bytes());
+                response[NONCE_LEN + 4..].copy_from_slice(&VERSIONS);
+                reply(channel, INIT, &response, send);
+                return None;
+            }
+            if channel == BROADCAST {
+                error(channel, ERR_INVALID_CHANNEL, send);
+                return None;
+            }
+            if self.pending.is_some_and(|pending| pending != channel) {
+                error(channel, ERR_CHANNEL_BUSY, send);
+                return None;
+            }
+            if length > MAX_MESSAGE {
+                self.pending = None;
+                error(channel, ERR_INVALID_LEN, send);
+                return None;
+            }
+
+            self.pending = Some(channel);
+            self.last_packet = now;
+            self.command = kind;
+            self.length = length;
+            self.received = length.min(INIT_DATA);
+            self.sequence = 0;
+            self.data[..self.received].copy_from_slice(&packet[7..7 + self.received]);
+        } else {
+            // Stray continuation packets are ignored.
+            if self.pending != Some(channel) {
+                return None;
+            }
+            if kind != self.sequence {
+                self.pending = None;
+                error(channel, ERR_INVALID_SEQ, send);
+                return None;
+            }
+            let count = (self.length - self.received).min(CONT_DATA);
+            self.data[self.received..self.received + count].copy_from_slice(&packet[5..5 + count]);
+            self.received += count;
+            self.sequence += 1;
+            self.last_packet = now;
+        }
+
+        if self.received < self.length {
+            return None;
+        }
+        self.pending = None;
+        let data = &self.data[..self.length];
+        match self.command {
+            PING => {
+                reply(channel, PING, data, send);
+                None
+            }
+            WINK => {
+                reply(channel, WINK, &[], send);
+                Some(Event::Wink)
+            }
+            MSG => Some(Event::Message { channel, data }),
+            _ => {
+                error(channel, ERR_INVALID_CMD, send);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Packets for `data` sent as `command` on `channel`.
+    fn packets(channel: u32, command: u8, data: &[u8]) -> Vec<[u8; PACKET_LEN]> {
+        let mut packets = Vec::new();
+        reply(channel, command, data, |packet| packets.push(*packet));
+        packets
+    }
+
+    // Feeds packets in, returning what was sent back and the last event's message, if any.
+    fn exchange(transport: &mut Transport, packets: &[[u8; PACKET_LEN]]) -> (Vec<[u8; PACKET_LEN]>, Option<Vec<u8>>) {
+        exchange_at(transport, 0, packets)
+    }
+
+    // The same, with the packets arriving at `now`.
+    fn exchange_at(
+        transport: &mut Transport,
+        now: u64,
+        packets: &[[u8; PACKET_LEN]],
+    ) -> (Vec<[u8; PACKET_LEN]>, Option<Vec<u8>>) {
+        let mut sent = Vec::new();
+        let mut message = None;
+        for packet in packets {
+            if let Some(Event::Message { data, .. }) = transport.receive(packet, now, |packet| sent.push(*packet)) {
+                message = Some(data.to_vec());
+            }
+        }
+        (sent, message)
+    }
+
+    fn open(transport: &mut Transport) -> u32 {
+        let (sent, _) = exchange(transport, &packets(BROADCAST, INIT, b"12345678"));
+        u32::from_be_bytes(sent[0][15..19].try_into().unwrap())
+    }
+
+    #[test]
+    fn init_hands_out_channels() {
+        let mut transport = Transport::default();
+        let (sent, _) = exchange(&mut transport, &packets(BROADCAST, INIT, b"nonce 01"));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0][..7], [0xff, 0xff, 0xff, 0xff, INIT, 0, 17]);
+        assert_eq!(&sent[0][7..15], b"nonce 01");
+        assert_eq!(sent[0][15..24], [0, 0, 0, 1, 2, 1, 0, 0, 1]);
+        assert_eq!(open(&mut transport), 2);
+
+        // On its own channel INIT keeps the channel.
+        let (sent, _) = exchange(&mut transport, &packets(2, INIT, b"nonce 02"));
+        assert_eq!(sent[0][..4], [0, 0, 0, 2]);
+        assert_eq!(sent[0][15..19], [0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn messages_split_and_joined() {
+        let mut transport = Transport::default();
+        let channel = open(&mut transport);
+        let data: Vec<u8> = (0..300).map(|i| i as u8).collect();
+
+        let sent = packets(channel, MSG, &data);
+        assert_eq!(sent.len(), 6);
+        assert_eq!(sent[1][4], 0);
+        assert_eq!(sent[5][4], 4);
+        let (_, message) = exchange(&mut transport, &sent);
+        assert_eq!(message, Some(data));
+
+        let (_, message) = exchange(&mut transport, &packets(channel, MSG, &[]));
+        assert_eq!(message, Some(vec![]));
+    }
+
+    #[test]
+    fn ping_and_wink() {
+        let mut transport = Transport::default();
+        let channel = open(&mut transport);
+        let data = [0x5a; 100];
+        let (sent, _) = exchange(&mut transport, &packets(channel, PING, &data));
+        assert_eq!(sent, packets(channel, PING, &data));
+
+        let mut sent = Vec::new();
+        let event = transport.receive(&packets(channel, WINK, &[])[0], 0, |packet| sent.push(*packet));
+        assert_eq!(event, Some(Event::Wink));
+        assert_eq!(sent, packets(channel, WINK, &[]));
+    }
+
+    #[test]
+    fn errors() {
+        let mut transport = Transport::default();
+        let channel = open(&mut transport);
+        let sent_error = |sent: &[[u8; PACKET_LEN]], channel: u32, code: u8| {
+            assert_eq!(sent.to_vec(), packets(channel, ERROR, &[code]));
+        };
+
+        let (sent, _) = exchange(&mut transport, &packets(channel, 0x99, b"?"));
+        sent_error(&sent, channel, ERR_INVALID_CMD);
+        let (sent, _) = exchange(&mut transport, &packets(7, MSG, b"?"));
+        sent_error(&sent, 7, ERR_INVALID_CHANNEL);
+        let (sent, _) = exchange(&mut transport, &packets(BROADCAST, MSG, b"?"));
+        sent_error(&sent, BROADCAST, ERR_INVALID_CHANNEL);
+
+        let mut long = packets(channel, MSG, &[0; 100]);
+        long[0][5..7].copy_from_slice(&(MAX_MESSAGE as u16 + 1).to_be_bytes());
+        let (sent, _) = exchange(&mut transport, &long[..1]);
+        sent_error(&sent, channel, ERR_INVALID_LEN);
+
+        // Another channel has to wait for a message that's half there.
+        let other = open(&mut transport);
+        let message = packets(channel, MSG, &[1; 100]);
+        let (sent, _) = exchange(&mut transport, &message[..1]);
+        assert!(sent.is_empty());
+        let (sent, _) = exchange(&mut transport, &packets(other, MSG, b"?"));
+        sent_error(&sent, other, ERR_CHANNEL_BUSY);
+        let (_, received) = exchange(&mut transport, &message[1..]);
+        assert_eq!(received, Some(vec![1; 100]));
+
+        // Out of order.
+        let mut message = packets(channel, MSG, &[2; 200]);
+        message.swap(1, 2);
+        let (sent, received) = exchange(&mut transport, &message);
+        sent_error(&sent, channel, ERR_INVALID_SEQ);
+        assert_eq!(received, None);
+    }
+
+    #[test]
+    fn stalled_messages_time_out() {
+        let mut transport = Transport::default();
+        let channel = open(&mut transport);
+        let other = open(&mut transport);
+        let message = packets(channel, MSG, &[3; 100]);
+
+        // Slow but not stalled.
+        let (sent, _) = exchange_at(&mut transport, 10, &message[..1]);
+        assert!(sent.is_empty());
+        let (_, received) = exchange_at(&mut transport, 10 + TIMEOUT, &message[1..]);
+        assert_eq!(received, Some(vec![3; 100]));
+
+        // Stalled, and another channel is let in.
+        exchange_at(&mut transport, 20, &message[..1]);
+        let mut sent = Vec::new();
+        transport.expire(20 + TIMEOUT, |packet| sent.push(*packet));
+        assert!(sent.is_empty());
+        transport.expire(21 + TIMEOUT, |packet| sent.push(*packet));
+        assert_eq!(sent, packets(channel, ERROR, &[ERR_MSG_TIMEOUT]));
+        let (_, received) = exchange_at(&mut transport, 21 + TIMEOUT, &packets(other, MSG, b"!"));
+        assert_eq!(received, Some(b"!".to_vec()));
+
+        // The rest of the dropped message is ignored.
+        let (sent, received) = exchange_at(&mut transport, 22 + TIMEOUT, &message[1..]);
+        assert!(sent.is_empty());
+        assert_eq!(received, None);
+
+        // `receive` drops it too, before taking the packet.
+        exchange_at(&mut transport, 30, &message[..1]);
+        let (sent, received) = exchange_at(&mut transport, 31 + TIMEOUT, &packets(other, MSG, b"?"));
+        assert_eq!(sent, packets(channel, ERROR, &[ERR_MSG_TIMEOUT]));
+        assert_eq!(received, Some(b"?".to_vec()));
+    }
+}
@@ -0,0 +1,208 @@
+//! Hashes, HMAC and CRC32 behind one enum, so a program can offer them as a list.
+
+use core::fmt;
+
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest as _, Sha256};
+
+/// Longest output, SHA-256's.
+pub const MAX_OUTPUT: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+    Crc32,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [Algorithm::Sha256, Algorithm::Sha1, Algorithm::Md5, Algorithm::Crc32];
+
+    pub fn parse(name: &str) -> Option<Algorithm> {
+        Algorithm::ALL.into_iter().find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Md5 => "md5",
+            Algorithm::Crc32 => "crc32",
+        }
+    }
+
+    /// Output length in bytes.
+    pub fn output_len(&self) -> usize {
+        match self {
+            Algorithm::Sha256 => 32,
+            Algorithm::Sha1 => 20,
+            Algorithm::Md5 => 16,
+            Algorithm::Crc32 => 4,
+        }
+    }
+
+    /// Whether there's an HMAC over it. CRC32 isn't a hash function and has none.
+    pub fn has_hmac(&self) -> bool {
+        *self != Algorithm::Crc32
+    }
+}
+
+/// A hash or MAC, shown as lowercase hex. CRC32 is big endian, the way it's usually written.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Output {
+    bytes: [u8; MAX_OUTPUT],
+    len: usize,
+}
+
+impl Output {
+    fn new(bytes: &[u8]) -> Output {
+        let mut output = Output { bytes: [0; MAX_OUTPUT], len: bytes.len() };
+        output.bytes[..bytes.len()].copy_from_slice(bytes);
+        output
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_bytes().iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+pub fn hash(algorithm: Algorithm, data: &[u8]) -> Output {
+    match algorithm {
+        Algorithm::Sha256 => Output::new(&Sha256::digest(data)),
+        Algorithm::Sha1 => Output::new(&Sha1::digest(data)),
+        Algorithm::Md5 => Output::new(&Md5::digest(data)),
+        Algorithm::Crc32 => Output::new(&crc32fast::hash(data).to_be_bytes()),
+    }
+}
+
+/// HMAC (RFC 2104) of `data` under `key`, `None` for CRC32.
+pub fn hmac(algorithm: Algorithm, key: &[u8], data: &[u8]) -> Option<Output> {
+    // Any key length is accepted, so `new_from_slice` can't fail.
+    fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Output {
+        let mut mac = <M as Mac>::new_from_slice(key).unwrap();
+        mac.update(data);
+        Output::new(&mac.finalize().into_bytes())
+    }
+
+    match algorithm {
+        Algorithm::Sha256 => Some(mac::<Hmac<Sha256>>(key, data)),
+        Algorithm::Sha1 => Some(mac::<Hmac<Sha1>>(key, data)),
+        Algorithm::Md5 => Some(mac::<Hmac<Md5>>(key, data)),
+        Algorithm::Crc32 => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::string::ToString;
+
+    fn hex(algorithm: Algorithm, data: &[u8]) -> String {
+        hash(algorithm, data).to_string()
+    }
+
+    fn hmac_hex(algorithm: Algorithm, key: &[u8], data: &[u8]) -> String {
+        hmac(algorithm, key, data).unwrap().to_string()
+    }
+
+    // FIPS 180-2 appendix B.
+    #[test]
+    fn sha256_known_answers() {
+        assert_eq!(hex(Algorithm::Sha256, b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hex(Algorithm::Sha256, b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            hex(Algorithm::Sha256, b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    // FIPS 180-1 appendix A and B.
+    #[test]
+    fn sha1_known_answers() {
+        assert_eq!(hex(Algorithm::Sha1, b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            hex(Algorithm::Sha1, b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    // RFC 1321 section A.5.
+    #[test]
+    fn md5_known_answers() {
+        assert_eq!(hex(Algorithm::Md5, b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(Algorithm::Md5, b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(hex(Algorithm::Md5, b"message digest"), "f96b697d7cb7938d525a2f31aaf161d0");
+    }
+
+    // The CRC-32/ISO-HDLC check value, and zlib's crc32 of a pangram.
+    #[test]
+    fn crc32_known_answers() {
+        assert_eq!(hex(Algorithm::Crc32, b"123456789"), "cbf43926");
+        assert_eq!(hex(Algorithm::Crc32, b"The quick brown fox jumps over the lazy dog"), "414fa339");
+        assert_eq!(hex(Algorithm::Crc32, b""), "00000000");
+    }
+
+    // RFC 4231 test cases 1, 2 and 6 (a key longer than the block size).
+    #[test]
+    fn hmac_sha256_known_answers() {
+        assert_eq!(
+            hmac_hex(Algorithm::Sha256, &[0x0b; 20], b"Hi There"),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hmac_hex(Algorithm::Sha256, b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hmac_hex(Algorithm::Sha256, &[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First"),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    // RFC 2202 test cases 1 and 2.
+    #[test]
+    fn hmac_sha1_and_md5_known_answers() {
+        assert_eq!(hmac_hex(Algorithm::Sha1, &[0x0b; 20], b"Hi There"), "b617318655057264e28bc0b6fb378c8ef146be00");
+        assert_eq!(hmac_hex(Algorithm::Sha1, b"Jefe", b"what do ya want for nothing?"), "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+        assert_eq!(hmac_hex(Algorithm::Md5, &[0x0b; 16], b"Hi There"), "9294727a3638bb1c13f48ef8158bfc9d");
+        assert_eq!(hmac_hex(Algorithm::Md5, b"Jefe", b"what do ya want for nothing?"), "750c783e6ab0b503eaa86e310a5db738");
+    }
+
+    #[test]
+    fn crc32_has_no_hmac() {
+        assert!(hmac(Algorithm::Crc32, b"key", b"data").is_none());
+        assert!(!Algorithm::Crc32.has_hmac());
+    }
+
+    #[test]
+    fn output_lengths_match() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(hash(algorithm, b"badge").as_bytes().len(), algorithm.output_len());
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(Algorithm::parse(algorithm.name()), Some(algorithm));
+        }
+        assert_eq!(Algorithm::parse("SHA256"), Some(Algorithm::Sha256));
+        assert_eq!(Algorithm::parse("sha512"), None);
+    }
+}
@@ -0,0 +1,16 @@
+//! The badge's cryptography, kept out of the firmware so it can be tested on the host.
+//!
+//! Everything here is `no_std` and allocation free. The firmware only adds buttons, screens and
+//! the serial console on top.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod cipher;
+pub mod ctaphid;
+pub mod hash;
+pub mod otp;
+pub mod passgen;
+pub mod rng;
+pub mod sign;
+pub mod u2f;
+pub mod vault;
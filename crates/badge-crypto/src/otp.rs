@@ -0,0 +1,281 @@
+//! One-time passwords: HOTP (RFC 4226), TOTP (RFC 6238) and the `otpauth://` URIs authenticator
+//! apps use to share their secrets.
+//!
+//! ```text
+//! otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example
+//! otpauth://hotp/Lab?secret=GEZDGNBVGY3TQOJQ&counter=7&digits=8
+//! ```
+//!
+//! Codes are returned as numbers, to be printed zero padded to `digits`.
+
+use core::fmt;
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::cipher::Transform;
+
+/// Longest secret kept, in bytes. Generators use 10 to 64.
+pub const MAX_SECRET: usize = 64;
+/// Longest TOTP period accepted, an hour. Sites use 30 or 60 seconds.
+pub const MAX_PERIOD: u32 = 3600;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// Not an `otpauth://totp/` or `otpauth://hotp/` URI.
+    NotOtpauth,
+    MissingSecret,
+    /// The secret isn't base32, or is longer than `MAX_SECRET`.
+    BadSecret,
+    /// A parameter with a value that can't be used, like `digits=12`.
+    BadParameter,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::NotOtpauth => "not an otpauth://totp/ or otpauth://hotp/ URI",
+            Error::MissingSecret => "no secret",
+            Error::BadSecret => "secret isn't valid base32",
+            Error::BadParameter => "unsupported algorithm, digits or period",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    pub fn parse(name: &str) -> Option<Algorithm> {
+        [Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512]
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// Time based, a new code every `period` seconds.
+    Totp { period: u32 },
+    /// Counter based, the counter is for the next code.
+    Hotp { counter: u64 },
+}
+
+/// HOTP: the HMAC of `counter`, dynamically truncated to `digits` decimal digits.
+pub fn hotp(algorithm: Algorithm, secret: &[u8], counter: u64, digits: u8) -> u32 {
+    fn mac<M: Mac + hmac::digest::KeyInit>(secret: &[u8], counter: u64, out: &mut [u8; 64]) -> usize {
+        // Any key length is accepted, so `new_from_slice` can't fail.
+        let mut mac = <M as Mac>::new_from_slice(secret).unwrap();
+        mac.update(&counter.to_be_bytes());
+        let bytes = mac.finalize().into_bytes();
+        out[..bytes.len()].copy_from_slice(&bytes);
+        bytes.len()
+    }
+
+    let mut out = [0; 64];
+    let len = match algorithm {
+        Algorithm::Sha1 => mac::<Hmac<Sha1>>(secret, counter, &mut out),
+        Algorithm::Sha256 => mac::<Hmac<Sha256>>(secret, counter, &mut out),
+        Algorithm::Sha512 => mac::<Hmac<Sha512>>(secret, counter, &mut out),
+    };
+    let offset = (out[len - 1] & 0xf) as usize;
+    let value = u32::from_be_bytes([out[offset], out[offset + 1], out[offset + 2], out[offset + 3]]) & 0x7fff_ffff;
+    value % 10u32.pow(digits as u32)
+}
+
+/// TOTP: HOTP of the number of `period`s since the Unix epoch.
+pub fn totp(algorithm: Algorithm, secret: &[u8], unix_time: u64, period: u32, digits: u8) -> u32 {
+    hotp(algorithm, secret, unix_time / period as u64, digits)
+}
+
+/// Seconds until the TOTP code at `unix_time` changes.
+pub fn remaining(unix_time: u64, period: u32) -> u32 {
+    period - (unix_time % period as u64) as u32
+}
+
+/// A parsed `otpauth://` URI. `label` and `issuer` are still percent-encoded, `name` decodes them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uri<'a> {
+    pub kind: Kind,
+    pub label: &'a str,
+    pub issuer: Option<&'a str>,
+    /// Base32, as in the URI.
+    pub secret: &'a str,
+    pub algorithm: Algorithm,
+    pub digits: u8,
+}
+
+impl<'a> Uri<'a> {
+    pub fn parse(uri: &'a str) -> Result<Uri<'a>, Error> {
+        let rest = uri.strip_prefix("otpauth://").ok_or(Error::NotOtpauth)?;
+        let (kind, rest) = rest.split_once('/').ok_or(Error::NotOtpauth)?;
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let mut parsed = Uri {
+            kind: if kind.eq_ignore_ascii_case("totp") {
+                Kind::Totp { period: 30 }
+            } else if kind.eq_ignore_ascii_case("hotp") {
+                Kind::Hotp { counter: 0 }
+            } else {
+                return Err(Error::NotOtpauth);
+            },
+            label,
+            issuer: None,
+            secret: "",
+            algorithm: Algorithm::Sha1,
+            digits: 6,
+        };
+        for (name, value) in query.split('&').filter_map(|parameter| parameter.split_once('=')) {
+            match (name, &mut parsed.kind) {
+                ("secret", _) => parsed.secret = value,
+                ("issuer", _) => parsed.issuer = Some(value),
+                ("algorithm", _) => parsed.algorithm = Algorithm::parse(value).ok_or(Error::BadParameter)?,
+                ("digits", _) => {
+                    parsed.digits = value.parse().ok().filter(|digits| (6..=8).contains(digits)).ok_or(Error::BadParameter)?
+                }
+                ("period", Kind::Totp { period }) => {
+                    *period = value.parse().ok().filter(|period| (1..=MAX_PERIOD).contains(period)).ok_or(Error::BadParameter)?
+                }
+                ("counter", Kind::Hotp { counter }) => *counter = value.parse().map_err(|_| Error::BadParameter)?,
+                // Apps add their own, like `image`.
+                _ => {}
+            }
+        }
+
+        if parsed.secret.is_empty() {
+            return Err(Error::MissingSecret);
+        }
+        parsed.decode_secret(&mut [0; MAX_SECRET])?;
+        Ok(parsed)
+    }
+
+    /// The secret's bytes, written to `out`.
+    pub fn decode_secret<'b>(&self, out: &'b mut [u8; MAX_SECRET]) -> Result<&'b [u8], Error> {
+        let len = Transform::FromBase32.apply(self.secret.as_bytes(), out).map_err(|_| Error::BadSecret)?;
+        Ok(&out[..len])
+    }
+
+    /// The account name shown for it, like "Example:alice@example.com", written to `out`. Apps
+    /// put the issuer in the label already, it's only added when they haven't.
+    pub fn name<'b>(&self, out: &'b mut [u8]) -> Option<&'b str> {
+        let mut label_buffer = [0; 128];
+        let label = decode(self.label, &mut label_buffer)?;
+        let mut issuer_buffer = [0; 64];
+        let issuer = match self.issuer {
+            Some(issuer) if !label.contains(':') => decode(issuer, &mut issuer_buffer).filter(|issuer| !issuer.is_empty()),
+            _ => None,
+        };
+
+        let (prefix, separator) = match issuer {
+            Some(issuer) => (issuer, ":"),
+            None => ("", ""),
+        };
+        let len = prefix.len() + separator.len() + label.len();
+        let out = out.get_mut(..len)?;
+        out[..prefix.len()].copy_from_slice(prefix.as_bytes());
+        out[prefix.len()..prefix.len() + separator.len()].copy_from_slice(separator.as_bytes());
+        out[prefix.len() + separator.len()..].copy_from_slice(label.as_bytes());
+        core::str::from_utf8(out).ok()
+    }
+}
+
+// Percent-decodes a URI component into `out`.
+fn decode<'b>(text: &str, out: &'b mut [u8]) -> Option<&'b str> {
+    let len = Transform::UrlDecode.apply(text.as_bytes(), out).ok()?;
+    core::str::from_utf8(&out[..len]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4226 appendix D.
+    #[test]
+    fn hotp_known_answers() {
+        let secret = b"12345678901234567890";
+        let codes = [755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489];
+        for (counter, code) in codes.into_iter().enumerate() {
+            assert_eq!(hotp(Algorithm::Sha1, secret, counter as u64, 6), code, "counter {}", counter);
+        }
+    }
+
+    // RFC 6238 appendix B, with the seed repeated to each hash's block size as in its reference code.
+    #[test]
+    fn totp_known_answers() {
+        let sha1 = b"12345678901234567890";
+        let sha256 = b"12345678901234567890123456789012";
+        let sha512 = b"1234567890123456789012345678901234567890123456789012345678901234";
+        let vectors: [(u64, u32, u32, u32); 6] = [
+            (59, 94287082, 46119246, 90693936),
+            (1111111109, 7081804, 68084774, 25091201),
+            (1111111111, 14050471, 67062674, 99943326),
+            (1234567890, 89005924, 91819424, 93441116),
+            (2000000000, 69279037, 90698825, 38618901),
+            (20000000000, 65353130, 77737706, 47863826),
+        ];
+        for (time, code_sha1, code_sha256, code_sha512) in vectors {
+            assert_eq!(totp(Algorithm::Sha1, sha1, time, 30, 8), code_sha1, "SHA1 at {}", time);
+            assert_eq!(totp(Algorithm::Sha256, sha256, time, 30, 8), code_sha256, "SHA256 at {}", time);
+            assert_eq!(totp(Algorithm::Sha512, sha512, time, 30, 8), code_sha512, "SHA512 at {}", time);
+        }
+    }
+
+    #[test]
+    fn remaining_seconds() {
+        assert_eq!(remaining(59, 30), 1);
+        assert_eq!(remaining(60, 30), 30);
+        assert_eq!(remaining(61, 60), 59);
+    }
+
+    #[test]
+    fn totp_uri() {
+        let uri = Uri::parse("otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example").unwrap();
+        assert_eq!(uri.kind, Kind::Totp { period: 30 });
+        assert_eq!((uri.algorithm, uri.digits), (Algorithm::Sha1, 6));
+        assert_eq!(uri.decode_secret(&mut [0; MAX_SECRET]).unwrap(), b"Hello!\xde\xad\xbe\xef");
+        assert_eq!(uri.name(&mut [0; 64]), Some("Example:alice@example.com"));
+
+        let uri = Uri::parse("otpauth://totp/alice%40example.com?issuer=Big%20Corp&secret=jbswy3dpehpk3pxp&algorithm=SHA256&digits=8&period=60&image=x").unwrap();
+        assert_eq!(uri.kind, Kind::Totp { period: 60 });
+        assert_eq!((uri.algorithm, uri.digits), (Algorithm::Sha256, 8));
+        assert_eq!(uri.name(&mut [0; 64]), Some("Big Corp:alice@example.com"));
+        assert_eq!(uri.name(&mut [0; 8]), None);
+
+        let uri = Uri::parse("otpauth://totp/x?secret=JBSWY3DP&period=3600").unwrap();
+        assert_eq!(uri.kind, Kind::Totp { period: MAX_PERIOD });
+    }
+
+    #[test]
+    fn hotp_uri() {
+        let uri = Uri::parse("otpauth://hotp/Lab?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=7").unwrap();
+        assert_eq!(uri.kind, Kind::Hotp { counter: 7 });
+        assert_eq!(uri.decode_secret(&mut [0; MAX_SECRET]).unwrap(), b"12345678901234567890");
+        assert_eq!(uri.name(&mut [0; 64]), Some("Lab"));
+    }
+
+    #[test]
+    fn bad_uris() {
+        assert_eq!(Uri::parse("https://example.com/?secret=JBSWY3DP"), Err(Error::NotOtpauth));
+        assert_eq!(Uri::parse("otpauth://motp/x?secret=JBSWY3DP"), Err(Error::NotOtpauth));
+        assert_eq!(Uri::parse("otpauth://totp/x?issuer=y"), Err(Error::MissingSecret));
+        assert_eq!(Uri::parse("otpauth://totp/x?secret=JBSW1"), Err(Error::BadSecret));
+        assert_eq!(Uri::parse("otpauth://totp/x?secret=JBSWY3DP&digits=12"), Err(Error::BadParameter));
+        assert_eq!(Uri::parse("otpauth://totp/x?secret=JBSWY3DP&period=0"), Err(Error::BadParameter));
+        assert_eq!(Uri::parse("otpauth://totp/x?secret=JBSWY3DP&period=3601"), Err(Error::BadParameter));
+        assert_eq!(Uri::parse("otpauth://totp/x?secret=JBSWY3DP&period=4294967295"), Err(Error::BadParameter));
+        assert_eq!(Uri::parse("otpauth://totp/x?secret=JBSWY3DP&algorithm=MD5"), Err(Error::BadParameter));
+    }
+}
@@ -0,0 +1,232 @@
+//! Diceware passphrases from the EFF's large wordlist, and random passwords.
+//!
+//! The wordlist's 7776 words (one for every roll of five dice) are packed by build.rs and unpacked
+//! a word at a time, see there for the format. Every word and character is picked uniformly with
+//! rejection sampling, so a passphrase of N words has N * 12.9 bits of entropy.
+
+use core::fmt;
+
+use rand_core::RngCore;
+
+/// Words in the list.
+pub const WORDS: usize = 7776;
+/// Longest word in the list.
+pub const MAX_WORD: usize = 9;
+
+pub const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+pub const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const DIGITS: &str = "0123456789";
+/// Printable ASCII symbols that are easy to type and don't need quoting in most places.
+pub const SYMBOLS: &str = "!#$%&*+-./:;=?@^_~";
+
+static WORDLIST: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/wordlist.bin"));
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// The result doesn't fit in the buffer.
+    TooLong,
+    /// No character classes to pick from.
+    NoCharacters,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::TooLong => "result too long",
+            Error::NoCharacters => "no characters to pick from",
+        })
+    }
+}
+
+struct Bits {
+    position: usize,
+}
+
+impl Bits {
+    fn take(&mut self, count: usize) -> u8 {
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = (WORDLIST[self.position / 8] >> (7 - (self.position & 7))) & 1;
+            value = (value << 1) | bit;
+            self.position += 1;
+        }
+        value
+    }
+}
+
+/// Word `index` of the list (0 is "abacus"), unpacked into `out`. `None` past the end.
+pub fn word(index: usize, out: &mut [u8; MAX_WORD]) -> Option<&str> {
+    if index >= WORDS {
+        return None;
+    }
+
+    let mut bits = Bits { position: 0 };
+    let mut len = 0;
+    for _ in 0..=index {
+        let shared = bits.take(4) as usize;
+        let rest = bits.take(4) as usize;
+        for byte in &mut out[shared..shared + rest] {
+            *byte = match bits.take(5) {
+                26 => b'-',
+                letter => b'a' + letter,
+            };
+        }
+        len = shared + rest;
+    }
+    core::str::from_utf8(&out[..len]).ok()
+}
+
+/// A uniform pick from `0..count`, `count` must be more than 0.
+pub fn below<R: RngCore>(rng: &mut R, count: u32) -> u32 {
+    // Values past the last whole multiple of `count` would favour the low results.
+    let zone = (1u64 << 32) / count as u64 * count as u64;
+    loop {
+        let value = rng.next_u32();
+        if (value as u64) < zone {
+            return value % count;
+        }
+    }
+}
+
+/// `words` diceware words separated by `separator`, written to `out`.
+pub fn passphrase<'a, R: RngCore>(rng: &mut R, words: usize, separator: u8, out: &'a mut [u8]) -> Result<&'a str, Error> {
+    let mut len = 0;
+    let mut buffer = [0; MAX_WORD];
+    for index in 0..words {
+        let word = word(below(rng, WORDS as u32) as usize, &mut buffer).unwrap_or_default();
+        let start = if index == 0 { len } else { len + 1 };
+        let end = start + word.len();
+        let slot = out.get_mut(len..end).ok_or(Error::TooLong)?;
+        if index > 0 {
+            slot[0] = separator;
+        }
+        slot[start - len..].copy_from_slice(word.as_bytes());
+        len = end;
+    }
+    core::str::from_utf8(&out[..len]).map_err(|_| Error::TooLong)
+}
+
+/// A password as long as `out` drawn from all of `classes`, with at least one character of each
+/// class when it's long enough for that.
+pub fn password<'a, R: RngCore>(rng: &mut R, classes: &[&str], out: &'a mut [u8]) -> Result<&'a str, Error> {
+    let total: usize = classes.iter().map(|class| class.len()).sum();
+    if total == 0 {
+        return Err(Error::NoCharacters);
+    }
+    let pick = |index: usize| {
+        let mut index = index;
+        for class in classes {
+            match class.as_bytes().get(index) {
+                Some(&byte) => return byte,
+                None => index -= class.len(),
+            }
+        }
+        unreachable!()
+    };
+
+    // Drawing again until every class shows up keeps the choice uniform over the passwords that
+    // have them all.
+    loop {
+        out.iter_mut().for_each(|byte| *byte = pick(below(rng, total as u32) as usize));
+        let complete = classes.iter().all(|class| out.iter().any(|byte| class.as_bytes().contains(byte)));
+        if complete || out.len() < classes.len() {
+            break;
+        }
+    }
+    core::str::from_utf8(out).map_err(|_| Error::NoCharacters)
+}
+
+/// Bits of entropy in `picks` uniform choices from `choices`, rounded down.
+pub fn entropy_bits(choices: usize, picks: usize) -> u32 {
+    // floor(log2(choices ^ picks)), keeping the product in range by shifting bits out of it.
+    let mut bits = 0;
+    let mut product = 1u64;
+    for _ in 0..picks {
+        product *= choices as u64;
+        while product >= 1 << 32 {
+            product >>= 1;
+            bits += 1;
+        }
+    }
+    bits + 63 - product.leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    const TEXT: &str = include_str!("../data/eff_large_wordlist.txt");
+
+    fn rng() -> ChaCha20Rng {
+        ChaCha20Rng::from_seed([7; 32])
+    }
+
+    #[test]
+    fn unpacks_words() {
+        let mut buffer = [0; MAX_WORD];
+        assert_eq!(TEXT.lines().count(), WORDS);
+        // Every word would take a while, unpacking starts from the top each time.
+        for (index, line) in TEXT.lines().enumerate().step_by(37) {
+            assert_eq!(word(index, &mut buffer), line.split('\t').nth(1));
+        }
+        assert_eq!(word(0, &mut buffer), Some("abacus"));
+        assert_eq!(word(WORDS - 1, &mut buffer), Some("zoom"));
+        assert_eq!(word(WORDS, &mut buffer), None);
+    }
+
+    #[test]
+    fn packed_smaller_than_text() {
+        assert!(WORDLIST.len() * 4 < TEXT.len());
+    }
+
+    #[test]
+    fn passphrases_are_words_from_the_list() {
+        let mut rng = rng();
+        let mut out = [0; 64];
+        let phrase = passphrase(&mut rng, 6, b' ', &mut out).unwrap();
+        assert_eq!(phrase.split(' ').count(), 6);
+        for word in phrase.split(' ') {
+            assert!(TEXT.lines().any(|line| line.split('\t').nth(1) == Some(word)));
+        }
+
+        let mut short = [0; 8];
+        assert_eq!(passphrase(&mut rng, 6, b' ', &mut short), Err(Error::TooLong));
+    }
+
+    #[test]
+    fn passwords_use_every_class() {
+        let mut rng = rng();
+        for _ in 0..100 {
+            let mut out = [0; 8];
+            let password = password(&mut rng, &[LOWER, DIGITS, SYMBOLS], &mut out).unwrap();
+            assert!(password.bytes().any(|c| c.is_ascii_lowercase()));
+            assert!(password.bytes().any(|c| c.is_ascii_digit()));
+            assert!(password.bytes().any(|c| SYMBOLS.as_bytes().contains(&c)));
+            assert!(!password.bytes().any(|c| c.is_ascii_uppercase()));
+        }
+        let mut out = [0; 8];
+        assert_eq!(password(&mut rng, &[], &mut out), Err(Error::NoCharacters));
+    }
+
+    #[test]
+    fn picks_are_uniform() {
+        let mut rng = rng();
+        let mut counts = [0u32; 6];
+        for _ in 0..60_000 {
+            counts[below(&mut rng, 6) as usize] += 1;
+        }
+        // 10000 expected each.
+        assert!(counts.iter().all(|&count| (9_500..10_500).contains(&count)));
+    }
+
+    #[test]
+    fn entropy() {
+        assert_eq!(entropy_bits(7776, 1), 12);
+        assert_eq!(entropy_bits(7776, 6), 77);
+        assert_eq!(entropy_bits(62, 16), 95);
+        assert_eq!(entropy_bits(10, 6), 19);
+        assert_eq!(entropy_bits(2, 64), 64);
+    }
+}
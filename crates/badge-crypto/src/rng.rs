@@ -0,0 +1,293 @@
+//! Random numbers for keys: a hardware noise source, health tested and condensed into seeds for
+//! a ChaCha20 CSPRNG.
+//!
+//! Raw samples are bytes from a `NoiseSource`, assumed to carry at least `MIN_ENTROPY` bit of
+//! entropy each, which is deliberately pessimistic for a ring oscillator. Every sample goes
+//! through the continuous health tests of NIST SP 800-90B section 4.4, sized for that entropy
+//! and a false alarm rate of 2^-20:
+//!
+//! - Repetition count: the same sample `RCT_CUTOFF` times in a row.
+//! - Adaptive proportion: the first sample of a `APT_WINDOW` sample window showing up
+//!   `APT_CUTOFF` times in it.
+//!
+//! `SAMPLES_PER_SEED` samples that pass are hashed with SHA-256 into a 32-byte seed, with 64
+//! bits more entropy going in than can come out. `Rng` seeds ChaCha20 that way after a startup
+//! test, and reseeds every `RESEED_INTERVAL` bytes by hashing its own output with a fresh seed.
+//! If a reseed fails its health tests the generator carries on from its current state, which
+//! stays as unpredictable as it was, and the failure is counted.
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{CryptoRng, Error, RngCore, SeedableRng};
+use sha2::{Digest as _, Sha256};
+
+/// Assumed entropy per sample, in bits.
+pub const MIN_ENTROPY: u32 = 1;
+/// 1 + ceil(20 / MIN_ENTROPY).
+pub const RCT_CUTOFF: u32 = 21;
+pub const APT_WINDOW: u32 = 512;
+/// SP 800-90B table 2, non-binary samples with one bit of entropy.
+pub const APT_CUTOFF: u32 = 311;
+/// Samples tested before the first seed, SP 800-90B section 4.3.
+pub const STARTUP_SAMPLES: usize = 1024;
+/// Samples hashed into each 32-byte seed: 256 bits out needs 320 in, with some to spare.
+pub const SAMPLES_PER_SEED: usize = 384;
+/// Output bytes between reseeds.
+pub const RESEED_INTERVAL: u64 = 1 << 20;
+
+/// Hardware randomness, one raw sample at a time.
+pub trait NoiseSource {
+    fn sample(&mut self) -> u8;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HealthError {
+    RepetitionCount,
+    AdaptiveProportion,
+}
+
+/// The continuous health tests, with counts for reporting.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HealthTests {
+    last: u8,
+    repeats: u32,
+    reference: u8,
+    matches: u32,
+    window: u32,
+    /// Samples tested so far.
+    pub samples: u64,
+    pub repetition_failures: u32,
+    pub proportion_failures: u32,
+}
+
+impl HealthTests {
+    pub fn check(&mut self, sample: u8) -> Result<(), HealthError> {
+        let first = self.samples == 0;
+        self.samples += 1;
+
+        if !first && sample == self.last {
+            self.repeats += 1;
+        } else {
+            self.last = sample;
+            self.repeats = 1;
+        }
+
+        if self.window == 0 {
+            self.reference = sample;
+            self.matches = 1;
+        } else if sample == self.reference {
+            self.matches += 1;
+        }
+        self.window = (self.window + 1) % APT_WINDOW;
+
+        if self.repeats >= RCT_CUTOFF {
+            self.repeats = 0;
+            self.repetition_failures += 1;
+            return Err(HealthError::RepetitionCount);
+        }
+        if self.matches >= APT_CUTOFF {
+            // Starts a new window.
+            self.window = 0;
+            self.matches = 0;
+            self.proportion_failures += 1;
+            return Err(HealthError::AdaptiveProportion);
+        }
+        Ok(())
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.repetition_failures + self.proportion_failures
+    }
+}
+
+/// A 32-byte seed hashed from `SAMPLES_PER_SEED` tested samples.
+pub fn seed<N: NoiseSource>(source: &mut N, health: &mut HealthTests) -> Result<[u8; 32], HealthError> {
+    let mut hasher = Sha256::new();
+    for _ in 0..SAMPLES_PER_SEED {
+        let sample = source.sample();
+        health.check(sample)?;
+        hasher.update([sample]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+pub struct Rng<N> {
+    source: N,
+    pub health: HealthTests,
+    chacha: ChaCha20Rng,
+    since_reseed: u64,
+    /// Reseeds that happened.
+    pub reseeds: u32,
+}
+
+impl<N: NoiseSource> Rng<N> {
+    /// Runs the startup tests and seeds the generator, fails if the source looks broken.
+    pub fn new(mut source: N) -> Result<Rng<N>, HealthError> {
+        let mut health = HealthTests::default();
+        for _ in 0..STARTUP_SAMPLES {
+            health.check(source.sample())?;
+        }
+        let seed = seed(&mut source, &mut health)?;
+        Ok(Rng { source, health, chacha: ChaCha20Rng::from_seed(seed), since_reseed: 0, reseeds: 0 })
+    }
+
+    /// Mixes a fresh seed into the generator.
+    pub fn reseed(&mut self) -> Result<(), HealthError> {
+        let fresh = seed(&mut self.source, &mut self.health)?;
+        let mut current = [0; 32];
+        self.chacha.fill_bytes(&mut current);
+        let mixed: [u8; 32] = Sha256::new().chain_update(current).chain_update(fresh).finalize().into();
+        self.chacha = ChaCha20Rng::from_seed(mixed);
+        self.since_reseed = 0;
+        self.reseeds += 1;
+        Ok(())
+    }
+
+    /// Untested, unconditioned samples straight from the source, for assessing it on the host.
+    pub fn raw(&mut self, out: &mut [u8]) {
+        out.iter_mut().for_each(|byte| *byte = self.source.sample());
+    }
+}
+
+impl<N: NoiseSource> RngCore for Rng<N> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if self.since_reseed >= RESEED_INTERVAL {
+            // On failure it's counted in `health` and tried again next time.
+            let _ = self.reseed();
+        }
+        self.chacha.fill_bytes(dest);
+        self.since_reseed += dest.len() as u64;
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<N: NoiseSource> CryptoRng for Rng<N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift32, a stand-in for a healthy source.
+    struct Noise(u32);
+
+    impl NoiseSource for Noise {
+        fn sample(&mut self) -> u8 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as u8
+        }
+    }
+
+    struct Stuck;
+
+    impl NoiseSource for Stuck {
+        fn sample(&mut self) -> u8 {
+            0x55
+        }
+    }
+
+    // Three samples in four are zero, never many in a row.
+    struct Biased(u8);
+
+    impl NoiseSource for Biased {
+        fn sample(&mut self) -> u8 {
+            self.0 = self.0.wrapping_add(1);
+            if self.0 & 3 == 0 { self.0 } else { 0 }
+        }
+    }
+
+    #[test]
+    fn repetition_count_cutoff() {
+        let mut health = HealthTests::default();
+        for _ in 0..RCT_CUTOFF - 1 {
+            assert_eq!(health.check(7), Ok(()));
+        }
+        assert_eq!(health.check(7), Err(HealthError::RepetitionCount));
+        assert_eq!(health.check(8), Ok(()));
+        assert_eq!(health.repetition_failures, 1);
+    }
+
+    #[test]
+    fn adaptive_proportion_cutoff() {
+        let mut health = HealthTests::default();
+        let mut source = Biased(0);
+        let mut result = Ok(());
+        for _ in 0..APT_WINDOW {
+            result = result.and(health.check(source.sample()));
+        }
+        assert_eq!(result, Err(HealthError::AdaptiveProportion));
+        assert_eq!(health.proportion_failures, 1);
+        assert_eq!(health.repetition_failures, 0);
+    }
+
+    #[test]
+    fn healthy_source_passes() {
+        let mut health = HealthTests::default();
+        let mut source = Noise(1);
+        for _ in 0..100_000 {
+            assert_eq!(health.check(source.sample()), Ok(()));
+        }
+        assert_eq!(health.failures(), 0);
+        assert_eq!(health.samples, 100_000);
+    }
+
+    #[test]
+    fn broken_sources_are_refused() {
+        assert_eq!(Rng::new(Stuck).err(), Some(HealthError::RepetitionCount));
+        assert_eq!(Rng::new(Biased(0)).err(), Some(HealthError::AdaptiveProportion));
+    }
+
+    #[test]
+    fn seeds_follow_the_source() {
+        let mut a = Rng::new(Noise(1)).unwrap();
+        let mut b = Rng::new(Noise(1)).unwrap();
+        let mut c = Rng::new(Noise(2)).unwrap();
+        let (x, y, z) = (a.next_u64(), b.next_u64(), c.next_u64());
+        assert_eq!(x, y);
+        assert_ne!(x, z);
+
+        // Reseeding changes the stream.
+        a.reseed().unwrap();
+        assert_ne!(a.next_u64(), b.next_u64());
+        assert_eq!(a.reseeds, 1);
+    }
+
+    #[test]
+    fn reseeds_after_the_interval() {
+        let mut rng = Rng::new(Noise(3)).unwrap();
+        let mut block = [0u8; 4096];
+        for _ in 0..RESEED_INTERVAL / block.len() as u64 + 1 {
+            rng.fill_bytes(&mut block);
+        }
+        assert_eq!(rng.reseeds, 1);
+    }
+
+    #[test]
+    fn output_looks_uniform() {
+        let mut rng = Rng::new(Noise(4)).unwrap();
+        let mut counts = [0u32; 256];
+        let mut block = [0u8; 1024];
+        for _ in 0..256 {
+            rng.fill_bytes(&mut block);
+            block.iter().for_each(|&byte| counts[byte as usize] += 1);
+        }
+        // 1024 expected per value, far outside these only with a broken generator.
+        assert!(counts.iter().all(|&count| (850..1200).contains(&count)));
+    }
+}
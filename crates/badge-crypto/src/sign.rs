@@ -0,0 +1,183 @@
+//! The badge's identity: an Ed25519 (RFC 8032) keypair that signs messages to prove which badge
+//! is which.
+//!
+//! Everything signed is prefixed with `CONTEXT`, so a signature can only ever be checked as a
+//! badge message and the key can't be tricked into signing something meant for another
+//! protocol. `verify` adds the same prefix.
+
+use core::fmt;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use crate::hash::{self, Algorithm};
+
+/// Prefixed to every message before it's signed.
+pub const CONTEXT: &[u8] = b"lynix-badge signed message\n";
+
+/// Longest message `sign` takes.
+pub const MAX_MESSAGE: usize = 256;
+
+pub const PUBLIC_KEY_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TooLong;
+
+impl fmt::Display for TooLong {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "messages are limited to {} bytes", MAX_MESSAGE)
+    }
+}
+
+pub struct Identity {
+    key: SigningKey,
+}
+
+impl Identity {
+    /// The keypair for a 32-byte secret seed, which should come from a good entropy source.
+    pub fn from_seed(seed: &[u8; 32]) -> Identity {
+        Identity { key: SigningKey::from_bytes(seed) }
+    }
+
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_LEN] {
+        self.key.verifying_key().to_bytes()
+    }
+
+    /// Signs `CONTEXT` followed by `message`.
+    pub fn sign(&self, message: &[u8]) -> Result<[u8; SIGNATURE_LEN], TooLong> {
+        let mut prefixed = [0; CONTEXT.len() + MAX_MESSAGE];
+        let len = CONTEXT.len() + message.len();
+        let prefixed = prefixed.get_mut(..len).ok_or(TooLong)?;
+        prefixed[..CONTEXT.len()].copy_from_slice(CONTEXT);
+        prefixed[CONTEXT.len()..].copy_from_slice(message);
+        Ok(self.key.sign(prefixed).to_bytes())
+    }
+}
+
+/// Whether `signature` is `public_key`'s over `CONTEXT` followed by `message`.
+pub fn verify(public_key: &[u8; PUBLIC_KEY_LEN], message: &[u8], signature: &[u8; SIGNATURE_LEN]) -> bool {
+    let Ok(key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    let mut prefixed = [0; CONTEXT.len() + MAX_MESSAGE];
+    let len = CONTEXT.len() + message.len();
+    let Some(prefixed) = prefixed.get_mut(..len) else {
+        return false;
+    };
+    prefixed[..CONTEXT.len()].copy_from_slice(CONTEXT);
+    prefixed[CONTEXT.len()..].copy_from_slice(message);
+    key.verify_strict(prefixed, &Signature::from_bytes(signature)).is_ok()
+}
+
+/// A short, readable stand-in for a public key: the first 16 bytes of its SHA-256, in groups of
+/// four hex digits.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Fingerprint([u8; 16]);
+
+impl Fingerprint {
+    pub fn of(public_key: &[u8; PUBLIC_KEY_LEN]) -> Fingerprint {
+        let mut fingerprint = [0; 16];
+        fingerprint.copy_from_slice(&hash::hash(Algorithm::Sha256, public_key).as_bytes()[..16]);
+        Fingerprint(fingerprint)
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, pair) in self.0.chunks(2).enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}{:02x}", pair[0], pair[1])?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::string::ToString;
+
+    fn unhex<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        bytes
+    }
+
+    // RFC 8032 section 7.1, tests 1 to 3. Those sign the bare message, without `CONTEXT`.
+    #[test]
+    fn rfc8032_known_answers() {
+        let vectors = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "",
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "72",
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+            (
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                "af82",
+                "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+            ),
+        ];
+        for (seed, public, message, signature) in vectors {
+            let identity = Identity::from_seed(&unhex(seed));
+            assert_eq!(identity.public_key(), unhex::<32>(public));
+
+            let message: std::vec::Vec<u8> = (0..message.len() / 2).map(|i| u8::from_str_radix(&message[i * 2..i * 2 + 2], 16).unwrap()).collect();
+            assert_eq!(identity.key.sign(&message).to_bytes(), unhex::<64>(signature));
+        }
+    }
+
+    #[test]
+    fn signs_and_verifies_with_context() {
+        let identity = Identity::from_seed(&[7; 32]);
+        let public = identity.public_key();
+        let signature = identity.sign(b"challenge 1234").unwrap();
+
+        assert!(verify(&public, b"challenge 1234", &signature));
+        assert!(!verify(&public, b"challenge 1235", &signature));
+        assert!(!verify(&Identity::from_seed(&[8; 32]).public_key(), b"challenge 1234", &signature));
+
+        // Not a signature over the bare message.
+        let key = VerifyingKey::from_bytes(&public).unwrap();
+        assert!(key.verify_strict(b"challenge 1234", &Signature::from_bytes(&signature)).is_err());
+
+        let mut tampered = signature;
+        tampered[10] ^= 1;
+        assert!(!verify(&public, b"challenge 1234", &tampered));
+    }
+
+    #[test]
+    fn message_length_limit() {
+        let identity = Identity::from_seed(&[1; 32]);
+        let longest = [b'x'; MAX_MESSAGE];
+        let signature = identity.sign(&longest).unwrap();
+        assert!(verify(&identity.public_key(), &longest, &signature));
+        assert_eq!(identity.sign(&[b'x'; MAX_MESSAGE + 1]), Err(TooLong));
+        assert!(!verify(&identity.public_key(), &[b'x'; MAX_MESSAGE + 1], &signature));
+    }
+
+    #[test]
+    fn fingerprint_format() {
+        // SHA-256 of 32 zero bytes starts 66687aadf862bd776c8fc18b8e9f8e20.
+        assert_eq!(Fingerprint::of(&[0; 32]).to_string(), "6668 7aad f862 bd77 6c8f c18b 8e9f 8e20");
+    }
+}
@@ -0,0 +1,468 @@
+//! FIDO U2F (CTAP1): the badge as a security key.
+//!
+//! `SecurityKey::process` answers the messages of the FIDO U2F Raw Message Formats spec, which
+//! arrive over USB HID as `ctaphid` describes. Registering makes a P-256 keypair for the site that
+//! isn't stored anywhere: the key handle the site keeps is a random nonce and a MAC, and the
+//! private key is worked out again from them when the site asks to authenticate.
+//!
+//! ```text
+//! private key  HMAC-SHA256(master, "u2f key" || application || nonce)
+//! key handle   nonce || HMAC-SHA256(master, "u2f handle" || application || nonce)
+//! ```
+//!
+//! So any number of sites can register, and a handle is refused by every site but the one it was
+//! made for. Registrations are signed with an attestation key and a self-signed certificate that
+//! are unique to the badge, unlike commercial keys that share them across a batch, so sites that
+//! keep the certificates can tell two registrations came from the same badge.
+
+use hmac::{Hmac, Mac};
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use rand_core::{CryptoRng, RngCore};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+pub const KEY_HANDLE_LEN: usize = 64;
+const NONCE_LEN: usize = 32;
+const PUBLIC_KEY_LEN: usize = 65;
+// DER ECDSA signatures are 70 to 72 bytes.
+const MAX_SIGNATURE: usize = 72;
+
+// Certificate, everything but the signature has a fixed length.
+const TBS_LEN: usize = 204;
+pub const MAX_CERTIFICATE: usize = 4 + TBS_LEN + ECDSA_WITH_SHA256.len() + 3 + MAX_SIGNATURE;
+/// Longest response, a registration.
+pub const MAX_RESPONSE: usize = 1 + PUBLIC_KEY_LEN + 1 + KEY_HANDLE_LEN + MAX_CERTIFICATE + MAX_SIGNATURE + 2;
+
+// Instructions.
+const REGISTER: u8 = 0x01;
+const AUTHENTICATE: u8 = 0x02;
+const VERSION: u8 = 0x03;
+
+// Authenticate's control byte.
+const CHECK_ONLY: u8 = 0x07;
+const ENFORCE_PRESENCE: u8 = 0x03;
+const DONT_ENFORCE_PRESENCE: u8 = 0x08;
+
+// Status words, at the end of every response.
+pub const SW_NO_ERROR: u16 = 0x9000;
+pub const SW_CONDITIONS_NOT_SATISFIED: u16 = 0x6985;
+pub const SW_WRONG_DATA: u16 = 0x6a80;
+pub const SW_WRONG_LENGTH: u16 = 0x6700;
+pub const SW_CLA_NOT_SUPPORTED: u16 = 0x6e00;
+pub const SW_INS_NOT_SUPPORTED: u16 = 0x6d00;
+
+// Certificate pieces. Subject and issuer are both CN=Lynix Badger U2F.
+const ECDSA_WITH_SHA256: &[u8] = b"\x30\x0a\x06\x08\x2a\x86\x48\xce\x3d\x04\x03\x02";
+const NAME: &[u8] = b"\x30\x1b\x31\x19\x30\x17\x06\x03\x55\x04\x03\x0c\x10Lynix Badger U2F";
+const VALIDITY: &[u8] = b"\x30\x1e\x17\x0d200101000000Z\x17\x0d491231235959Z";
+// id-ecPublicKey on prime256v1, then the BIT STRING header for the point.
+const PUBLIC_KEY_INFO: &[u8] = b"\x30\x59\x30\x13\x06\x07\x2a\x86\x48\xce\x3d\x02\x01\x06\x08\x2a\x86\x48\xce\x3d\x03\x01\x07\x03\x42\x00";
+
+/// Whether a request needed someone to press the button.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Presence {
+    NotNeeded,
+    /// Refused for want of it, the host asks again until it's there.
+    Wanted,
+    /// It was used up, the next request that needs it has to wait for another press.
+    Used,
+}
+
+pub struct SecurityKey {
+    master: [u8; 32],
+    attestation: SigningKey,
+    certificate: [u8; MAX_CERTIFICATE],
+    certificate_len: usize,
+}
+
+impl Drop for SecurityKey {
+    fn drop(&mut self) {
+        self.master.zeroize();
+    }
+}
+
+fn status(out: &mut [u8], len: usize, status: u16) -> usize {
+    out[len..len + 2].copy_from_slice(&status.to_be_bytes());
+    len + 2
+}
+
+// Copies `parts` one after the other into `out`, returning the length.
+fn concat(parts: &[&[u8]], out: &mut [u8]) -> usize {
+    let mut len = 0;
+    for part in parts {
+        out[len..len + part.len()].copy_from_slice(part);
+        len += part.len();
+    }
+    len
+}
+
+// The data of a request, short or extended length, with CLA, INS and P1.
+fn parse(request: &[u8]) -> Result<(u8, u8, u8, &[u8]), u16> {
+    let (header, body) = request.split_at_checked(4).ok_or(SW_WRONG_LENGTH)?;
+    let data = match body {
+        // Nothing, or only Le, short or extended.
+        [] | [_] | [0, _, _] => &[][..],
+        // Extended: a zero, then a two byte length, and optionally two bytes of Le.
+        [0, high, low, rest @ ..] => {
+            let len = u16::from_be_bytes([*high, *low]) as usize;
+            match rest.len().checked_sub(len) {
+                Some(0 | 2) => &rest[..len],
+                _ => return Err(SW_WRONG_LENGTH),
+            }
+        }
+        [len, rest @ ..] => match rest.len().checked_sub(*len as usize) {
+            Some(0 | 1) => &rest[..*len as usize],
+            _ => return Err(SW_WRONG_LENGTH),
+        },
+    };
+    Ok((header[0], header[1], header[2], data))
+}
+
+impl SecurityKey {
+    /// A security key from two secrets, which should come from a good entropy source: `master`
+    /// wraps the keys made for sites and `attestation` signs registrations. `None` if
+    /// `attestation` isn't a valid P-256 private key, which is vanishingly unlikely.
+    pub fn new(master: [u8; 32], attestation: &[u8; 32]) -> Option<SecurityKey> {
+        let attestation = SigningKey::from_slice(attestation).ok()?;
+        let mut key = SecurityKey { master, attestation, certificate: [0; MAX_CERTIFICATE], certificate_len: 0 };
+
+        let public = key.attestation.verifying_key().to_encoded_point(false);
+        let mut tbs = [0; TBS_LEN];
+        let tbs_content = TBS_LEN - 3;
+        let len = concat(
+            &[
+                &[0x30, 0x81, tbs_content as u8],
+                // Version 3, serial number 1.
+                b"\xa0\x03\x02\x01\x02\x02\x01\x01",
+                ECDSA_WITH_SHA256,
+                NAME,
+                VALIDITY,
+                NAME,
+                PUBLIC_KEY_INFO,
+                public.as_bytes(),
+            ],
+            &mut tbs,
+        );
+        debug_assert_eq!(len, TBS_LEN);
+
+        let signature: Signature = key.attestation.sign(&tbs);
+        let signature = signature.to_der();
+        let signature = signature.as_bytes();
+        let content = (TBS_LEN + ECDSA_WITH_SHA256.len() + 3 + signature.len()) as u16;
+        let [high, low] = content.to_be_bytes();
+        key.certificate_len = concat(
+            &[&[0x30, 0x82, high, low], &tbs, ECDSA_WITH_SHA256, &[0x03, signature.len() as u8 + 1, 0x00], signature],
+            &mut key.certificate,
+        );
+        Some(key)
+    }
+
+    /// The attestation certificate, DER.
+    pub fn certificate(&self) -> &[u8] {
+        &self.certificate[..self.certificate_len]
+    }
+
+    fn mac(&self, label: &[u8], application: &[u8], nonce: &[u8]) -> Hmac<Sha256> {
+        // Any key length is accepted, so `new_from_slice` can't fail.
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.master).unwrap();
+        mac.update(label);
+        mac.update(application);
+        mac.update(nonce);
+        mac
+    }
+
+    // The private key for a handle, if the handle was made by this badge for `application`.
+    fn key(&self, application: &[u8], handle: &[u8]) -> Option<SigningKey> {
+        let (nonce, tag) = handle.split_at_checked(NONCE_LEN).filter(|_| handle.len() == KEY_HANDLE_LEN)?;
+        self.mac(b"u2f handle", application, nonce).verify_slice(tag).ok()?;
+        let mut secret: [u8; 32] = self.mac(b"u2f key", application, nonce).finalize().into_bytes().into();
+        let key = SigningKey::from_slice(&secret).ok();
+        secret.zeroize();
+        key
+    }
+
+    /// Answers the U2F request `request` into `out`, returning the response's length. `present` is
+    /// whether someone pressed the button to allow it. Registering takes a nonce from `rng`,
+    /// authenticating adds one to `counter`, which has to be saved before the response is sent.
+    /// Nothing more is signed once `counter` reaches `u32::MAX`: sites take a counter that goes
+    /// back down for a cloned key.
+    pub fn process<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        request: &[u8],
+        counter: &mut u32,
+        present: bool,
+        out: &mut [u8; MAX_RESPONSE],
+    ) -> (usize, Presence) {
+        let (class, instruction, control, data) = match parse(request) {
+            Ok(parsed) => parsed,
+            Err(sw) => return (status(out, 0, sw), Presence::NotNeeded),
+        };
+        if class != 0 {
+            return (status(out, 0, SW_CLA_NOT_SUPPORTED), Presence::NotNeeded);
+        }
+
+        match instruction {
+            REGISTER if data.len() != 64 => (status(out, 0, SW_WRONG_LENGTH), Presence::NotNeeded),
+            REGISTER if !present => (status(out, 0, SW_CONDITIONS_NOT_SATISFIED), Presence::Wanted),
+            REGISTER => {
+                let (challenge, application) = data.split_at(32);
+                let mut handle = [0; KEY_HANDLE_LEN];
+                let key = loop {
+                    rng.fill_bytes(&mut handle[..NONCE_LEN]);
+                    let tag = self.mac(b"u2f handle", application, &handle[..NONCE_LEN]).finalize().into_bytes();
+                    handle[NONCE_LEN..].copy_from_slice(&tag);
+                    if let Some(key) = self.key(application, &handle) {
+                        break key;
+                    }
+                };
+                let public = key.verifying_key().to_encoded_point(false);
+                let public: &[u8; PUBLIC_KEY_LEN] = public.as_bytes().try_into().unwrap();
+                (self.registration(application, challenge, &handle, public, out), Presence::Used)
+            }
+            AUTHENTICATE => {
+                let Some((challenge, rest)) = data.split_at_checked(32) else {
+                    return (status(out, 0, SW_WRONG_LENGTH), Presence::NotNeeded);
+                };
+                let Some((application, [len, handle @ ..])) = rest.split_at_checked(32) else {
+                    return (status(out, 0, SW_WRONG_LENGTH), Presence::NotNeeded);
+                };
+                if *len as usize != handle.len() {
+                    return (status(out, 0, SW_WRONG_LENGTH), Presence::NotNeeded);
+                }
+                let Some(key) = self.key(application, handle) else {
+                    return (status(out, 0, SW_WRONG_DATA), Presence::NotNeeded);
+                };
+
+                let (flags, presence) = match control {
+                    // The handle is ours, which the spec says to answer this way.
+                    CHECK_ONLY => return (status(out, 0, SW_CONDITIONS_NOT_SATISFIED), Presence::NotNeeded),
+                    ENFORCE_PRESENCE if !present => return (status(out, 0, SW_CONDITIONS_NOT_SATISFIED), Presence::Wanted),
+                    ENFORCE_PRESENCE => (0x01, Presence::Used),
+                    DONT_ENFORCE_PRESENCE => (0x00, Presence::NotNeeded),
+                    _ => return (status(out, 0, SW_WRONG_DATA), Presence::NotNeeded),
+                };
+                let Some(next) = counter.checked_add(1) else {
+                    return (status(out, 0, SW_CONDITIONS_NOT_SATISFIED), presence);
+                };
+                *counter = next;
+                let signed = authentication_message(application, flags, *counter, challenge);
+                let signature: Signature = key.sign(&signed);
+                let len = concat(&[&[flags], &counter.to_be_bytes(), signature.to_der().as_bytes()], out);
+                (status(out, len, SW_NO_ERROR), presence)
+            }
+            VERSION if data.is_empty() => {
+                let len = concat(&[b"U2F_V2"], out);
+                (status(out, len, SW_NO_ERROR), Presence::NotNeeded)
+            }
+            VERSION => (status(out, 0, SW_WRONG_LENGTH), Presence::NotNeeded),
+            _ => (status(out, 0, SW_INS_NOT_SUPPORTED), Presence::NotNeeded),
+        }
+    }
+
+    // A registration response for a keypair and handle that are already made.
+    fn registration(
+        &self,
+        application: &[u8],
+        challenge: &[u8],
+        handle: &[u8; KEY_HANDLE_LEN],
+        public: &[u8; PUBLIC_KEY_LEN],
+        out: &mut [u8; MAX_RESPONSE],
+    ) -> usize {
+        let signed = registration_message(application, challenge, handle, public);
+        let signature: Signature = self.attestation.sign(&signed);
+        let len = concat(
+            &[&[0x05], public, &[KEY_HANDLE_LEN as u8], handle, self.certificate(), signature.to_der().as_bytes()],
+            out,
+        );
+        status(out, len, SW_NO_ERROR)
+    }
+}
+
+// What the attestation key signs when registering.
+fn registration_message(application: &[u8], challenge: &[u8], handle: &[u8], public: &[u8]) -> [u8; 194] {
+    let mut message = [0; 194];
+    concat(&[&[0x00], application, challenge, handle, public], &mut message);
+    message
+}
+
+// What a site's key signs when authenticating.
+fn authentication_message(application: &[u8], flags: u8, counter: u32, challenge: &[u8]) -> [u8; 69] {
+    let mut message = [0; 69];
+    concat(&[application, &[flags], &counter.to_be_bytes(), challenge], &mut message);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Verifier, VerifyingKey};
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // FIDO U2F Raw Message Formats, section 8.1: the example registration.
+    const ATTESTATION_KEY: &str = "f3fccc0d00d8031954f90864d43c247f4bf5f0665c6b50cc17749a27d1cf7664";
+    const USER_PUBLIC_KEY: &str = "04b174bc49c7ca254b70d2e5c207cee9cf174820ebd77ea3c65508c26da51b657c1cc6b952f8621697936482da0a6d3d3826a59095daf6cd7c03e2e60385d2f6d9";
+    const CHALLENGE: &str = "4142d21c00d94ffb9d504ada8f99b721f4b191ae4e37ca0140f696b6983cfacb";
+    // SHA-256 of "http://example.com".
+    const APPLICATION: &str = "f0e6a6a97042a4f1f1c87f5f7d44315b2d852c2df5c7991cc66241bf7072d1c4";
+    const KEY_HANDLE: &str = "2a552dfdb7477ed65fd84133f86196010b2215b57da75d315b7b9e8fe2e3925a6019551bab61d16591659cbaf00b4950f7abfe6660e2e006f76868b772d70c25";
+    const SIGNATURE: &str = "304502201471899bcc3987e62e8202c9b39c33c19033f7340352dba80fcab017db9230e402210082677d673d891933ade6f617e5dbde2e247e70423fd5ad7804a6d3d3961ef871";
+
+    fn key() -> SecurityKey {
+        SecurityKey::new([1; 32], &hex(ATTESTATION_KEY).try_into().unwrap()).unwrap()
+    }
+
+    fn apdu(instruction: u8, control: u8, data: &[u8]) -> Vec<u8> {
+        let mut apdu = vec![0x00, instruction, control, 0x00, 0x00];
+        apdu.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        apdu.extend_from_slice(data);
+        apdu.extend_from_slice(&[0x00, 0x00]);
+        apdu
+    }
+
+    fn verify(public: &[u8], message: &[u8], der: &[u8]) -> bool {
+        let key = VerifyingKey::from_sec1_bytes(public).unwrap();
+        key.verify(message, &Signature::from_der(der).unwrap()).is_ok()
+    }
+
+    #[test]
+    fn registration_matches_the_spec_example() {
+        let key = key();
+        let (application, challenge, handle, public) = (hex(APPLICATION), hex(CHALLENGE), hex(KEY_HANDLE), hex(USER_PUBLIC_KEY));
+        let attestation = key.attestation.verifying_key().to_encoded_point(false);
+
+        // The spec's signature checks out over what's signed here.
+        let signed = registration_message(&application, &challenge, &handle, &public);
+        assert!(verify(attestation.as_bytes(), &signed, &hex(SIGNATURE)));
+
+        let mut out = [0; MAX_RESPONSE];
+        let len = key.registration(&application, &challenge, &handle.clone().try_into().unwrap(), &public.clone().try_into().unwrap(), &mut out);
+        let response = &out[..len];
+        let mut expected = vec![0x05];
+        expected.extend_from_slice(&public);
+        expected.push(64);
+        expected.extend_from_slice(&handle);
+        expected.extend_from_slice(key.certificate());
+        assert_eq!(response[..expected.len()], expected[..]);
+        assert_eq!(response[len - 2..], [0x90, 0x00]);
+        assert!(verify(attestation.as_bytes(), &signed, &response[expected.len()..len - 2]));
+    }
+
+    #[test]
+    fn certificate() {
+        use x509_cert::der::{Decode, Encode};
+
+        let key = key();
+        let certificate = x509_cert::Certificate::from_der(key.certificate()).unwrap();
+        assert_eq!(certificate.tbs_certificate.subject.to_string(), "CN=Lynix Badger U2F");
+        assert_eq!(certificate.tbs_certificate.issuer, certificate.tbs_certificate.subject);
+        let attestation = key.attestation.verifying_key().to_encoded_point(false);
+        assert_eq!(certificate.tbs_certificate.subject_public_key_info.subject_public_key.raw_bytes(), attestation.as_bytes());
+
+        let tbs = certificate.tbs_certificate.to_der().unwrap();
+        assert_eq!(tbs.len(), TBS_LEN);
+        assert!(verify(attestation.as_bytes(), &tbs, certificate.signature.raw_bytes()));
+    }
+
+    #[test]
+    fn register_then_authenticate() {
+        let key = key();
+        let mut rng = ChaCha20Rng::from_seed([7; 32]);
+        let mut counter = 0;
+        let mut out = [0; MAX_RESPONSE];
+        let (challenge, application) = (hex(CHALLENGE), hex(APPLICATION));
+
+        let register = apdu(REGISTER, 0, &[&challenge[..], &application].concat());
+        let (len, presence) = key.process(&mut rng, &register, &mut counter, false, &mut out);
+        assert_eq!((&out[..len], presence), (&[0x69, 0x85][..], Presence::Wanted));
+
+        let (len, presence) = key.process(&mut rng, &register, &mut counter, true, &mut out);
+        assert_eq!(presence, Presence::Used);
+        assert_eq!(out[len - 2..len], [0x90, 0x00]);
+        let public = out[1..66].to_vec();
+        let handle = out[67..131].to_vec();
+        assert_eq!(out[66], 64);
+
+        let authenticate = |control| apdu(AUTHENTICATE, control, &[&challenge[..], &application, &[64], &handle].concat());
+
+        let (len, presence) = key.process(&mut rng, &authenticate(CHECK_ONLY), &mut counter, true, &mut out);
+        assert_eq!((&out[..len], presence), (&[0x69, 0x85][..], Presence::NotNeeded));
+        let (len, presence) = key.process(&mut rng, &authenticate(ENFORCE_PRESENCE), &mut counter, false, &mut out);
+        assert_eq!((&out[..len], presence), (&[0x69, 0x85][..], Presence::Wanted));
+        assert_eq!(counter, 0);
+
+        let (len, presence) = key.process(&mut rng, &authenticate(ENFORCE_PRESENCE), &mut counter, true, &mut out);
+        assert_eq!(presence, Presence::Used);
+        assert_eq!((out[0], &out[1..5], &out[len - 2..len]), (0x01, &[0, 0, 0, 1][..], &[0x90, 0x00][..]));
+        assert!(verify(&public, &authentication_message(&application, 0x01, 1, &challenge), &out[5..len - 2]));
+
+        let (len, presence) = key.process(&mut rng, &authenticate(DONT_ENFORCE_PRESENCE), &mut counter, false, &mut out);
+        assert_eq!(presence, Presence::NotNeeded);
+        assert_eq!((out[0], &out[1..5]), (0x00, &[0, 0, 0, 2][..]));
+        assert!(verify(&public, &authentication_message(&application, 0x00, 2, &challenge), &out[5..len - 2]));
+        assert_eq!(counter, 2);
+
+        // Worn out, rather than wrapping around to 0.
+        counter = u32::MAX - 1;
+        let (len, _) = key.process(&mut rng, &authenticate(DONT_ENFORCE_PRESENCE), &mut counter, false, &mut out);
+        assert_eq!((&out[1..5], &out[len - 2..len]), (&[0xff; 4][..], &[0x90, 0x00][..]));
+        let (len, presence) = key.process(&mut rng, &authenticate(ENFORCE_PRESENCE), &mut counter, true, &mut out);
+        assert_eq!((&out[..len], presence), (&[0x69, 0x85][..], Presence::Used));
+        assert_eq!(counter, u32::MAX);
+    }
+
+    #[test]
+    fn handles_only_work_for_their_site() {
+        let key = key();
+        let mut rng = ChaCha20Rng::from_seed([7; 32]);
+        let mut counter = 0;
+        let mut out = [0; MAX_RESPONSE];
+        let challenge = hex(CHALLENGE);
+        let register = apdu(REGISTER, 0, &[&challenge[..], &hex(APPLICATION)].concat());
+        key.process(&mut rng, &register, &mut counter, true, &mut out);
+        let handle = out[67..131].to_vec();
+
+        let mut tampered = handle.clone();
+        tampered[40] ^= 1;
+        let other_badge = SecurityKey::new([2; 32], &hex(ATTESTATION_KEY).try_into().unwrap()).unwrap();
+        let cases = [
+            (&key, [0x11; 32].to_vec(), handle.clone()),
+            (&key, hex(APPLICATION), tampered),
+            (&key, hex(APPLICATION), handle[..63].to_vec()),
+            (&other_badge, hex(APPLICATION), handle.clone()),
+        ];
+        for (key, application, handle) in cases {
+            let request = apdu(AUTHENTICATE, ENFORCE_PRESENCE, &[&challenge[..], &application, &[handle.len() as u8], &handle].concat());
+            let (len, _) = key.process(&mut rng, &request, &mut counter, true, &mut out);
+            assert_eq!(out[..len], [0x6a, 0x80]);
+        }
+        assert_eq!(counter, 0);
+    }
+
+    #[test]
+    fn version_and_bad_requests() {
+        let key = key();
+        let mut rng = ChaCha20Rng::from_seed([7; 32]);
+        let mut out = [0; MAX_RESPONSE];
+        let mut answer = |request: &[u8]| {
+            let (len, _) = key.process(&mut rng, request, &mut 0, true, &mut out);
+            out[..len].to_vec()
+        };
+
+        // Extended length with and without Le, and short.
+        for request in [&[0x00, 0x03, 0, 0, 0, 0, 0][..], &[0x00, 0x03, 0, 0], &[0x00, 0x03, 0, 0, 0]] {
+            assert_eq!(answer(request), b"U2F_V2\x90\x00");
+        }
+        assert_eq!(answer(&apdu(VERSION, 0, &[])), b"U2F_V2\x90\x00");
+        assert_eq!(answer(&[0x80, 0x03, 0, 0]), [0x6e, 0x00]);
+        assert_eq!(answer(&[0x00, 0x40, 0, 0]), [0x6d, 0x00]);
+        assert_eq!(answer(&[0x00, 0x03]), [0x67, 0x00]);
+        assert_eq!(answer(&apdu(REGISTER, 0, &[0; 63])), [0x67, 0x00]);
+        assert_eq!(answer(&[0x00, 0x01, 0, 0, 0, 0, 64, 1, 2]), [0x67, 0x00]);
+    }
+}
@@ -0,0 +1,215 @@
+//! Notes encrypted under a PIN.
+//!
+//! The key is PBKDF2-HMAC-SHA256 of the PIN with a random salt, and the notes are sealed with
+//! ChaCha20-Poly1305 under a fresh random nonce every time they're saved. A sealed vault is
+//!
+//! ```text
+//! iterations: u32 LE  salt: [u8; 16]  nonce: [u8; 12]  tag: [u8; 16]  ciphertext
+//! ```
+//!
+//! with the iterations and salt authenticated along with the ciphertext. A wrong PIN and a
+//! tampered vault look the same: the tag doesn't match.
+//!
+//! PINs are short, so someone who reads the flash can try them all. The iterations only make
+//! each guess cost about a second on the badge and much less on a computer.
+
+use core::fmt;
+
+use chacha20poly1305::aead::AeadInPlace;
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce, Tag};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+pub const SALT_LEN: usize = 16;
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = 4 + SALT_LEN;
+/// Bytes a sealed vault has on top of the notes.
+pub const OVERHEAD: usize = HEADER_LEN + NONCE_LEN + TAG_LEN;
+/// PBKDF2 iterations for new vaults, about a second on the RP2040.
+pub const ITERATIONS: u32 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// Wrong PIN, or the vault was changed.
+    WrongPin,
+    /// Too short to be a sealed vault, or asks for more iterations than `ITERATIONS`.
+    Corrupt,
+    /// The result doesn't fit in the buffer.
+    TooLong,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::WrongPin => "wrong PIN",
+            Error::Corrupt => "vault is damaged",
+            Error::TooLong => "notes too long",
+        })
+    }
+}
+
+/// The key for an unlocked vault, wiped when dropped.
+pub struct Vault {
+    key: [u8; 32],
+    iterations: u32,
+    salt: [u8; SALT_LEN],
+}
+
+impl Drop for Vault {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl Vault {
+    /// A new vault for `pin`. `salt` must be random.
+    pub fn create(pin: &[u8], salt: [u8; SALT_LEN], iterations: u32) -> Vault {
+        let mut key = [0; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(pin, &salt, iterations, &mut key);
+        Vault { key, iterations, salt }
+    }
+
+    /// Unlocks `sealed` with `pin` and decrypts the notes into `out`, returning their length.
+    ///
+    /// The iterations are checked before the key is derived, since the tag can only be checked
+    /// after: a changed header asking for billions would keep the badge busy for hours.
+    pub fn open(pin: &[u8], sealed: &[u8], out: &mut [u8]) -> Result<(Vault, usize), Error> {
+        if sealed.len() < OVERHEAD {
+            return Err(Error::Corrupt);
+        }
+        let (header, rest) = sealed.split_at(HEADER_LEN);
+        let (nonce, rest) = rest.split_at(NONCE_LEN);
+        let (tag, ciphertext) = rest.split_at(TAG_LEN);
+
+        let iterations = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        if !(1..=ITERATIONS).contains(&iterations) {
+            return Err(Error::Corrupt);
+        }
+        let mut salt = [0; SALT_LEN];
+        salt.copy_from_slice(&header[4..]);
+        let vault = Vault::create(pin, salt, iterations);
+
+        let out = out.get_mut(..ciphertext.len()).ok_or(Error::TooLong)?;
+        out.copy_from_slice(ciphertext);
+        let cipher = ChaCha20Poly1305::new(&vault.key.into());
+        if cipher.decrypt_in_place_detached(Nonce::from_slice(nonce), header, out, Tag::from_slice(tag)).is_err() {
+            out.zeroize();
+            return Err(Error::WrongPin);
+        }
+        Ok((vault, ciphertext.len()))
+    }
+
+    /// Seals `notes` into `out` under `nonce`, which must never be used twice with this vault:
+    /// pick a random one every time. Returns the sealed length.
+    pub fn seal(&self, nonce: [u8; NONCE_LEN], notes: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+        let len = OVERHEAD + notes.len();
+        let out = out.get_mut(..len).ok_or(Error::TooLong)?;
+        let (header, rest) = out.split_at_mut(HEADER_LEN);
+        let (nonce_out, rest) = rest.split_at_mut(NONCE_LEN);
+        let (tag_out, ciphertext) = rest.split_at_mut(TAG_LEN);
+
+        header[..4].copy_from_slice(&self.iterations.to_le_bytes());
+        header[4..].copy_from_slice(&self.salt);
+        nonce_out.copy_from_slice(&nonce);
+        ciphertext.copy_from_slice(notes);
+
+        let cipher = ChaCha20Poly1305::new(&self.key.into());
+        let tag = cipher
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), header, ciphertext)
+            .map_err(|_| Error::TooLong)?;
+        tag_out.copy_from_slice(&tag);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Few iterations so the tests stay quick.
+    const FAST: u32 = 10;
+
+    fn sealed(pin: &[u8], notes: &[u8]) -> Vec<u8> {
+        let vault = Vault::create(pin, [3; SALT_LEN], FAST);
+        let mut out = vec![0; OVERHEAD + notes.len()];
+        let len = vault.seal([9; NONCE_LEN], notes, &mut out).unwrap();
+        assert_eq!(len, out.len());
+        out
+    }
+
+    #[test]
+    fn round_trip() {
+        let sealed = sealed(b"1234", b"wifi: hunter2\nlocker 42");
+        let mut out = [0; 64];
+        let (vault, len) = Vault::open(b"1234", &sealed, &mut out).unwrap();
+        assert_eq!(&out[..len], b"wifi: hunter2\nlocker 42");
+        assert_eq!(vault.salt, [3; SALT_LEN]);
+        assert_eq!(vault.iterations, FAST);
+        // The notes don't show in the sealed vault.
+        assert!(!sealed.windows(7).any(|window| window == b"hunter2"));
+    }
+
+    #[test]
+    fn wrong_pin() {
+        let sealed = sealed(b"1234", b"secret");
+        let mut out = [0; 64];
+        assert_eq!(Vault::open(b"1235", &sealed, &mut out).err(), Some(Error::WrongPin));
+        assert_eq!(out, [0; 64]);
+    }
+
+    #[test]
+    fn tampering_is_caught() {
+        let sealed = sealed(b"1234", b"secret");
+        let mut out = [0; 64];
+        for index in 0..sealed.len() {
+            let mut changed = sealed.clone();
+            changed[index] ^= 1;
+            // Fewer iterations than that in the header would make this slow.
+            if index < 4 {
+                changed[..4].copy_from_slice(&(FAST + 1).to_le_bytes());
+            }
+            assert_eq!(Vault::open(b"1234", &changed, &mut out).err(), Some(Error::WrongPin), "byte {}", index);
+        }
+        assert_eq!(Vault::open(b"1234", &sealed[..OVERHEAD - 1], &mut out).err(), Some(Error::Corrupt));
+    }
+
+    #[test]
+    fn iterations_are_bounded() {
+        let mut out = [0; 64];
+        for iterations in [0, ITERATIONS + 1, u32::MAX] {
+            let mut changed = sealed(b"1234", b"secret");
+            changed[..4].copy_from_slice(&iterations.to_le_bytes());
+            assert_eq!(Vault::open(b"1234", &changed, &mut out).err(), Some(Error::Corrupt), "{}", iterations);
+        }
+    }
+
+    #[test]
+    fn fresh_nonce_changes_everything() {
+        let vault = Vault::create(b"1234", [3; SALT_LEN], FAST);
+        let mut a = [0; OVERHEAD + 6];
+        let mut b = [0; OVERHEAD + 6];
+        vault.seal([1; NONCE_LEN], b"secret", &mut a).unwrap();
+        vault.seal([2; NONCE_LEN], b"secret", &mut b).unwrap();
+        assert_eq!(a[..HEADER_LEN], b[..HEADER_LEN]);
+        assert_ne!(a[HEADER_LEN + NONCE_LEN..], b[HEADER_LEN + NONCE_LEN..]);
+    }
+
+    #[test]
+    fn buffers_too_small() {
+        let vault = Vault::create(b"1234", [3; SALT_LEN], FAST);
+        let mut small = [0; OVERHEAD + 5];
+        assert_eq!(vault.seal([1; NONCE_LEN], b"secret", &mut small), Err(Error::TooLong));
+
+        let sealed = sealed(b"1234", b"secret");
+        assert_eq!(Vault::open(b"1234", &sealed, &mut [0; 5]).err(), Some(Error::TooLong));
+    }
+
+    #[test]
+    fn pbkdf2_vector() {
+        // RFC 7914 section 11, PBKDF2-HMAC-SHA256 with one iteration.
+        let mut key = [0; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(b"passwd", b"salt", 1, &mut key);
+        assert_eq!(key[..8], [0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f]);
+    }
+}
@@ -0,0 +1,359 @@
+//! The LXSC badge screen format: screens described as elements, stored in flash so the same
+//! firmware can show a different person's badge.
+//!
+//! `tools/screenc` encodes it and the firmware decodes it, both with the code here, so the two
+//! can't drift apart. Everything is `no_std` and allocation free, so it's tested on the host.
+//!
+//! All integers are little-endian.
+//!
+//! ```text
+//! header  "LXSC"  version: u8 (1)  screens: u8  length: u16  crc32: u32
+//! body    `length` bytes, CRC-32 (IEEE) in the header covers them
+//! ```
+//!
+//! Each screen is an element count (u8) followed by that many elements:
+//!
+//! ```text
+//! 0x01 image  x: i16  y: i16  name_len: u8  name         (manifest handle, e.g. LYNIX)
+//! 0x02 text   x: i16  y: i16  width: u16  font: u8  align: u8  len: u16  utf-8
+//! 0x03 qr     x: i16  y: i16  scale: u8  len: u16  payload
+//! 0x04 rect   x: i16  y: i16  width: u16  height: u16  fill: u8  stroke: u8
+//! ```
+//!
+//! `font` is a ProFont point size, one of [`FONTS`]. `align` is 0 left, 1 center, 2 right.
+//! Rectangle `fill` is 0 none, 1 black, 2 white; `stroke` is a black border width.
+
+#![cfg_attr(not(test), no_std)]
+
+pub const MAGIC: &[u8; 4] = b"LXSC";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 12;
+
+/// ProFont sizes the firmware has, in points.
+pub const FONTS: [u8; 7] = [7, 9, 10, 12, 14, 18, 24];
+
+const IMAGE: u8 = 0x01;
+const TEXT: u8 = 0x02;
+const QR: u8 = 0x03;
+const RECT: u8 = 0x04;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fill {
+    None,
+    Black,
+    White,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Element<'a> {
+    Image { x: i16, y: i16, name: &'a str },
+    Text { x: i16, y: i16, width: u16, font: u8, align: Align, text: &'a str },
+    Qr { x: i16, y: i16, scale: u8, payload: &'a str },
+    Rect { x: i16, y: i16, width: u16, height: u16, fill: Fill, stroke: u8 },
+}
+
+/// A string, or the whole body, is longer than its length field can say.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TooLong;
+
+#[derive(Clone, Copy)]
+pub struct Screens<'a> {
+    count: usize,
+    body: &'a [u8],
+}
+
+#[derive(Clone, Copy)]
+pub struct Screen<'a> {
+    count: usize,
+    data: &'a [u8],
+}
+
+/// Checks a whole blob up front, so drawing never has to deal with malformed data.
+pub fn parse(data: &[u8]) -> Option<Screens<'_>> {
+    let mut header = Reader::new(data);
+    if header.bytes(4)? != MAGIC || header.u8()? != VERSION {
+        return None;
+    }
+    let count = header.u8()? as usize;
+    let length = header.u16()? as usize;
+    let crc = header.u32()?;
+
+    let body = data.get(HEADER_LEN..HEADER_LEN + length)?;
+    if crc32(body) != crc {
+        return None;
+    }
+
+    let mut reader = Reader::new(body);
+    for _ in 0..count {
+        read_screen(&mut reader)?;
+    }
+    Some(Screens { count, body })
+}
+
+/// The header for a body of `count` screens, each an element count and then the elements.
+pub fn header(count: u8, body: &[u8]) -> Result<[u8; HEADER_LEN], TooLong> {
+    let length = u16::try_from(body.len()).map_err(|_| TooLong)?;
+    let mut header = [0; HEADER_LEN];
+    header[..4].copy_from_slice(MAGIC);
+    header[4] = VERSION;
+    header[5] = count;
+    header[6..8].copy_from_slice(&length.to_le_bytes());
+    header[8..].copy_from_slice(&crc32(body).to_le_bytes());
+    Ok(header)
+}
+
+impl<'a> Screens<'a> {
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<Screen<'a>> {
+        if index >= self.count {
+            return None;
+        }
+        let mut reader = Reader::new(self.body);
+        for _ in 0..index {
+            read_screen(&mut reader)?;
+        }
+        read_screen(&mut reader)
+    }
+}
+
+impl<'a> Screen<'a> {
+    pub fn elements(&self) -> impl Iterator<Item = Result<Element<'a>, ()>> + 'a {
+        let mut reader = Reader::new(self.data);
+        (0..self.count).map(move |_| read_element(&mut reader).ok_or(()))
+    }
+}
+
+impl Element<'_> {
+    /// Appends the element's bytes to `out`.
+    pub fn encode(&self, out: &mut impl Extend<u8>) -> Result<(), TooLong> {
+        match *self {
+            Element::Image { x, y, name } => {
+                let len = u8::try_from(name.len()).map_err(|_| TooLong)?;
+                push_position(out, IMAGE, x, y);
+                out.extend([len]);
+                out.extend(name.bytes());
+            }
+            Element::Text { x, y, width, font, align, text } => {
+                push_position(out, TEXT, x, y);
+                out.extend(width.to_le_bytes());
+                out.extend([font, align as u8]);
+                push_string(out, text)?;
+            }
+            Element::Qr { x, y, scale, payload } => {
+                push_position(out, QR, x, y);
+                out.extend([scale]);
+                push_string(out, payload)?;
+            }
+            Element::Rect { x, y, width, height, fill, stroke } => {
+                push_position(out, RECT, x, y);
+                out.extend(width.to_le_bytes());
+                out.extend(height.to_le_bytes());
+                out.extend([fill as u8, stroke]);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn push_position(out: &mut impl Extend<u8>, tag: u8, x: i16, y: i16) {
+    out.extend([tag]);
+    out.extend(x.to_le_bytes());
+    out.extend(y.to_le_bytes());
+}
+
+fn push_string(out: &mut impl Extend<u8>, text: &str) -> Result<(), TooLong> {
+    let len = u16::try_from(text.len()).map_err(|_| TooLong)?;
+    out.extend(len.to_le_bytes());
+    out.extend(text.bytes());
+    Ok(())
+}
+
+// Walks past one screen's elements to find where it ends.
+fn read_screen<'a>(reader: &mut Reader<'a>) -> Option<Screen<'a>> {
+    let count = reader.u8()? as usize;
+    let start = reader.pos;
+    for _ in 0..count {
+        read_element(reader)?;
+    }
+    Some(Screen { count, data: &reader.data[start..reader.pos] })
+}
+
+fn read_element<'a>(reader: &mut Reader<'a>) -> Option<Element<'a>> {
+    let tag = reader.u8()?;
+    let x = reader.i16()?;
+    let y = reader.i16()?;
+
+    match tag {
+        IMAGE => {
+            let len = reader.u8()? as usize;
+            Some(Element::Image { x, y, name: reader.str(len)? })
+        }
+        TEXT => {
+            let width = reader.u16()?;
+            let font = Some(reader.u8()?).filter(|font| FONTS.contains(font))?;
+            let align = match reader.u8()? {
+                0 => Align::Left,
+                1 => Align::Center,
+                2 => Align::Right,
+                _ => return None,
+            };
+            let len = reader.u16()? as usize;
+            Some(Element::Text { x, y, width, font, align, text: reader.str(len)? })
+        }
+        QR => {
+            let scale = reader.u8()?;
+            let len = reader.u16()? as usize;
+            Some(Element::Qr { x, y, scale, payload: reader.str(len)? })
+        }
+        RECT => {
+            let width = reader.u16()?;
+            let height = reader.u16()?;
+            let fill = match reader.u8()? {
+                0 => Fill::None,
+                1 => Fill::Black,
+                2 => Fill::White,
+                _ => return None,
+            };
+            let stroke = reader.u8()?;
+            Some(Element::Rect { x, y, width, height, fill, stroke })
+        }
+        _ => None,
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn str(&mut self, len: usize) -> Option<&'a str> {
+        core::str::from_utf8(self.bytes(len)?).ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let b = self.bytes(2)?;
+        Some(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let b = self.bytes(4)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// CRC-32 (IEEE 802.3), bitwise. Blobs are a few KB and only checked when a screen is drawn,
+/// so a table isn't worth the flash.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARD: [Element; 4] = [
+        Element::Image { x: 0, y: 0, name: "LYNIX" },
+        Element::Text { x: 140, y: 32, width: 156, font: 14, align: Align::Center, text: "Cybersecurity Student\nCanadian" },
+        Element::Qr { x: 230, y: 62, scale: 2, payload: "https://lynix.ca" },
+        Element::Rect { x: 0, y: 0, width: 296, height: 128, fill: Fill::None, stroke: 2 },
+    ];
+
+    // Two screens: the card, then one with just its border.
+    fn blob() -> Vec<u8> {
+        let mut body = vec![CARD.len() as u8];
+        for element in &CARD {
+            element.encode(&mut body).unwrap();
+        }
+        body.push(1);
+        CARD[3].encode(&mut body).unwrap();
+
+        let mut blob = header(2, &body).unwrap().to_vec();
+        blob.extend_from_slice(&body);
+        blob
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let blob = blob();
+        let screens = parse(&blob).unwrap();
+        assert_eq!(screens.len(), 2);
+
+        let card: Vec<_> = screens.get(0).unwrap().elements().map(Result::unwrap).collect();
+        assert_eq!(card, CARD);
+        let border: Vec<_> = screens.get(1).unwrap().elements().map(Result::unwrap).collect();
+        assert_eq!(border, [CARD[3]]);
+        assert!(screens.get(2).is_none());
+    }
+
+    #[test]
+    fn rejects_damage() {
+        let blob = blob();
+
+        let mut flipped = blob.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(parse(&flipped).is_none());
+
+        assert!(parse(&blob[..blob.len() - 1]).is_none());
+        assert!(parse(&[0xff; 64]).is_none());
+
+        // A valid CRC over an element the firmware can't draw.
+        let mut body = vec![1];
+        Element::Text { x: 0, y: 0, width: 10, font: 8, align: Align::Left, text: "x" }.encode(&mut body).unwrap();
+        let mut blob = header(1, &body).unwrap().to_vec();
+        blob.extend_from_slice(&body);
+        assert!(parse(&blob).is_none());
+    }
+
+    #[test]
+    fn too_long() {
+        let name = "x".repeat(256);
+        assert_eq!(Element::Image { x: 0, y: 0, name: &name }.encode(&mut Vec::new()), Err(TooLong));
+        assert_eq!(header(1, &[0; 65536]), Err(TooLong));
+    }
+}
@@ -0,0 +1,255 @@
+//! Which keys type which characters, for the keyboard layout the host has set.
+//!
+//! A USB keyboard sends key positions, not characters: the host turns them into characters with
+//! its own layout, so the badge has to know that layout to type text. Key codes are from the HID
+//! Usage Tables, keyboard page (0x07), named after the key on a US keyboard.
+
+use crate::Report;
+
+/// Left Shift, as a report's modifier bit.
+pub const SHIFT: u8 = 0x02;
+/// Right Alt, AltGr on European layouts.
+pub const ALT_GR: u8 = 0x40;
+
+const SPACE: u8 = 0x2c;
+/// Space, to follow a dead key with.
+pub(crate) const SPACE_REPORT: Report = Report { modifiers: 0, key: SPACE };
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    #[default]
+    Us,
+    Uk,
+    De,
+}
+
+/// The keys for one character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stroke {
+    pub report: Report,
+    /// A dead key, which waits for the next one to put an accent on: Space after it gives the
+    /// character itself.
+    pub dead: bool,
+}
+
+fn key(key: u8) -> Option<Stroke> {
+    Some(Stroke { report: Report { modifiers: 0, key }, dead: false })
+}
+
+fn shift(key: u8) -> Option<Stroke> {
+    Some(Stroke { report: Report { modifiers: SHIFT, key }, dead: false })
+}
+
+fn alt_gr(key: u8) -> Option<Stroke> {
+    Some(Stroke { report: Report { modifiers: ALT_GR, key }, dead: false })
+}
+
+fn dead(stroke: Option<Stroke>) -> Option<Stroke> {
+    stroke.map(|stroke| Stroke { dead: true, ..stroke })
+}
+
+// Letters, digits and the whitespace keys, where every layout here agrees.
+fn common(c: char) -> Option<Stroke> {
+    match c {
+        'a'..='z' => key(0x04 + (c as u8 - b'a')),
+        'A'..='Z' => shift(0x04 + (c as u8 - b'A')),
+        '1'..='9' => key(0x1e + (c as u8 - b'1')),
+        '0' => key(0x27),
+        '\n' => key(0x28),
+        '\t' => key(0x2b),
+        ' ' => key(SPACE),
+        _ => None,
+    }
+}
+
+fn us(c: char) -> Option<Stroke> {
+    match c {
+        '!' => shift(0x1e),
+        '@' => shift(0x1f),
+        '#' => shift(0x20),
+        '$' => shift(0x21),
+        '%' => shift(0x22),
+        '^' => shift(0x23),
+        '&' => shift(0x24),
+        '*' => shift(0x25),
+        '(' => shift(0x26),
+        ')' => shift(0x27),
+        '-' => key(0x2d),
+        '_' => shift(0x2d),
+        '=' => key(0x2e),
+        '+' => shift(0x2e),
+        '[' => key(0x2f),
+        '{' => shift(0x2f),
+        ']' => key(0x30),
+        '}' => shift(0x30),
+        '\\' => key(0x31),
+        '|' => shift(0x31),
+        ';' => key(0x33),
+        ':' => shift(0x33),
+        '\'' => key(0x34),
+        '"' => shift(0x34),
+        '`' => key(0x35),
+        '~' => shift(0x35),
+        ',' => key(0x36),
+        '<' => shift(0x36),
+        '.' => key(0x37),
+        '>' => shift(0x37),
+        '/' => key(0x38),
+        '?' => shift(0x38),
+        _ => common(c),
+    }
+}
+
+// UK keyboards differ from US ones in a handful of keys, including the one left of Z (0x64) and
+// the one left of Enter (0x32).
+fn uk(c: char) -> Option<Stroke> {
+    match c {
+        '"' => shift(0x1f),
+        '£' => shift(0x20),
+        '@' => shift(0x34),
+        '#' => key(0x32),
+        '~' => shift(0x32),
+        '\\' => key(0x64),
+        '|' => shift(0x64),
+        '¬' => shift(0x35),
+        _ => us(c),
+    }
+}
+
+// German QWERTZ: Y and Z swapped, and most symbols moved or behind AltGr.
+fn de(c: char) -> Option<Stroke> {
+    match c {
+        'y' => key(0x1d),
+        'z' => key(0x1c),
+        'Y' => shift(0x1d),
+        'Z' => shift(0x1c),
+        '!' => shift(0x1e),
+        '"' => shift(0x1f),
+        '§' => shift(0x20),
+        '$' => shift(0x21),
+        '%' => shift(0x22),
+        '&' => shift(0x23),
+        '/' => shift(0x24),
+        '(' => shift(0x25),
+        ')' => shift(0x26),
+        '=' => shift(0x27),
+        'ß' => key(0x2d),
+        '?' => shift(0x2d),
+        '´' => dead(key(0x2e)),
+        '`' => dead(shift(0x2e)),
+        'ü' => key(0x2f),
+        'Ü' => shift(0x2f),
+        '+' => key(0x30),
+        '*' => shift(0x30),
+        '#' => key(0x32),
+        '\'' => shift(0x32),
+        'ö' => key(0x33),
+        'Ö' => shift(0x33),
+        'ä' => key(0x34),
+        'Ä' => shift(0x34),
+        '^' => dead(key(0x35)),
+        '°' => shift(0x35),
+        ',' => key(0x36),
+        ';' => shift(0x36),
+        '.' => key(0x37),
+        ':' => shift(0x37),
+        '-' => key(0x38),
+        '_' => shift(0x38),
+        '<' => key(0x64),
+        '>' => shift(0x64),
+        '²' => alt_gr(0x1f),
+        '³' => alt_gr(0x20),
+        '{' => alt_gr(0x24),
+        '[' => alt_gr(0x25),
+        ']' => alt_gr(0x26),
+        '}' => alt_gr(0x27),
+        '\\' => alt_gr(0x2d),
+        '@' => alt_gr(0x14),
+        '€' => alt_gr(0x08),
+        '~' => alt_gr(0x30),
+        'µ' => alt_gr(0x10),
+        '|' => alt_gr(0x64),
+        _ => common(c),
+    }
+}
+
+impl Layout {
+    /// `us`, `uk` or `de`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "us" => Some(Layout::Us),
+            "uk" => Some(Layout::Uk),
+            "de" => Some(Layout::De),
+            _ => None,
+        }
+    }
+
+    /// How to type `c`, or `None` if it isn't on this layout's keys.
+    pub fn stroke(self, c: char) -> Option<Stroke> {
+        match self {
+            Layout::Us => us(c),
+            Layout::Uk => uk(c),
+            Layout::De => de(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(layout: Layout, c: char) -> (u8, u8) {
+        let stroke = layout.stroke(c).unwrap();
+        (stroke.report.modifiers, stroke.report.key)
+    }
+
+    #[test]
+    fn printable_ascii_on_every_layout() {
+        for layout in [Layout::Us, Layout::Uk, Layout::De] {
+            for c in ' '..='~' {
+                assert!(layout.stroke(c).is_some(), "{:?} can't type {:?}", layout, c);
+            }
+        }
+        assert_eq!(Layout::Us.stroke('é'), None);
+        assert_eq!(Layout::Us.stroke('£'), None);
+    }
+
+    #[test]
+    fn us() {
+        assert_eq!(report(Layout::Us, 'a'), (0, 0x04));
+        assert_eq!(report(Layout::Us, 'Z'), (SHIFT, 0x1d));
+        assert_eq!(report(Layout::Us, '0'), (0, 0x27));
+        assert_eq!(report(Layout::Us, '@'), (SHIFT, 0x1f));
+        assert_eq!(report(Layout::Us, '"'), (SHIFT, 0x34));
+        assert_eq!(report(Layout::Us, '\n'), (0, 0x28));
+    }
+
+    #[test]
+    fn uk() {
+        assert_eq!(report(Layout::Uk, '"'), (SHIFT, 0x1f));
+        assert_eq!(report(Layout::Uk, '@'), (SHIFT, 0x34));
+        assert_eq!(report(Layout::Uk, '£'), (SHIFT, 0x20));
+        assert_eq!(report(Layout::Uk, '\\'), (0, 0x64));
+        // The rest is as on US keyboards.
+        assert_eq!(report(Layout::Uk, '/'), report(Layout::Us, '/'));
+        assert_eq!(report(Layout::Uk, 'q'), report(Layout::Us, 'q'));
+    }
+
+    #[test]
+    fn de() {
+        assert_eq!(report(Layout::De, 'z'), (0, 0x1c));
+        assert_eq!(report(Layout::De, 'Y'), (SHIFT, 0x1d));
+        assert_eq!(report(Layout::De, '/'), (SHIFT, 0x24));
+        assert_eq!(report(Layout::De, '@'), (ALT_GR, 0x14));
+        assert_eq!(report(Layout::De, 'ü'), (0, 0x2f));
+        assert_eq!(report(Layout::De, '-'), (0, 0x38));
+        assert!(Layout::De.stroke('^').unwrap().dead);
+        assert!(!Layout::De.stroke('°').unwrap().dead);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(Layout::from_name("de"), Some(Layout::De));
+        assert_eq!(Layout::from_name("fr"), None);
+    }
+}
@@ -0,0 +1,449 @@
+//! A small DuckyScript-style language for typing on a computer from a USB keyboard.
+//!
+//! One command per line:
+//!
+//! ```text
+//! REM a comment                 ignored, as are blank lines
+//! STRING text                   types the text, exactly as written after the first space
+//! STRINGLN text                 the same, then Enter
+//! DELAY 500                     waits, in milliseconds
+//! DEFAULT_DELAY 100             waits this long after every later command (DEFAULTDELAY too)
+//! REPEAT 3                      runs the command before it 3 more times
+//! GUI r                         presses keys together: modifiers, then at most one other key
+//! CTRL-ALT-DELETE               the keys can be joined with `-` too
+//! ```
+//!
+//! The modifiers are CTRL (CONTROL), SHIFT, ALT and GUI (WINDOWS, COMMAND). Other keys are named
+//! (ENTER, ESC, TAB, SPACE, BACKSPACE, DELETE, INSERT, HOME, END, PAGEUP, PAGEDOWN, UP, DOWN, LEFT,
+//! RIGHT, F1 to F12, CAPSLOCK, NUMLOCK, SCROLLLOCK, PRINTSCREEN, PAUSE, MENU) or a single character
+//! of the keyboard layout. Commands and key names don't care about case.
+//!
+//! `Script` turns a script into the keyboard reports to send, `check` finds mistakes without
+//! running it. Everything is `no_std` and allocation free, and tested on the host.
+
+#![cfg_attr(not(test), no_std)]
+
+use core::fmt;
+use core::iter::Enumerate;
+use core::str::{Chars, Lines};
+
+pub mod layout;
+
+pub use layout::Layout;
+
+/// Keys held down: modifier bits (Ctrl, Shift, Alt, GUI, left then right) and one key code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub modifiers: u8,
+    pub key: u8,
+}
+
+impl Report {
+    /// Nothing held down.
+    pub const RELEASE: Report = Report { modifiers: 0, key: 0 };
+}
+
+/// Something for the keyboard to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Send this report, after the last one has been taken.
+    Report(Report),
+    /// Wait this many milliseconds.
+    Delay(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Not a command, modifier or key name.
+    UnknownKey,
+    /// A character the keyboard layout can't type.
+    Untypeable(char),
+    /// DELAY, DEFAULT_DELAY or REPEAT without a number of them.
+    BadNumber,
+    /// REPEAT with no command before it.
+    NothingToRepeat,
+    /// More than one key besides the modifiers.
+    TooManyKeys,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error {
+    /// Counting from 1.
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match self.kind {
+            ErrorKind::UnknownKey => write!(f, "unknown command or key"),
+            ErrorKind::Untypeable(c) => write!(f, "{:?} isn't on the keyboard layout", c),
+            ErrorKind::BadNumber => write!(f, "expected a number"),
+            ErrorKind::NothingToRepeat => write!(f, "REPEAT needs a command before it"),
+            ErrorKind::TooManyKeys => write!(f, "only one key besides CTRL, SHIFT, ALT and GUI"),
+        }
+    }
+}
+
+// What a line asks for.
+#[derive(Clone, Copy)]
+enum Line<'a> {
+    Nothing,
+    Run(Command<'a>),
+    DefaultDelay(u32),
+    Repeat(u32),
+}
+
+// A line that does something, and can be repeated.
+#[derive(Clone, Copy)]
+enum Command<'a> {
+    Type { text: &'a str, enter: bool },
+    Delay(u32),
+    Press(Report),
+}
+
+const MODIFIERS: &[(&str, u8)] = &[
+    ("CTRL", 0x01),
+    ("CONTROL", 0x01),
+    ("SHIFT", 0x02),
+    ("ALT", 0x04),
+    ("GUI", 0x08),
+    ("WINDOWS", 0x08),
+    ("COMMAND", 0x08),
+];
+
+const KEYS: &[(&str, u8)] = &[
+    ("ENTER", 0x28),
+    ("ESC", 0x29),
+    ("ESCAPE", 0x29),
+    ("BACKSPACE", 0x2a),
+    ("TAB", 0x2b),
+    ("SPACE", 0x2c),
+    ("CAPSLOCK", 0x39),
+    ("PRINTSCREEN", 0x46),
+    ("SCROLLLOCK", 0x47),
+    ("PAUSE", 0x48),
+    ("BREAK", 0x48),
+    ("INSERT", 0x49),
+    ("HOME", 0x4a),
+    ("PAGEUP", 0x4b),
+    ("DELETE", 0x4c),
+    ("DEL", 0x4c),
+    ("END", 0x4d),
+    ("PAGEDOWN", 0x4e),
+    ("RIGHT", 0x4f),
+    ("RIGHTARROW", 0x4f),
+    ("LEFT", 0x50),
+    ("LEFTARROW", 0x50),
+    ("DOWN", 0x51),
+    ("DOWNARROW", 0x51),
+    ("UP", 0x52),
+    ("UPARROW", 0x52),
+    ("NUMLOCK", 0x53),
+    ("MENU", 0x65),
+    ("APP", 0x65),
+];
+
+fn lookup(table: &[(&str, u8)], name: &str) -> Option<u8> {
+    table.iter().find(|(known, _)| known.eq_ignore_ascii_case(name)).map(|&(_, code)| code)
+}
+
+fn named_key(name: &str) -> Option<u8> {
+    if let Some(key) = lookup(KEYS, name) {
+        return Some(key);
+    }
+    // F1 to F12.
+    let number = name.strip_prefix(['F', 'f'])?.parse::<u8>().ok()?;
+    (1..=12).contains(&number).then(|| 0x3a + number - 1)
+}
+
+// Keys pressed together, like `CTRL ALT DELETE` or `GUI-r`.
+fn combo(line: &str, layout: Layout) -> Result<Report, ErrorKind> {
+    let mut report = Report::RELEASE;
+    let words = line.split_whitespace().flat_map(|word| {
+        // A lone `-` is the minus key, not a separator.
+        let separator = if word == "-" { ' ' } else { '-' };
+        word.split(separator)
+    });
+    for word in words.filter(|word| !word.is_empty()) {
+        if let Some(bit) = lookup(MODIFIERS, word) {
+            report.modifiers |= bit;
+            continue;
+        }
+        let key = match named_key(word) {
+            Some(key) => key,
+            None => {
+                let mut chars = word.chars();
+                let (Some(c), None) = (chars.next(), chars.next()) else {
+                    return Err(ErrorKind::UnknownKey);
+                };
+                // Letters are keys here, `CTRL C` is Ctrl and C, not Ctrl, Shift and C.
+                let stroke = layout.stroke(c.to_ascii_lowercase()).ok_or(ErrorKind::Untypeable(c))?;
+                report.modifiers |= stroke.report.modifiers;
+                stroke.report.key
+            }
+        };
+        if report.key != 0 {
+            return Err(ErrorKind::TooManyKeys);
+        }
+        report.key = key;
+    }
+    Ok(report)
+}
+
+fn number(text: Option<&str>) -> Result<u32, ErrorKind> {
+    text.and_then(|text| text.trim().parse().ok()).ok_or(ErrorKind::BadNumber)
+}
+
+fn parse(line: &str, layout: Layout) -> Result<Line<'_>, ErrorKind> {
+    let line = line.trim_start();
+    let (command, rest) = match line.split_once(' ') {
+        Some((command, rest)) => (command, Some(rest)),
+        None => (line, None),
+    };
+    let command_is = |name: &str| command.eq_ignore_ascii_case(name);
+
+    Ok(if line.trim().is_empty() || command_is("REM") {
+        Line::Nothing
+    } else if command_is("STRING") || command_is("STRINGLN") {
+        let text = rest.unwrap_or("");
+        if let Some(c) = text.chars().find(|&c| layout.stroke(c).is_none()) {
+            return Err(ErrorKind::Untypeable(c));
+        }
+        Line::Run(Command::Type { text, enter: command_is("STRINGLN") })
+    } else if command_is("DELAY") {
+        Line::Run(Command::Delay(number(rest)?))
+    } else if command_is("DEFAULT_DELAY") || command_is("DEFAULTDELAY") {
+        Line::DefaultDelay(number(rest)?)
+    } else if command_is("REPEAT") {
+        Line::Repeat(number(rest)?)
+    } else {
+        Line::Run(Command::Press(combo(line, layout)?))
+    })
+}
+
+/// Finds the first mistake in a script, without running it.
+pub fn check(script: &str, layout: Layout) -> Result<(), Error> {
+    let mut runnable = false;
+    for (index, line) in script.lines().enumerate() {
+        let error = |kind| Error { line: index + 1, kind };
+        match parse(line, layout).map_err(error)? {
+            Line::Run(_) => runnable = true,
+            Line::Repeat(_) if !runnable => return Err(error(ErrorKind::NothingToRepeat)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// A script being run: an iterator over the actions that type it, which stops after an error.
+pub struct Script<'a> {
+    layout: Layout,
+    lines: Enumerate<Lines<'a>>,
+    // Line number of the command running, for errors.
+    line: usize,
+    default_delay: u32,
+    // Last command run, for REPEAT, and how many more times to run it.
+    last: Option<Command<'a>>,
+    repeats: u32,
+    // Text still to type, then whether to press Enter, until the command running is finished.
+    text: Chars<'a>,
+    finish: Option<bool>,
+    queue: [Action; 4],
+    queued: usize,
+    taken: usize,
+    failed: bool,
+}
+
+impl<'a> Script<'a> {
+    pub fn new(script: &'a str, layout: Layout) -> Self {
+        Script {
+            layout,
+            lines: script.lines().enumerate(),
+            line: 0,
+            default_delay: 0,
+            last: None,
+            repeats: 0,
+            text: "".chars(),
+            finish: None,
+            queue: [Action::Delay(0); 4],
+            queued: 0,
+            taken: 0,
+            failed: false,
+        }
+    }
+
+    fn push(&mut self, action: Action) {
+        self.queue[self.queued] = action;
+        self.queued += 1;
+    }
+
+    // Press and let go.
+    fn tap(&mut self, report: Report) {
+        self.push(Action::Report(report));
+        self.push(Action::Report(Report::RELEASE));
+    }
+
+    fn start(&mut self, command: Command<'a>) {
+        let enter = match command {
+            Command::Type { text, enter } => {
+                self.text = text.chars();
+                enter
+            }
+            Command::Delay(ms) => {
+                self.push(Action::Delay(ms));
+                false
+            }
+            Command::Press(report) => {
+                self.tap(report);
+                false
+            }
+        };
+        self.finish = Some(enter);
+    }
+
+    fn fail(&mut self, kind: ErrorKind) -> Option<Result<Action, Error>> {
+        self.failed = true;
+        Some(Err(Error { line: self.line, kind }))
+    }
+}
+
+impl<'a> Iterator for Script<'a> {
+    type Item = Result<Action, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.failed {
+                return None;
+            }
+            if self.taken < self.queued {
+                self.taken += 1;
+                return Some(Ok(self.queue[self.taken - 1]));
+            }
+            self.queued = 0;
+            self.taken = 0;
+
+            // Lines are checked as they're read, so every character here can be typed.
+            if let Some(c) = self.text.next() {
+                if let Some(stroke) = self.layout.stroke(c) {
+                    self.tap(stroke.report);
+                    if stroke.dead {
+                        self.tap(layout::SPACE_REPORT);
+                    }
+                }
+                continue;
+            }
+            if let Some(enter) = self.finish.take() {
+                if enter {
+                    self.tap(Report { modifiers: 0, key: 0x28 });
+                }
+                if self.default_delay > 0 {
+                    self.push(Action::Delay(self.default_delay));
+                }
+                continue;
+            }
+            if let (Some(command), 1..) = (self.last, self.repeats) {
+                self.repeats -= 1;
+                self.start(command);
+                continue;
+            }
+
+            let (index, line) = self.lines.next()?;
+            self.line = index + 1;
+            match parse(line, self.layout) {
+                Err(kind) => return self.fail(kind),
+                Ok(Line::Nothing) => {}
+                Ok(Line::DefaultDelay(ms)) => self.default_delay = ms,
+                Ok(Line::Repeat(_)) if self.last.is_none() => return self.fail(ErrorKind::NothingToRepeat),
+                Ok(Line::Repeat(count)) => self.repeats = count,
+                Ok(Line::Run(command)) => {
+                    self.last = Some(command);
+                    self.start(command);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use layout::SHIFT;
+
+    fn run(script: &str, layout: Layout) -> Result<Vec<Action>, Error> {
+        Script::new(script, layout).collect()
+    }
+
+    fn press(modifiers: u8, key: u8) -> [Action; 2] {
+        [Action::Report(Report { modifiers, key }), Action::Report(Report::RELEASE)]
+    }
+
+    #[test]
+    fn strings() {
+        let actions = run("REM says hi\n\nSTRING Hi!\nSTRINGLN  x", Layout::Us).unwrap();
+        let expected = [press(SHIFT, 0x0b), press(0, 0x0c), press(SHIFT, 0x1e), press(0, 0x2c), press(0, 0x1b), press(0, 0x28)].concat();
+        assert_eq!(actions, expected);
+
+        assert_eq!(run("STRING", Layout::Us).unwrap(), []);
+        assert_eq!(run("string a\r\n", Layout::Us).unwrap(), press(0, 0x04));
+    }
+
+    #[test]
+    fn layouts_change_the_keys() {
+        assert_eq!(run("STRING z@", Layout::De).unwrap(), [press(0, 0x1c), press(0x40, 0x14)].concat());
+        // A dead key is followed by a space, so it types itself.
+        assert_eq!(run("STRING ^", Layout::De).unwrap(), [press(0, 0x35), press(0, 0x2c)].concat());
+        assert_eq!(run("STRING \"", Layout::Uk).unwrap(), press(SHIFT, 0x1f));
+    }
+
+    #[test]
+    fn combos() {
+        assert_eq!(run("GUI r", Layout::Us).unwrap(), press(0x08, 0x15));
+        assert_eq!(run("CTRL-ALT-DELETE", Layout::Us).unwrap(), press(0x05, 0x4c));
+        assert_eq!(run("ctrl shift esc", Layout::Us).unwrap(), press(0x03, 0x29));
+        assert_eq!(run("CTRL C", Layout::Us).unwrap(), press(0x01, 0x06));
+        assert_eq!(run("CTRL -", Layout::Us).unwrap(), press(0x01, 0x2d));
+        assert_eq!(run("ALT F4", Layout::Us).unwrap(), press(0x04, 0x3d));
+        assert_eq!(run("F12", Layout::Us).unwrap(), press(0, 0x45));
+        assert_eq!(run("GUI", Layout::Us).unwrap(), press(0x08, 0));
+        // Shifted characters bring Shift along.
+        assert_eq!(run("CTRL ?", Layout::Us).unwrap(), press(0x03, 0x38));
+    }
+
+    #[test]
+    fn delays_and_repeats() {
+        let actions = run("DELAY 500\nENTER\nREPEAT 2", Layout::Us).unwrap();
+        let enter = press(0, 0x28);
+        assert_eq!(actions, [&[Action::Delay(500)][..], &enter, &enter, &enter].concat());
+
+        let actions = run("DEFAULT_DELAY 20\nSTRING ab\nREPEAT 1\nDEFAULTDELAY 0\nTAB", Layout::Us).unwrap();
+        let ab = [&press(0, 0x04)[..], &press(0, 0x05), &[Action::Delay(20)]].concat();
+        assert_eq!(actions, [&ab[..], &ab, &press(0, 0x2b)].concat());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |script: &str, line, kind| {
+            let expected = Err(Error { line, kind });
+            assert_eq!(check(script, Layout::Us), expected, "{}", script);
+            assert_eq!(run(script, Layout::Us).map(|_| ()), expected, "{}", script);
+        };
+        error("STRING ok\nSTRNG typo", 2, ErrorKind::UnknownKey);
+        error("CTRL F13", 1, ErrorKind::UnknownKey);
+        error("STRING café", 1, ErrorKind::Untypeable('é'));
+        error("DELAY soon", 1, ErrorKind::BadNumber);
+        error("DEFAULT_DELAY", 1, ErrorKind::BadNumber);
+        error("REM nothing yet\nREPEAT 2", 2, ErrorKind::NothingToRepeat);
+        error("CTRL a b", 1, ErrorKind::TooManyKeys);
+        assert_eq!(check("STRING £5", Layout::Uk), Ok(()));
+
+        // Nothing after an error is typed.
+        let mut script = Script::new("TAB\nNOPE\nTAB", Layout::Us);
+        assert_eq!(script.by_ref().take(2).count(), 2);
+        assert!(script.next().unwrap().is_err());
+        assert_eq!(script.next(), None);
+
+        assert_eq!(Error { line: 3, kind: ErrorKind::Untypeable('é') }.to_string(), "line 3: 'é' isn't on the keyboard layout");
+    }
+}
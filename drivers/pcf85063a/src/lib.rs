@@ -0,0 +1,475 @@
+//! Driver for the NXP PCF85063A real-time clock, as fitted to the Badger 2040 W.
+//!
+//! The chip keeps time on the board's backup supply while the RP2040 is off, and its interrupt
+//! output can power the board back up, so an alarm or countdown timer set before shutting down
+//! is how the badge wakes itself for timed updates.
+//!
+//! Times are 24-hour, years 2000 to 2099. Weekdays use the chip's numbering, 0 is Sunday.
+//!
+//! Works with any `embedded-hal` 0.2 blocking I2C bus.
+
+#![cfg_attr(not(test), no_std)]
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Fixed 7-bit I2C address.
+pub const ADDRESS: u8 = 0x51;
+
+mod reg {
+    pub const CONTROL_1: u8 = 0x00;
+    pub const CONTROL_2: u8 = 0x01;
+    pub const SECONDS: u8 = 0x04;
+    pub const SECOND_ALARM: u8 = 0x0b;
+    pub const TIMER_VALUE: u8 = 0x10;
+    pub const TIMER_MODE: u8 = 0x11;
+}
+
+// Control_1
+const STOP: u8 = 1 << 5;
+const RESET: u8 = 0x58;
+// Control_2
+const AIE: u8 = 1 << 7;
+const AF: u8 = 1 << 6;
+const TF: u8 = 1 << 3;
+const COF: u8 = 0b111;
+// Seconds
+const OS: u8 = 1 << 7;
+// Alarm registers, set to ignore that field.
+const AEN: u8 = 1 << 7;
+// Timer_mode
+const TE: u8 = 1 << 2;
+const TIE: u8 = 1 << 1;
+
+#[derive(Debug, PartialEq)]
+pub enum Error<E> {
+    I2c(E),
+    /// The oscillator stopped since the time was last set (e.g. the backup supply ran out), so
+    /// the time can't be trusted.
+    ClockIntegrity,
+    /// A field out of range, or a register holding something that isn't a valid time.
+    InvalidTime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    /// 0 is Sunday.
+    pub weekday: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    fn is_valid(&self) -> bool {
+        (2000..=2099).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.weekday < 7
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+}
+
+/// Fields the alarm compares, `None` ignores that field. The alarm goes off when every field
+/// that is set matches.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Alarm {
+    pub second: Option<u8>,
+    pub minute: Option<u8>,
+    pub hour: Option<u8>,
+    pub day: Option<u8>,
+    /// 0 is Sunday.
+    pub weekday: Option<u8>,
+}
+
+/// Countdown timer source clock, which sets its resolution and range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimerClock {
+    Hz4096 = 0b00,
+    Hz64 = 0b01,
+    Hz1 = 0b10,
+    /// One tick a minute, up to 255 minutes.
+    PerMinute = 0b11,
+}
+
+/// Frequency on the CLKOUT pin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockOut {
+    Hz32768 = 0b000,
+    Hz16384 = 0b001,
+    Hz8192 = 0b010,
+    Hz4096 = 0b011,
+    Hz2048 = 0b100,
+    Hz1024 = 0b101,
+    Hz1 = 0b110,
+    /// Held low, which saves power.
+    Off = 0b111,
+}
+
+pub struct Pcf85063a<I2C> {
+    i2c: I2C,
+}
+
+fn bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+fn from_bcd(value: u8) -> Option<u8> {
+    let (tens, units) = (value >> 4, value & 0x0f);
+    (tens < 10 && units < 10).then_some(tens * 10 + units)
+}
+
+impl<I2C, E> Pcf85063a<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    pub fn new(i2c: I2C) -> Self {
+        Pcf85063a { i2c }
+    }
+
+    /// Gives the bus back.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    fn read(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        self.i2c.write_read(ADDRESS, &[register], buffer).map_err(Error::I2c)
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, Error<E>> {
+        let mut value = [0];
+        self.read(register, &mut value)?;
+        Ok(value[0])
+    }
+
+    fn write(&mut self, register: u8, values: &[u8]) -> Result<(), Error<E>> {
+        let mut buffer = [0; 8];
+        buffer[0] = register;
+        buffer[1..=values.len()].copy_from_slice(values);
+        self.i2c.write(ADDRESS, &buffer[..=values.len()]).map_err(Error::I2c)
+    }
+
+    fn update(&mut self, register: u8, clear: u8, set: u8) -> Result<(), Error<E>> {
+        let value = self.read_register(register)?;
+        self.write(register, &[(value & !clear) | set])
+    }
+
+    /// Software reset, every register back to its power-on value.
+    pub fn reset(&mut self) -> Result<(), Error<E>> {
+        self.write(reg::CONTROL_1, &[RESET])
+    }
+
+    pub fn datetime(&mut self) -> Result<DateTime, Error<E>> {
+        let mut data = [0; 7];
+        self.read(reg::SECONDS, &mut data)?;
+        if data[0] & OS != 0 {
+            return Err(Error::ClockIntegrity);
+        }
+
+        let field = |value: u8, mask: u8| from_bcd(value & mask).ok_or(Error::InvalidTime);
+        let time = DateTime {
+            second: field(data[0], 0x7f)?,
+            minute: field(data[1], 0x7f)?,
+            hour: field(data[2], 0x3f)?,
+            day: field(data[3], 0x3f)?,
+            weekday: data[4] & 0x07,
+            month: field(data[5], 0x1f)?,
+            year: 2000 + field(data[6], 0xff)? as u16,
+        };
+        if !time.is_valid() {
+            return Err(Error::InvalidTime);
+        }
+        Ok(time)
+    }
+
+    /// Sets the time, which also clears the oscillator stop flag.
+    pub fn set_datetime(&mut self, time: &DateTime) -> Result<(), Error<E>> {
+        if !time.is_valid() {
+            return Err(Error::InvalidTime);
+        }
+        // Stopping the clock while writing resets the prescaler, so the new second is a full one.
+        self.update(reg::CONTROL_1, 0, STOP)?;
+        self.write(
+            reg::SECONDS,
+            &[
+                bcd(time.second),
+                bcd(time.minute),
+                bcd(time.hour),
+                bcd(time.day),
+                time.weekday,
+                bcd(time.month),
+                bcd((time.year - 2000) as u8),
+            ],
+        )?;
+        self.update(reg::CONTROL_1, STOP, 0)
+    }
+
+    /// Sets the alarm and enables its interrupt, clearing a previous alarm that went off.
+    pub fn set_alarm(&mut self, alarm: &Alarm) -> Result<(), Error<E>> {
+        let field = |value: Option<u8>, max: u8| match value {
+            None => Ok(AEN),
+            Some(value) if value <= max => Ok(bcd(value)),
+            Some(_) => Err(Error::InvalidTime),
+        };
+        let day = match alarm.day {
+            Some(0) => return Err(Error::InvalidTime),
+            day => field(day, 31)?,
+        };
+        let registers = [
+            field(alarm.second, 59)?,
+            field(alarm.minute, 59)?,
+            field(alarm.hour, 23)?,
+            day,
+            match alarm.weekday {
+                None => AEN,
+                Some(weekday) if weekday < 7 => weekday,
+                Some(_) => return Err(Error::InvalidTime),
+            },
+        ];
+        self.write(reg::SECOND_ALARM, &registers)?;
+        self.update(reg::CONTROL_2, AF, AIE)
+    }
+
+    /// Turns the alarm and its interrupt off.
+    pub fn disable_alarm(&mut self) -> Result<(), Error<E>> {
+        self.write(reg::SECOND_ALARM, &[AEN; 5])?;
+        self.update(reg::CONTROL_2, AIE | AF, 0)
+    }
+
+    pub fn alarm_fired(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read_register(reg::CONTROL_2)? & AF != 0)
+    }
+
+    /// Clears the alarm flag, which releases the interrupt pin.
+    pub fn clear_alarm_flag(&mut self) -> Result<(), Error<E>> {
+        self.update(reg::CONTROL_2, AF, 0)
+    }
+
+    /// Starts the countdown timer from `ticks` of `clock`, with its interrupt enabled.
+    pub fn start_timer(&mut self, ticks: u8, clock: TimerClock) -> Result<(), Error<E>> {
+        // The timer has to be stopped to load a new value.
+        self.write(reg::TIMER_MODE, &[0])?;
+        self.update(reg::CONTROL_2, TF, 0)?;
+        self.write(reg::TIMER_VALUE, &[ticks, ((clock as u8) << 3) | TE | TIE])
+    }
+
+    pub fn stop_timer(&mut self) -> Result<(), Error<E>> {
+        self.write(reg::TIMER_MODE, &[0])?;
+        self.update(reg::CONTROL_2, TF, 0)
+    }
+
+    pub fn timer_fired(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read_register(reg::CONTROL_2)? & TF != 0)
+    }
+
+    pub fn clear_timer_flag(&mut self) -> Result<(), Error<E>> {
+        self.update(reg::CONTROL_2, TF, 0)
+    }
+
+    pub fn set_clock_out(&mut self, frequency: ClockOut) -> Result<(), Error<E>> {
+        // Writing 1 to the alarm and timer flags leaves them as they are.
+        self.update(reg::CONTROL_2, COF, AF | TF | frequency as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    // What the driver is expected to do on the bus, in order.
+    #[derive(Debug, PartialEq)]
+    enum Transaction {
+        Write(Vec<u8>),
+        // Register written, then the bytes the chip answers with.
+        WriteRead(u8, Vec<u8>),
+    }
+
+    struct Mock {
+        expected: VecDeque<Transaction>,
+    }
+
+    impl Mock {
+        fn new(expected: Vec<Transaction>) -> Self {
+            Mock { expected: expected.into() }
+        }
+
+        fn done(&self) {
+            assert!(self.expected.is_empty(), "transactions not made: {:?}", self.expected);
+        }
+    }
+
+    impl Write for Mock {
+        type Error = ();
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+            assert_eq!(address, ADDRESS);
+            assert_eq!(self.expected.pop_front(), Some(Transaction::Write(bytes.to_vec())));
+            Ok(())
+        }
+    }
+
+    impl WriteRead for Mock {
+        type Error = ();
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+            assert_eq!(address, ADDRESS);
+            match self.expected.pop_front() {
+                Some(Transaction::WriteRead(register, response)) => {
+                    assert_eq!(bytes, [register]);
+                    buffer.copy_from_slice(&response);
+                    Ok(())
+                }
+                other => panic!("unexpected read of {:02x?}, expected {:?}", bytes, other),
+            }
+        }
+    }
+
+    use Transaction::{Write as W, WriteRead as R};
+
+    fn check(expected: Vec<Transaction>, f: impl FnOnce(&mut Pcf85063a<Mock>)) {
+        let mut rtc = Pcf85063a::new(Mock::new(expected));
+        f(&mut rtc);
+        rtc.release().done();
+    }
+
+    const TIME: DateTime = DateTime { year: 2024, month: 8, day: 26, weekday: 1, hour: 9, minute: 41, second: 7 };
+
+    #[test]
+    fn reads_time() {
+        check(vec![R(0x04, vec![0x07, 0x41, 0x09, 0x26, 0x01, 0x08, 0x24])], |rtc| {
+            assert_eq!(rtc.datetime(), Ok(TIME));
+        });
+    }
+
+    #[test]
+    fn stopped_oscillator_is_reported() {
+        check(vec![R(0x04, vec![0x87, 0x41, 0x09, 0x26, 0x01, 0x08, 0x24])], |rtc| {
+            assert_eq!(rtc.datetime(), Err(Error::ClockIntegrity));
+        });
+    }
+
+    #[test]
+    fn garbage_is_not_a_time() {
+        check(vec![R(0x04, vec![0x07, 0x41, 0x09, 0x26, 0x01, 0x13, 0x24])], |rtc| {
+            assert_eq!(rtc.datetime(), Err(Error::InvalidTime));
+        });
+        check(vec![R(0x04, vec![0x0a, 0x41, 0x09, 0x26, 0x01, 0x08, 0x24])], |rtc| {
+            assert_eq!(rtc.datetime(), Err(Error::InvalidTime));
+        });
+    }
+
+    #[test]
+    fn sets_time_with_the_clock_stopped() {
+        check(
+            vec![
+                R(0x00, vec![0x00]),
+                W(vec![0x00, 0x20]),
+                W(vec![0x04, 0x07, 0x41, 0x09, 0x26, 0x01, 0x08, 0x24]),
+                R(0x00, vec![0x20]),
+                W(vec![0x00, 0x00]),
+            ],
+            |rtc| assert_eq!(rtc.set_datetime(&TIME), Ok(())),
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_time_without_touching_the_bus() {
+        check(vec![], |rtc| {
+            assert_eq!(rtc.set_datetime(&DateTime { hour: 24, ..TIME }), Err(Error::InvalidTime));
+            assert_eq!(rtc.set_datetime(&DateTime { year: 1999, ..TIME }), Err(Error::InvalidTime));
+            assert_eq!(rtc.set_alarm(&Alarm { minute: Some(60), ..Alarm::default() }), Err(Error::InvalidTime));
+            assert_eq!(rtc.set_alarm(&Alarm { day: Some(0), ..Alarm::default() }), Err(Error::InvalidTime));
+        });
+    }
+
+    #[test]
+    fn sets_alarm() {
+        let alarm = Alarm { minute: Some(30), hour: Some(7), ..Alarm::default() };
+        check(
+            vec![
+                W(vec![0x0b, 0x80, 0x30, 0x07, 0x80, 0x80]),
+                // A fired alarm is cleared, clock out is left alone.
+                R(0x01, vec![0x47]),
+                W(vec![0x01, 0x87]),
+            ],
+            |rtc| assert_eq!(rtc.set_alarm(&alarm), Ok(())),
+        );
+    }
+
+    #[test]
+    fn disables_alarm() {
+        check(
+            vec![W(vec![0x0b, 0x80, 0x80, 0x80, 0x80, 0x80]), R(0x01, vec![0xc0]), W(vec![0x01, 0x00])],
+            |rtc| assert_eq!(rtc.disable_alarm(), Ok(())),
+        );
+    }
+
+    #[test]
+    fn alarm_flag() {
+        check(vec![R(0x01, vec![0xc0]), R(0x01, vec![0xc8]), W(vec![0x01, 0x88])], |rtc| {
+            assert_eq!(rtc.alarm_fired(), Ok(true));
+            assert_eq!(rtc.clear_alarm_flag(), Ok(()));
+        });
+    }
+
+    #[test]
+    fn starts_timer() {
+        check(
+            vec![
+                W(vec![0x11, 0x00]),
+                R(0x01, vec![0x08]),
+                W(vec![0x01, 0x00]),
+                W(vec![0x10, 0x0a, 0x1e]),
+            ],
+            |rtc| assert_eq!(rtc.start_timer(10, TimerClock::PerMinute), Ok(())),
+        );
+    }
+
+    #[test]
+    fn timer_flag() {
+        check(vec![R(0x01, vec![0x08]), R(0x01, vec![0x08]), W(vec![0x01, 0x00])], |rtc| {
+            assert_eq!(rtc.timer_fired(), Ok(true));
+            assert_eq!(rtc.clear_timer_flag(), Ok(()));
+        });
+    }
+
+    #[test]
+    fn sets_clock_out_without_clearing_flags() {
+        check(vec![R(0x01, vec![0x80]), W(vec![0x01, 0xcf])], |rtc| {
+            assert_eq!(rtc.set_clock_out(ClockOut::Off), Ok(()));
+        });
+    }
+
+    #[test]
+    fn resets() {
+        check(vec![W(vec![0x00, 0x58])], |rtc| assert_eq!(rtc.reset(), Ok(())));
+    }
+
+    #[test]
+    fn bus_errors_are_passed_on() {
+        struct Broken;
+        impl Write for Broken {
+            type Error = u8;
+            fn write(&mut self, _: u8, _: &[u8]) -> Result<(), u8> {
+                Err(7)
+            }
+        }
+        impl WriteRead for Broken {
+            type Error = u8;
+            fn write_read(&mut self, _: u8, _: &[u8], _: &mut [u8]) -> Result<(), u8> {
+                Err(7)
+            }
+        }
+
+        let mut rtc = Pcf85063a::new(Broken);
+        assert_eq!(rtc.datetime(), Err(Error::I2c(7)));
+        assert_eq!(rtc.reset(), Err(Error::I2c(7)));
+    }
+}
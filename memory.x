@@ -1,6 +1,10 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 128K
+    /* Data the firmware saves itself, in 4K sectors. Keep in sync with src/store.rs. */
+    STORE : ORIGIN = 0x101E0000, LENGTH = 64K
+    /* Badge screens, flashed separately (see tools/screenc). Keep in sync with src/screens.rs. */
+    SCREENS : ORIGIN = 0x101F0000, LENGTH = 64K
     RAM   : ORIGIN = 0x20000000, LENGTH = 256K
 }
 
@@ -0,0 +1,212 @@
+// Images converted from `assets/*.bmp` by build.rs.
+//
+// Each image is stored as 1 bit per pixel, either packed row-major (MSB first) or as alternating
+// run lengths, whichever came out smaller. Runs start with `BinaryColor::Off` and every length is
+// a LEB128 varint. Pixels are decoded on the fly while drawing, nothing is buffered in RAM.
+//
+// Images marked `gray` in the manifest also get a `GrayAsset`: four levels, 2 bits per pixel.
+
+use embedded_graphics::{
+    image::ImageDrawable,
+    pixelcolor::{BinaryColor, Gray2},
+    prelude::*,
+    primitives::Rectangle,
+};
+use uc8151::{HEIGHT, WIDTH};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Packed,
+    Rle,
+}
+
+pub struct Asset {
+    pub width: u32,
+    pub height: u32,
+    encoding: Encoding,
+    data: &'static [u8],
+}
+
+impl Asset {
+    // Fails the build if the image can't fit on the panel or its data doesn't match its size.
+    pub const fn new(width: u32, height: u32, encoding: Encoding, data: &'static [u8]) -> Self {
+        assert!(width <= WIDTH && height <= HEIGHT, "asset is larger than the display");
+
+        let pixels = width as usize * height as usize;
+        match encoding {
+            Encoding::Packed => assert!(data.len() == pixels.div_ceil(8), "packed asset has the wrong length"),
+            Encoding::Rle => {
+                let mut total = 0;
+                let mut i = 0;
+                while i < data.len() {
+                    let (run, next) = read_varint(data, i);
+                    total += run;
+                    i = next;
+                }
+                assert!(total == pixels, "run-length asset has the wrong pixel count");
+            }
+        }
+
+        Asset { width, height, encoding, data }
+    }
+
+    pub fn pixels(&self) -> AssetPixels<'_> {
+        AssetPixels {
+            asset: self,
+            index: 0,
+            offset: 0,
+            run_left: 0,
+            color: BinaryColor::On,
+        }
+    }
+}
+
+const fn read_varint(data: &[u8], mut i: usize) -> (usize, usize) {
+    let mut value = 0;
+    let mut shift = 0;
+    while i < data.len() {
+        let byte = data[i];
+        value |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        i += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    (value, i)
+}
+
+/// Streaming decoder, yields every pixel of the image in row-major order.
+pub struct AssetPixels<'a> {
+    asset: &'a Asset,
+    index: usize,
+    offset: usize,
+    run_left: usize,
+    color: BinaryColor,
+}
+
+impl<'a> Iterator for AssetPixels<'a> {
+    type Item = Pixel<BinaryColor>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let width = self.asset.width as usize;
+        if self.index >= width * self.asset.height as usize {
+            return None;
+        }
+
+        let color = match self.asset.encoding {
+            Encoding::Packed => {
+                let byte = self.asset.data[self.index / 8];
+                BinaryColor::from(byte & (0x80 >> (self.index % 8)) != 0)
+            }
+            Encoding::Rle => {
+                // Zero-length runs only flip the colour.
+                while self.run_left == 0 {
+                    let (run, next) = read_varint(self.asset.data, self.offset);
+                    self.offset = next;
+                    self.run_left = run;
+                    self.color = self.color.invert();
+                }
+                self.run_left -= 1;
+                self.color
+            }
+        };
+
+        let point = Point::new((self.index % width) as i32, (self.index / width) as i32);
+        self.index += 1;
+        Some(Pixel(point, color))
+    }
+}
+
+impl OriginDimensions for Asset {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl ImageDrawable for Asset {
+    type Color = BinaryColor;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        target.draw_iter(self.pixels())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let offset = area.top_left;
+        target.draw_iter(
+            self.pixels()
+                .filter(|Pixel(p, _)| area.contains(*p))
+                .map(|Pixel(p, c)| Pixel(p - offset, c)),
+        )
+    }
+}
+
+pub struct GrayAsset {
+    pub width: u32,
+    pub height: u32,
+    data: &'static [u8],
+}
+
+impl GrayAsset {
+    pub const fn new(width: u32, height: u32, data: &'static [u8]) -> Self {
+        assert!(width <= WIDTH && height <= HEIGHT, "asset is larger than the display");
+        assert!(data.len() == (width as usize * height as usize).div_ceil(4), "gray asset has the wrong length");
+        GrayAsset { width, height, data }
+    }
+
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel<Gray2>> + '_ {
+        let width = self.width as usize;
+        (0..width * self.height as usize).map(move |i| {
+            let level = (self.data[i / 4] >> (6 - (i % 4) * 2)) & 0b11;
+            Pixel(Point::new((i % width) as i32, (i / width) as i32), Gray2::new(level))
+        })
+    }
+}
+
+impl OriginDimensions for GrayAsset {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl ImageDrawable for GrayAsset {
+    type Color = Gray2;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray2>,
+    {
+        target.draw_iter(self.pixels())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray2>,
+    {
+        let offset = area.top_left;
+        target.draw_iter(
+            self.pixels()
+                .filter(|Pixel(p, _)| area.contains(*p))
+                .map(|Pixel(p, c)| Pixel(p - offset, c)),
+        )
+    }
+}
+
+/// An image by its manifest handle, see `CATALOG`.
+pub struct Entry {
+    pub name: &'static str,
+    pub image: &'static Asset,
+    pub gray: Option<&'static GrayAsset>,
+}
+
+pub fn find(name: &str) -> Option<&'static Entry> {
+    CATALOG.iter().find(|entry| entry.name == name)
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
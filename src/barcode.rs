@@ -0,0 +1,39 @@
+// Drawing barcodes. The encoders are in `badge_barcode`, tested on the host.
+
+// Graphics
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+
+pub use badge_barcode::{encode, Symbol, Symbology};
+
+/// Draws `symbol` with its top-left module at (x, y), each module `scale` pixels square.
+/// 1D symbols are stretched to `bar_height` pixels tall.
+pub fn draw_symbol<D>(
+    display: &mut D,
+    symbol: &Symbol,
+    x: i32,
+    y: i32,
+    scale: u32,
+    bar_height: u32,
+) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let module_height = if symbol.height() == 1 { bar_height } else { scale };
+    let style = PrimitiveStyle::with_fill(BinaryColor::Off);
+
+    for row in 0..symbol.height() {
+        for col in 0..symbol.width() {
+            if symbol.get(col, row) {
+                let _ = Rectangle::new(
+                    Point::new(x + (col as u32 * scale) as i32, y + (row as u32 * module_height) as i32),
+                    Size::new(scale, module_height),
+                )
+                .into_styled(style)
+                .draw(display);
+            }
+        }
+    }
+}
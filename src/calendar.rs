@@ -0,0 +1,307 @@
+// Dates and times of day.
+//
+// Plain calendar arithmetic with no hardware behind it, so build.rs can use it to check profiles.
+// Weekdays are numbered from Monday (0) to Sunday (6). Timestamps are seconds since 1970-01-01
+// 00:00, in whatever zone the moment is in. Dates are 2000 to 2099, the years the RTC keeps.
+
+use core::fmt;
+
+pub const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+pub const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+pub const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+pub const FIRST_YEAR: u16 = 2000;
+pub const LAST_YEAR: u16 = 2099;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// `YYYY-MM-DD`.
+    pub fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.splitn(3, '-');
+        let year = parts.next().filter(|year| year.len() == 4)?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        let date = Date { year, month, day };
+        (date.is_valid()).then_some(date)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (FIRST_YEAR..=LAST_YEAR).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= days_in_month(self.year, self.month)
+    }
+
+    pub fn weekday(&self) -> u8 {
+        // Sakamoto's method, which counts from Sunday.
+        const OFFSETS: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let year = if self.month < 3 { self.year - 1 } else { self.year };
+        let sunday = (year + year / 4 - year / 100 + year / 400 + OFFSETS[self.month as usize - 1] + self.day as u16) % 7;
+        ((sunday + 6) % 7) as u8
+    }
+
+    /// Days since 1970-01-01.
+    pub fn days(&self) -> i32 {
+        // Howard Hinnant's days_from_civil, with years starting in March.
+        let year = self.year as i32 - (self.month <= 2) as i32;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i32;
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + self.day as i32 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    pub fn from_days(days: i32) -> Date {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i32;
+        Date { year: year as u16, month: month as u8, day: day as u8 }
+    }
+
+    // `n`th Sunday of the month counting from 1, or the last one for 0.
+    fn sunday(year: u16, month: u8, n: u8) -> Date {
+        if n == 0 {
+            let last = Date { year, month, day: days_in_month(year, month) };
+            return Date { day: last.day - (last.weekday() + 1) % 7, ..last };
+        }
+        let first = Date { year, month, day: 1 };
+        Date { day: 1 + (13 - first.weekday()) % 7 + (n - 1) * 7, ..first }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+pub fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// `HH:MM` or `HH:MM:SS`, as hour, minute and second.
+pub fn parse_time(text: &str) -> Option<(u8, u8, u8)> {
+    let mut parts = text.splitn(3, ':');
+    let hour = parts.next()?.parse().ok().filter(|&hour| hour < 24)?;
+    let minute = parts.next()?.parse().ok().filter(|&minute| minute < 60)?;
+    let second = match parts.next() {
+        Some(second) => second.parse().ok().filter(|&second| second < 60)?,
+        None => 0,
+    };
+    Some((hour, minute, second))
+}
+
+/// A point in local time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Moment {
+    pub date: Date,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Moment {
+    pub fn minute_of_day(&self) -> u16 {
+        self.hour as u16 * 60 + self.minute as u16
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.date.days() as i64 * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+
+    pub fn from_timestamp(timestamp: i64) -> Moment {
+        let seconds = timestamp.rem_euclid(86400);
+        Moment {
+            date: Date::from_days(timestamp.div_euclid(86400) as i32),
+            hour: (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+        }
+    }
+
+    pub fn add_seconds(&self, seconds: i64) -> Moment {
+        Moment::from_timestamp(self.timestamp() + seconds)
+    }
+}
+
+impl fmt::Display for Moment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:02}:{:02}:{:02}", self.date, self.hour, self.minute, self.second)
+    }
+}
+
+/// Standard time's offset from UTC, in minutes.
+#[derive(Clone, Copy, PartialEq)]
+pub struct UtcOffset(pub i16);
+
+impl UtcOffset {
+    // UTC-12:00 to UTC+14:00 covers every zone in use.
+    pub const MIN: i16 = -12 * 60;
+    pub const MAX: i16 = 14 * 60;
+
+    /// `+HH:MM` or `-HH:MM`.
+    pub fn parse(text: &str) -> Option<UtcOffset> {
+        let (sign, rest) = match text.as_bytes().first()? {
+            b'+' => (1, &text[1..]),
+            b'-' => (-1, &text[1..]),
+            _ => return None,
+        };
+        let (hours, minutes) = rest.split_once(':')?;
+        let minutes = hours.parse::<i16>().ok()? * 60 + minutes.parse::<i16>().ok().filter(|&minutes| minutes < 60)?;
+        Some(UtcOffset(sign * minutes)).filter(|offset| (Self::MIN..=Self::MAX).contains(&offset.0))
+    }
+}
+
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        write!(f, "{}{:02}:{:02}", sign, self.0.unsigned_abs() / 60, self.0.unsigned_abs() % 60)
+    }
+}
+
+/// Daylight saving rules, an hour ahead of standard time while in effect.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dst {
+    None,
+    /// Second Sunday in March 02:00 to first Sunday in November 02:00, local time.
+    NorthAmerica,
+    /// Last Sunday in March to last Sunday in October, 01:00 UTC.
+    Europe,
+}
+
+impl Dst {
+    pub const ALL: [Dst; 3] = [Dst::None, Dst::NorthAmerica, Dst::Europe];
+
+    pub fn parse(name: &str) -> Option<Dst> {
+        Dst::ALL.into_iter().find(|dst| dst.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dst::None => "none",
+            Dst::NorthAmerica => "north-america",
+            Dst::Europe => "europe",
+        }
+    }
+
+    /// Whether daylight saving is in effect at `utc` in a zone `offset` from UTC.
+    pub fn active(&self, utc: &Moment, offset: UtcOffset) -> bool {
+        let year = utc.date.year;
+        let at = |date: Date, hour: u8| Moment { date, hour, minute: 0, second: 0 }.timestamp();
+        let offset = offset.0 as i64 * 60;
+
+        let (start, end) = match self {
+            Dst::None => return false,
+            Dst::NorthAmerica => (
+                at(Date::sunday(year, 3, 2), 2) - offset,
+                at(Date::sunday(year, 11, 1), 2) - offset - 3600,
+            ),
+            Dst::Europe => (at(Date::sunday(year, 3, 0), 1), at(Date::sunday(year, 10, 0), 1)),
+        };
+        (start..end).contains(&utc.timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn years_are_the_rtcs() {
+        assert_eq!(Date::parse("2000-01-01"), Some(Date { year: 2000, month: 1, day: 1 }));
+        assert_eq!(Date::parse("2099-12-31"), Some(Date { year: 2099, month: 12, day: 31 }));
+        // January and February count back a year to find the weekday.
+        assert_eq!(Date::parse("0000-01-15"), None);
+        assert_eq!(Date::parse("1999-12-31"), None);
+        assert_eq!(Date::parse("2100-01-01"), None);
+        assert_eq!(Date::parse("24-01-15"), None);
+    }
+
+    fn date(text: &str) -> Date {
+        Date::parse(text).unwrap()
+    }
+
+    fn utc(text: &str) -> Moment {
+        let (day, time) = text.split_once(' ').unwrap();
+        let (hour, minute, second) = parse_time(time).unwrap();
+        Moment { date: date(day), hour, minute, second }
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(date("2000-01-01").weekday(), 5);
+        // 2000 is a leap year even though it's a century, 2100 wouldn't be.
+        assert_eq!(date("2000-02-29").weekday(), 1);
+        assert_eq!(date("2000-03-01").weekday(), 2);
+        assert_eq!(date("2024-02-29").weekday(), 3);
+        assert_eq!(date("2024-03-01").weekday(), 4);
+        assert_eq!(date("2023-12-31").weekday(), 6);
+        assert_eq!(date("2024-01-01").weekday(), 0);
+        assert_eq!(date("2099-12-31").weekday(), 3);
+        assert!(Date::parse("2023-02-29").is_none());
+
+        // Weekdays agree with counting days from a Thursday.
+        for text in ["2000-01-01", "2000-02-29", "2024-02-29", "2038-01-19", "2099-12-31"] {
+            let date = date(text);
+            assert_eq!(date.weekday() as i32, (date.days() + 3).rem_euclid(7), "{}", text);
+            assert_eq!(Date::from_days(date.days()), date);
+        }
+    }
+
+    #[test]
+    fn changeover_sundays() {
+        assert_eq!(Date::sunday(2024, 3, 2), date("2024-03-10"));
+        assert_eq!(Date::sunday(2024, 11, 1), date("2024-11-03"));
+        assert_eq!(Date::sunday(2024, 3, 0), date("2024-03-31"));
+        assert_eq!(Date::sunday(2024, 10, 0), date("2024-10-27"));
+        // Months that start on a Sunday.
+        assert_eq!(Date::sunday(2026, 3, 2), date("2026-03-08"));
+        assert_eq!(Date::sunday(2026, 11, 1), date("2026-11-01"));
+    }
+
+    #[test]
+    fn north_america_changes_at_2am_local() {
+        let eastern = UtcOffset::parse("-05:00").unwrap();
+        let active = |text| Dst::NorthAmerica.active(&utc(text), eastern);
+        // 2024-03-10 02:00 EST, 07:00 UTC.
+        assert!(!active("2024-03-10 06:59:59"));
+        assert!(active("2024-03-10 07:00"));
+        // 2024-11-03 02:00 EDT, 06:00 UTC.
+        assert!(active("2024-11-03 05:59:59"));
+        assert!(!active("2024-11-03 06:00"));
+        assert!(!active("2024-01-15 12:00"));
+        assert!(active("2024-07-01 12:00"));
+    }
+
+    #[test]
+    fn europe_changes_at_1am_utc() {
+        let central = UtcOffset::parse("+01:00").unwrap();
+        let active = |text| Dst::Europe.active(&utc(text), central);
+        assert!(!active("2024-03-31 00:59:59"));
+        assert!(active("2024-03-31 01:00"));
+        assert!(active("2024-10-27 00:59:59"));
+        assert!(!active("2024-10-27 01:00"));
+        assert!(!Dst::None.active(&utc("2024-07-01 12:00"), central));
+    }
+}
@@ -0,0 +1,216 @@
+// Wall clock time from the RP2040's RTC.
+//
+// The RTC counts UTC. It starts from a placeholder date at power on and keeps counting while the
+// badge is running, but the time only means something once it has been set, over serial or from
+// Settings. Local time is UTC plus the zone's standard offset, plus an hour while its daylight
+// saving rules are in effect. The zone defaults to the profile's and can be changed at runtime.
+//
+//   time                              prints local time and the zone
+//   time set 2024-08-09 14:30[:00]    sets local time
+//   time utc 2024-08-09 18:30[:00]    sets UTC, for syncing from the host
+//   time zone -04:00                  standard time's offset from UTC
+//   time dst north-america            daylight saving rules: none, north-america or europe
+//
+// A host can sync it with `date -u '+time utc %F %T' > /dev/ttyACM0`.
+//
+// On the Badger 2040 W (`badger2040w` feature) the battery-backed PCF85063A is the source of
+// truth: the time is read from it at power on and written to it whenever it's set.
+
+use core::fmt::Write as FmtWrite;
+
+use heapless::String;
+use rp2040_hal::rtc::{DateTime, DayOfWeek, RealTimeClock};
+#[cfg(feature = "badger2040w")]
+use rp2040_hal::gpio::{bank0::{Gpio4, Gpio5}, FunctionI2C, Pin};
+
+use crate::calendar::{self, Date, Dst, Moment, UtcOffset};
+use crate::profile;
+use crate::usb;
+
+/// The Badger 2040 W's RTC, on I2C0.
+#[cfg(feature = "badger2040w")]
+pub type External = pcf85063a::Pcf85063a<
+    rp2040_hal::I2C<rp2040_hal::pac::I2C0, (Pin<Gpio4, FunctionI2C>, Pin<Gpio5, FunctionI2C>)>,
+>;
+
+pub struct Clock {
+    rtc: RealTimeClock,
+    #[cfg(feature = "badger2040w")]
+    external: Option<External>,
+    set: bool,
+    pub offset: UtcOffset,
+    pub dst: Dst,
+}
+
+/// What the RTC counts from until it's set.
+pub const PLACEHOLDER: DateTime = DateTime {
+    year: 2023,
+    month: 1,
+    day: 1,
+    day_of_week: DayOfWeek::Sunday,
+    hour: 0,
+    minute: 0,
+    second: 0,
+};
+
+impl Clock {
+    pub fn new(rtc: RealTimeClock) -> Self {
+        Clock {
+            rtc,
+            #[cfg(feature = "badger2040w")]
+            external: None,
+            set: false,
+            offset: profile::UTC_OFFSET,
+            dst: profile::DST,
+        }
+    }
+
+    /// Takes the time from the external RTC, if it has kept it, and keeps it in sync from now on.
+    #[cfg(feature = "badger2040w")]
+    pub fn attach(&mut self, mut external: External) {
+        if let Ok(time) = external.datetime() {
+            let utc = Moment {
+                date: Date { year: time.year, month: time.month, day: time.day },
+                hour: time.hour,
+                minute: time.minute,
+                second: time.second,
+            };
+            self.set_utc(&utc);
+        }
+        self.external = Some(external);
+    }
+
+    /// UTC, or `None` while the clock hasn't been set.
+    pub fn utc(&self) -> Option<Moment> {
+        if !self.set {
+            return None;
+        }
+        let now = self.rtc.now().ok()?;
+        Some(Moment {
+            date: Date { year: now.year, month: now.month, day: now.day },
+            hour: now.hour,
+            minute: now.minute,
+            second: now.second,
+        })
+    }
+
+    /// Local time, or `None` while the clock hasn't been set.
+    pub fn now(&self) -> Option<Moment> {
+        self.utc().map(|utc| utc.add_seconds(self.offset_at(&utc)))
+    }
+
+    // Seconds local time is ahead of UTC at `utc`.
+    fn offset_at(&self, utc: &Moment) -> i64 {
+        let dst = if self.dst.active(utc, self.offset) { 3600 } else { 0 };
+        self.offset.0 as i64 * 60 + dst
+    }
+
+    /// Sets the clock from local time.
+    pub fn set(&mut self, local: &Moment) -> bool {
+        // Whether daylight saving applies depends on the UTC time we're working out, so decide it
+        // an hour before the standard time reading. The hour that repeats in autumn is taken as
+        // the first of the two.
+        let standard = local.add_seconds(-(self.offset.0 as i64) * 60);
+        let utc = local.add_seconds(-self.offset_at(&standard.add_seconds(-3600)));
+        self.set_utc(&utc)
+    }
+
+    pub fn set_utc(&mut self, utc: &Moment) -> bool {
+        let day_of_week = match utc.date.weekday() {
+            0 => DayOfWeek::Monday,
+            1 => DayOfWeek::Tuesday,
+            2 => DayOfWeek::Wednesday,
+            3 => DayOfWeek::Thursday,
+            4 => DayOfWeek::Friday,
+            5 => DayOfWeek::Saturday,
+            _ => DayOfWeek::Sunday,
+        };
+        let time = DateTime {
+            year: utc.date.year,
+            month: utc.date.month,
+            day: utc.date.day,
+            day_of_week,
+            hour: utc.hour,
+            minute: utc.minute,
+            second: utc.second,
+        };
+
+        self.set = self.rtc.set_datetime(time).is_ok();
+
+        #[cfg(feature = "badger2040w")]
+        if let Some(external) = self.external.as_mut() {
+            let _ = external.set_datetime(&pcf85063a::DateTime {
+                year: utc.date.year,
+                month: utc.date.month,
+                day: utc.date.day,
+                weekday: (utc.date.weekday() + 1) % 7,
+                hour: utc.hour,
+                minute: utc.minute,
+                second: utc.second,
+            });
+        }
+
+        self.set
+    }
+
+    /// Handles `time ...` typed on the serial console.
+    pub fn command<'a>(&mut self, mut args: impl Iterator<Item = &'a str>) {
+        let usage = "usage: time [set|utc YYYY-MM-DD HH:MM[:SS] | zone +HH:MM | dst none|north-america|europe]";
+        let result = match args.next() {
+            None => Ok(()),
+            Some(command @ ("set" | "utc")) => {
+                let date = args.next().and_then(Date::parse);
+                let time = args.next().and_then(calendar::parse_time);
+                match (date, time) {
+                    (Some(date), Some((hour, minute, second))) => {
+                        let moment = Moment { date, hour, minute, second };
+                        let set = if command == "set" { self.set(&moment) } else { self.set_utc(&moment) };
+                        if set { Ok(()) } else { Err("the RTC didn't take that time") }
+                    }
+                    _ => Err(usage),
+                }
+            }
+            Some("zone") => match args.next().and_then(UtcOffset::parse) {
+                Some(offset) => {
+                    self.offset = offset;
+                    Ok(())
+                }
+                None => Err("zones look like -04:00 or +05:30"),
+            },
+            Some("dst") => match args.next().and_then(Dst::parse) {
+                Some(dst) => {
+                    self.dst = dst;
+                    Ok(())
+                }
+                None => Err("dst is none, north-america or europe"),
+            },
+            Some(_) => Err(usage),
+        };
+
+        let mut line: String<96> = String::new();
+        match (result, self.now()) {
+            (Err(message), _) => {
+                let _ = write!(line, "{}\r\n", message);
+            }
+            (Ok(()), Some(now)) => {
+                let _ = write!(line, "{} (UTC{}, dst {})\r\n", now, self.offset, self.dst.name());
+            }
+            (Ok(()), None) => {
+                let _ = write!(line, "not set (UTC{}, dst {})\r\n", self.offset, self.dst.name());
+            }
+        }
+        usb::write(line.as_bytes());
+    }
+}
+
+/// Short local date and time for a status corner, e.g. "Mon 26 Aug 09:41". Empty while the
+/// clock isn't set.
+pub fn status(now: Option<&Moment>) -> String<24> {
+    let mut text = String::new();
+    if let Some(now) = now {
+        let day = calendar::DAY_NAMES[now.date.weekday() as usize];
+        let month = calendar::MONTHS[now.date.month as usize - 1];
+        let _ = write!(text, "{} {} {} {:02}:{:02}", day, now.date.day, month, now.hour, now.minute);
+    }
+    text
+}
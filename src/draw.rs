@@ -1,13 +1,12 @@
 // Graphics
 use embedded_graphics::{
     image::Image,
-    mono_font::{MonoTextStyle},
     pixelcolor::BinaryColor,
     prelude::*,
     primitives::{PrimitiveStyle, Rectangle},
 };
 use embedded_graphics::mono_font::MonoFont;
-use embedded_graphics::text::{Text};
+use embedded_graphics::text::{renderer::TextRenderer, Baseline, Text};
 use embedded_hal::blocking::spi::Write;
 use embedded_text::{
     alignment::HorizontalAlignment,
@@ -16,32 +15,29 @@ use embedded_text::{
 };
 
 use profont::*;
-use tinybmp::Bmp;
-use uc8151::{Uc8151, WIDTH};
+use uc8151::Uc8151;
+
+use crate::assets::Asset;
+use crate::font::TextStyle;
 
 
 // GPIO traits
 use embedded_hal::digital::v2::OutputPin;
 use embedded_hal::digital::v2::InputPin;
 
-pub fn draw_image<SPI, CS, DC, BUSY, RESET>(
-    display: &mut Uc8151<SPI, CS, DC, BUSY, RESET>,
-    data: &[u8],
+pub fn draw_image<D>(
+    display: &mut D,
+    asset: &Asset,
     x: i32,
     y: i32
 ) where
-    SPI: Write<u8>,
-    CS: OutputPin,
-    DC: OutputPin,
-    BUSY: InputPin,
-    RESET: OutputPin,
+    D: DrawTarget<Color = BinaryColor>,
 {
-    let tga: Bmp<BinaryColor> = Bmp::from_slice(data).unwrap();
-    let _ = Image::new(&tga, Point::new(x, y)).draw(display);
+    let _ = Image::new(asset, Point::new(x, y)).draw(display);
 }
 
-pub fn draw_textbox<SPI, CS, DC, BUSY, RESET>(
-    display: &mut Uc8151<SPI, CS, DC, BUSY, RESET>,
+pub fn draw_textbox<D>(
+    display: &mut D,
     text: &str,
     font: MonoFont,
     foreground: BinaryColor,
@@ -51,13 +47,9 @@ pub fn draw_textbox<SPI, CS, DC, BUSY, RESET>(
     width: u32,
     height: u32,
 ) where
-    SPI: Write<u8>,
-    CS: OutputPin,
-    DC: OutputPin,
-    BUSY: InputPin,
-    RESET: OutputPin,
+    D: DrawTarget<Color = BinaryColor>,
 {
-    let character_style = MonoTextStyle::new(&font, foreground);
+    let character_style = TextStyle::new(&font, foreground);
     let textbox_style = TextBoxStyleBuilder::new()
         .height_mode(HeightMode::FitToText)
         .alignment(align)
@@ -66,13 +58,12 @@ pub fn draw_textbox<SPI, CS, DC, BUSY, RESET>(
         .build();
 
     let bounds = Rectangle::new(Point::new(x, y), Size::new(width, height));
-    bounds
+    let _ = bounds
         .into_styled(PrimitiveStyle::with_fill(foreground))
-        .draw(display)
-        .unwrap();
+        .draw(display);
 
     let text_box = TextBox::with_textbox_style(text, bounds, character_style, textbox_style);
-    text_box.draw(display).unwrap();
+    let _ = text_box.draw(display);
 }
 
 pub fn draw_text<SPI, CS, DC, BUSY, RESET>(
@@ -89,6 +80,61 @@ pub fn draw_text<SPI, CS, DC, BUSY, RESET>(
     BUSY: InputPin,
     RESET: OutputPin,
 {
-    let text_style = MonoTextStyle::new(&PROFONT_14_POINT, foreground);
+    let text_style = TextStyle::new(&PROFONT_14_POINT, foreground);
     Text::new(text, Point::new(x, y), text_style).draw(display).unwrap();
-}
\ No newline at end of file
+}
+
+/// Draws `text` with every pixel of `font` blown up to a `scale` by `scale` block, centred across
+/// the display with its top at `y`, for numbers bigger than the largest font. Returns the area
+/// the text covers.
+pub fn draw_large_text<D>(
+    display: &mut D,
+    text: &str,
+    font: MonoFont,
+    scale: u32,
+    foreground: BinaryColor,
+    y: i32,
+) -> Rectangle where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let style = TextStyle::new(&font, foreground);
+    let size = style.measure_string(text, Point::zero(), Baseline::Top).bounding_box.size * scale;
+    let x = (display.bounding_box().size.width as i32 - size.width as i32) / 2;
+
+    let mut target = Scaled { target: display, scale, origin: Point::new(x, y) };
+    let _ = Text::with_baseline(text, Point::zero(), style, Baseline::Top).draw(&mut target);
+
+    Rectangle::new(Point::new(x, y), size)
+}
+
+// Draws onto `target` with every pixel a `scale` by `scale` block, starting at `origin`.
+struct Scaled<'a, D> {
+    target: &'a mut D,
+    scale: u32,
+    origin: Point,
+}
+
+impl<D> DrawTarget for Scaled<'_, D> where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error> where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        for Pixel(point, color) in pixels {
+            let block = Rectangle::new(self.origin + point * self.scale as i32, Size::new_equal(self.scale));
+            self.target.fill_solid(&block, color)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D> OriginDimensions for Scaled<'_, D> where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    fn size(&self) -> Size {
+        self.target.bounding_box().size / self.scale
+    }
+}
@@ -0,0 +1,114 @@
+// Randomness for keys and anything else that needs it, from the RP2040's ring oscillator.
+//
+// Each raw sample mixes two things the crystal-clocked CPU can't predict: eight reads of the
+// ROSC's RANDOMBIT register, which samples the free-running oscillator, and how many loop
+// iterations it takes the ROSC's COUNT register to count down, which drifts with the jitter
+// between the two oscillators. `badge_crypto::rng` health tests the samples, hashes them into
+// seeds and runs ChaCha20 on top. Over serial:
+//
+//   random 64                         64 random bytes in hex, 32 to a line
+//   random raw 64                     64 raw samples in hex, untested and unconditioned
+//   random health                     health test counts
+//
+// For statistical test suites on the host, capture a large `random` run and turn the hex back
+// into binary, see the README.
+
+use core::fmt::Write as FmtWrite;
+use core::str::SplitWhitespace;
+
+use badge_crypto::rng::{self, NoiseSource};
+use heapless::String;
+use rand_core::RngCore;
+use rp2040_hal::pac;
+use rp2040_hal::rosc::{Enabled, RingOscillator};
+
+use crate::usb;
+
+pub type Rng = rng::Rng<Noise>;
+
+// ROSC cycles timed per sample.
+const JITTER_COUNT: u32 = 64;
+
+// Bytes per line of `random` output.
+const LINE_BYTES: usize = 32;
+
+pub struct Noise {
+    rosc: RingOscillator<Enabled>,
+}
+
+impl Noise {
+    // Loop iterations for the ROSC to count down `JITTER_COUNT` cycles, low byte.
+    fn jitter(&self) -> u8 {
+        // SAFETY: COUNT isn't used by the HAL's `RingOscillator` or anything else.
+        let registers = unsafe { &*pac::ROSC::ptr() };
+        registers.count.write(|w| unsafe { w.bits(JITTER_COUNT) });
+        let mut spins = 0u8;
+        while registers.count.read().bits() != 0 {
+            spins = spins.wrapping_add(1);
+        }
+        spins
+    }
+}
+
+impl NoiseSource for Noise {
+    fn sample(&mut self) -> u8 {
+        let mut bits = 0u8;
+        for _ in 0..8 {
+            bits = (bits << 1) | self.rosc.get_random_bit() as u8;
+        }
+        bits ^ self.jitter()
+    }
+}
+
+/// Seeds the generator, `None` if the ring oscillator fails its startup health tests.
+pub fn start(rosc: RingOscillator<Enabled>) -> Option<Rng> {
+    Rng::new(Noise { rosc }).ok()
+}
+
+fn write_hex(bytes: &[u8]) {
+    let mut line: String<{ LINE_BYTES * 2 + 2 }> = String::new();
+    for byte in bytes {
+        let _ = write!(line, "{:02x}", byte);
+    }
+    let _ = line.push_str("\r\n");
+    usb::write(line.as_bytes());
+}
+
+/// Handles `random ...` typed on the serial console, `words` are the ones after `random`.
+pub fn command(rng: Option<&mut Rng>, mut words: SplitWhitespace) {
+    let Some(rng) = rng else {
+        usb::write(b"no random numbers: the ring oscillator failed its health tests\r\n");
+        return;
+    };
+
+    let (raw, count) = match words.next() {
+        Some("health") => {
+            let mut text: String<128> = String::new();
+            let _ = write!(
+                text,
+                "samples {}\r\nrepetition failures {}\r\nproportion failures {}\r\nreseeds {}\r\n",
+                rng.health.samples, rng.health.repetition_failures, rng.health.proportion_failures, rng.reseeds
+            );
+            usb::write(text.as_bytes());
+            return;
+        }
+        Some("raw") => (true, words.next()),
+        count => (false, count),
+    };
+    let Some(mut count) = count.and_then(|count| count.parse::<u32>().ok()) else {
+        usb::write(b"usage: random [raw] BYTES | random health\r\n");
+        return;
+    };
+
+    let mut bytes = [0; LINE_BYTES];
+    while count > 0 {
+        let line = &mut bytes[..(count as usize).min(LINE_BYTES)];
+        if raw {
+            rng.raw(line);
+        } else {
+            rng.fill_bytes(line);
+        }
+        write_hex(line);
+        count -= line.len() as u32;
+    }
+}
@@ -0,0 +1,89 @@
+// Typing with the badge's buttons.
+//
+// Up/Down scroll through the characters and A adds the one showing. Past the ends of the
+// character list are "del", which removes the last character, and "ok", which finishes. The
+// candidate is shown in brackets after the text typed so far.
+
+use core::fmt::Write as FmtWrite;
+
+use heapless::String;
+
+pub const CHARACTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 .,:;-_/@!?#=+*'\"()";
+
+// Longest text `label` shows before cutting off the front.
+const LABEL_TAIL: usize = 16;
+
+enum Key {
+    Character(char),
+    Delete,
+    Done,
+}
+
+pub struct Entry<const N: usize> {
+    pub text: String<N>,
+    // Into the characters, then delete, then done.
+    cursor: usize,
+}
+
+impl<const N: usize> Default for Entry<N> {
+    fn default() -> Self {
+        Entry { text: String::new(), cursor: 0 }
+    }
+}
+
+impl<const N: usize> Entry<N> {
+    fn keys() -> usize {
+        CHARACTERS.len() + 2
+    }
+
+    fn key(&self) -> Key {
+        match CHARACTERS.as_bytes().get(self.cursor) {
+            Some(&byte) => Key::Character(byte as char),
+            None if self.cursor == CHARACTERS.len() => Key::Delete,
+            None => Key::Done,
+        }
+    }
+
+    /// Handles the buttons, returns true once "ok" is picked.
+    pub fn handle(&mut self, btn_up_pressed: bool, btn_down_pressed: bool, btn_a_pressed: bool) -> bool {
+        if btn_up_pressed {
+            self.cursor = (self.cursor + Self::keys() - 1) % Self::keys();
+        } else if btn_down_pressed {
+            self.cursor = (self.cursor + 1) % Self::keys();
+        } else if btn_a_pressed {
+            match self.key() {
+                Key::Character(c) => {
+                    let _ = self.text.push(c);
+                }
+                Key::Delete => {
+                    self.text.pop();
+                }
+                Key::Done => {
+                    self.cursor = 0;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// The end of the text, with the candidate in brackets while `typing`.
+    pub fn label(&self, typing: bool) -> String<32> {
+        let mut label = String::new();
+        let tail = self.text.char_indices().rev().nth(LABEL_TAIL - 1).map_or(0, |(i, _)| i);
+        if tail > 0 {
+            let _ = label.push_str("..");
+        }
+        let _ = label.push_str(&self.text[tail..]);
+
+        if typing {
+            let _ = match self.key() {
+                Key::Character(' ') => write!(label, "[ ]"),
+                Key::Character(c) => write!(label, "[{}]", c),
+                Key::Delete => write!(label, "[del]"),
+                Key::Done => write!(label, "[ok]"),
+            };
+        }
+        label
+    }
+}
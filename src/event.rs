@@ -0,0 +1,143 @@
+// Events the Countdown program counts down to, from the profile.
+//
+// An event is when it starts, optionally `..` when it ends, then its name:
+//
+//   2024-08-08 10:00..2024-08-11 18:00 DEF CON 32
+//   2024-10-19 09:00 BSides
+//   2024-12-27..2024-12-30 38C3
+//
+// Times are local and default to the start of the day. Without an end an event lasts until the
+// end of the day it starts on, and an end date without a time is the end of that day.
+
+use crate::calendar::{self, Date, Moment};
+
+pub struct Event<'a> {
+    pub name: &'a str,
+    pub start: Moment,
+    // Exclusive.
+    pub end: Moment,
+}
+
+pub enum Countdown {
+    Until { days: u32, hours: u8, minutes: u8 },
+    Now,
+    Over,
+}
+
+impl<'a> Event<'a> {
+    pub fn parse(line: &'a str) -> Result<Event<'a>, &'static str> {
+        let (start, _, rest) = moment(line.trim()).ok_or("events start with a date like 2024-08-08, then maybe 10:00")?;
+        let (end, name) = match rest.trim_start().strip_prefix("..") {
+            Some(rest) => match moment(rest).ok_or("events end with ..2024-08-11, then maybe 18:00")? {
+                (end, true, name) => (end, name),
+                (end, false, name) => (end.add_seconds(86400), name),
+            },
+            None => (Moment { hour: 0, minute: 0, second: 0, ..start }.add_seconds(86400), rest),
+        };
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("an event needs a name after its date");
+        }
+        if end <= start {
+            return Err("event ends before it starts");
+        }
+        Ok(Event { name, start, end })
+    }
+
+    pub fn countdown(&self, now: &Moment) -> Countdown {
+        if *now >= self.end {
+            return Countdown::Over;
+        }
+        if *now >= self.start {
+            return Countdown::Now;
+        }
+        // Round up, so it reads 0m only once the event has started.
+        let minutes = (self.start.timestamp() - now.timestamp() + 59) / 60;
+        Countdown::Until {
+            days: (minutes / (24 * 60)) as u32,
+            hours: (minutes / 60 % 24) as u8,
+            minutes: (minutes % 60) as u8,
+        }
+    }
+}
+
+// First word of `text`, up to a space or `..`.
+fn word(text: &str) -> &str {
+    let end = text.find(|c: char| c.is_whitespace()).unwrap_or(text.len());
+    let end = text[..end].find("..").unwrap_or(end);
+    &text[..end]
+}
+
+// A date and optional time off the front of `text`, returns it, whether the time was given, and
+// what follows.
+fn moment(text: &str) -> Option<(Moment, bool, &str)> {
+    let date = Date::parse(word(text))?;
+    let rest = &text[word(text).len()..];
+    let trimmed = rest.trim_start();
+    match calendar::parse_time(word(trimmed)) {
+        Some((hour, minute, second)) => Some((Moment { date, hour, minute, second }, true, &trimmed[word(trimmed).len()..])),
+        None => Some((Moment { date, hour: 0, minute: 0, second: 0 }, false, rest)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> Moment {
+        moment(text).unwrap().0
+    }
+
+    fn until(event: &Event, now: &str) -> Option<(u32, u8, u8)> {
+        match event.countdown(&at(now)) {
+            Countdown::Until { days, hours, minutes } => Some((days, hours, minutes)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_starts_ends_and_names() {
+        let event = Event::parse("2024-08-08 10:00..2024-08-11 18:00 DEF CON 32").unwrap();
+        assert_eq!(event.name, "DEF CON 32");
+        assert_eq!(event.start, at("2024-08-08 10:00"));
+        assert_eq!(event.end, at("2024-08-11 18:00"));
+
+        // Without an end it lasts the day, and an end date without a time takes in that day.
+        let event = Event::parse("2024-10-19 09:00 BSides").unwrap();
+        assert_eq!(event.end, at("2024-10-20"));
+        let event = Event::parse("2024-12-27..2024-12-30 38C3").unwrap();
+        assert_eq!(event.end, at("2024-12-31"));
+
+        assert!(Event::parse("2024-10-19").is_err());
+        assert!(Event::parse("2024-08-11..2024-08-08 Backwards").is_err());
+        assert!(Event::parse("2024-10-19 10:00..2024-10-19 09:00 Backwards").is_err());
+        assert!(Event::parse("Oct 19 BSides").is_err());
+    }
+
+    #[test]
+    fn counts_down_across_midnight() {
+        let event = Event::parse("2024-10-19 09:00 BSides").unwrap();
+        assert_eq!(until(&event, "2024-10-18 23:30"), Some((0, 9, 30)));
+        assert_eq!(until(&event, "2024-10-17 09:00"), Some((2, 0, 0)));
+        // Partial minutes round up, so 0m only shows once it's on.
+        assert_eq!(until(&event, "2024-10-19 08:59:01"), Some((0, 0, 1)));
+        assert!(matches!(event.countdown(&at("2024-10-19 09:00")), Countdown::Now));
+        assert!(matches!(event.countdown(&at("2024-10-19 23:59:59")), Countdown::Now));
+        assert!(matches!(event.countdown(&at("2024-10-20")), Countdown::Over));
+    }
+
+    #[test]
+    fn counts_down_across_the_new_year() {
+        let event = Event::parse("2024-12-30..2025-01-02 Congress").unwrap();
+        assert_eq!(until(&event, "2024-12-29 23:59:30"), Some((0, 0, 1)));
+        assert!(matches!(event.countdown(&at("2025-01-01 00:00")), Countdown::Now));
+        assert!(matches!(event.countdown(&at("2025-01-02 23:59:59")), Countdown::Now));
+        assert!(matches!(event.countdown(&at("2025-01-03")), Countdown::Over));
+
+        let event = Event::parse("2025-01-01 New Year").unwrap();
+        assert_eq!(until(&event, "2024-12-31 12:00"), Some((0, 12, 0)));
+        // 2024 is a leap year, 366 days to go.
+        assert_eq!(until(&event, "2024-01-01 00:00"), Some((366, 0, 0)));
+    }
+}
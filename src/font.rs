@@ -0,0 +1,303 @@
+// Proportional text on top of the existing monospace fonts.
+//
+// Glyphs still come from `MonoFont`s (ProFont for the UI), but each one advances by its inked
+// width plus a little tracking instead of the full cell, and a few classic pairs are kerned.
+// Digits keep their full cell so numbers that change (clocks, counters) don't jitter.
+//
+// Characters the font doesn't have are looked up in a chain of ISO-8859 fonts from
+// embedded-graphics, picked to roughly match the size, which covers Latin-1 and most of Latin
+// Extended-A. Anything still missing is drawn as the font's `?`.
+//
+// `TextStyle` implements the embedded-graphics text traits, so it works with `Text` and with
+// embedded-text's `TextBox` wherever a `MonoTextStyle` was used before.
+
+use embedded_graphics::{
+    image::GetPixel,
+    mono_font::{
+        iso_8859_1, iso_8859_13, iso_8859_14, iso_8859_16, iso_8859_2, iso_8859_3, iso_8859_4, iso_8859_9,
+        MonoFont,
+    },
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::Rectangle,
+    text::{
+        renderer::{CharacterStyle, TextMetrics, TextRenderer},
+        Baseline,
+    },
+};
+
+// Fallback chains, one per size. Order matters: the first font that has a glyph wins.
+static FALLBACK_SMALL: [MonoFont<'static>; 8] = [
+    iso_8859_1::FONT_6X10,
+    iso_8859_2::FONT_6X10,
+    iso_8859_3::FONT_6X10,
+    iso_8859_4::FONT_6X10,
+    iso_8859_9::FONT_6X10,
+    iso_8859_13::FONT_6X10,
+    iso_8859_14::FONT_6X10,
+    iso_8859_16::FONT_6X10,
+];
+
+static FALLBACK_MEDIUM: [MonoFont<'static>; 8] = [
+    iso_8859_1::FONT_7X13,
+    iso_8859_2::FONT_7X13,
+    iso_8859_3::FONT_7X13,
+    iso_8859_4::FONT_7X13,
+    iso_8859_9::FONT_7X13,
+    iso_8859_13::FONT_7X13,
+    iso_8859_14::FONT_7X13,
+    iso_8859_16::FONT_7X13,
+];
+
+static FALLBACK_LARGE: [MonoFont<'static>; 8] = [
+    iso_8859_1::FONT_10X20,
+    iso_8859_2::FONT_10X20,
+    iso_8859_3::FONT_10X20,
+    iso_8859_4::FONT_10X20,
+    iso_8859_9::FONT_10X20,
+    iso_8859_13::FONT_10X20,
+    iso_8859_14::FONT_10X20,
+    iso_8859_16::FONT_10X20,
+];
+
+// Pairs that get pulled together, in eighths of the cell width.
+const KERNING: [(char, char, i32); 28] = [
+    ('A', 'V', 1), ('V', 'A', 1), ('A', 'W', 1), ('W', 'A', 1),
+    ('A', 'Y', 1), ('Y', 'A', 1), ('A', 'T', 1), ('T', 'A', 1),
+    ('L', 'T', 1), ('L', 'Y', 1), ('L', 'V', 1), ('P', 'A', 1),
+    ('T', 'a', 1), ('T', 'e', 1), ('T', 'o', 1), ('T', 'r', 1),
+    ('V', 'a', 1), ('V', 'e', 1), ('V', 'o', 1), ('Y', 'a', 1),
+    ('Y', 'e', 1), ('Y', 'o', 1), ('P', '.', 1), ('P', ',', 1),
+    ('T', '.', 1), ('T', ',', 1), ('r', '.', 1), ('r', ',', 1),
+];
+
+// No font has a glyph for this, so its index is whatever a font uses for missing characters.
+const UNMAPPED: char = '\u{ffff}';
+
+struct Glyph<'a> {
+    font: &'a MonoFont<'a>,
+    // Top left of the glyph's cell in the font image.
+    cell: Point,
+    // Inked columns of the cell that get drawn.
+    left: u32,
+    width: u32,
+    advance: u32,
+}
+
+impl Glyph<'_> {
+    fn ink(&self) -> impl Iterator<Item = Point> + '_ {
+        let height = self.font.character_size.height as i32;
+        (0..self.width as i32).flat_map(move |x| {
+            (0..height).filter_map(move |y| {
+                let at = self.cell + Point::new(self.left as i32 + x, y);
+                (self.font.image.pixel(at) == Some(BinaryColor::On)).then_some(Point::new(x, y))
+            })
+        })
+    }
+}
+
+fn has_glyph(font: &MonoFont, c: char) -> bool {
+    c == '?' || font.glyph_mapping.index(c) != font.glyph_mapping.index(UNMAPPED)
+}
+
+fn cell(font: &MonoFont, c: char) -> Point {
+    let size = font.character_size;
+    let per_row = (font.image.size().width / size.width).max(1);
+    let index = font.glyph_mapping.index(c) as u32;
+    Point::new(((index % per_row) * size.width) as i32, ((index / per_row) * size.height) as i32)
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle<'a> {
+    font: &'a MonoFont<'a>,
+    fallbacks: &'static [MonoFont<'static>],
+    text_color: Option<BinaryColor>,
+    background_color: Option<BinaryColor>,
+}
+
+impl<'a> TextStyle<'a> {
+    pub fn new(font: &'a MonoFont<'a>, text_color: BinaryColor) -> Self {
+        let height = font.character_size.height;
+        let fallbacks = if height >= 20 {
+            &FALLBACK_LARGE
+        } else if height >= 13 {
+            &FALLBACK_MEDIUM
+        } else {
+            &FALLBACK_SMALL
+        };
+
+        TextStyle { font, fallbacks, text_color: Some(text_color), background_color: None }
+    }
+
+    // Tracking, space width and kerning all scale with the primary font.
+    fn unit(&self) -> u32 {
+        (self.font.character_size.width / 8).max(1)
+    }
+
+    fn space(&self) -> u32 {
+        self.font.character_size.width.div_ceil(2)
+    }
+
+    fn kerning(&self, left: char, right: char) -> i32 {
+        KERNING
+            .iter()
+            .find(|(l, r, _)| *l == left && *r == right)
+            .map_or(0, |(_, _, amount)| amount * self.unit() as i32)
+    }
+
+    fn glyph(&self, c: char) -> Glyph<'a> {
+        let font = if has_glyph(self.font, c) {
+            self.font
+        } else {
+            self.fallbacks.iter().find(|font| has_glyph(font, c)).unwrap_or(self.font)
+        };
+        let size = font.character_size;
+        let cell = cell(font, c);
+
+        if c.is_whitespace() {
+            return Glyph { font, cell, left: 0, width: 0, advance: self.space() };
+        }
+
+        if c.is_ascii_digit() {
+            return Glyph { font, cell, left: 0, width: size.width, advance: size.width + font.character_spacing };
+        }
+
+        let inked = |x: u32| (0..size.height).any(|y| font.image.pixel(cell + Point::new(x as i32, y as i32)) == Some(BinaryColor::On));
+        match ((0..size.width).find(|&x| inked(x)), (0..size.width).rev().find(|&x| inked(x))) {
+            (Some(left), Some(right)) => {
+                let width = right - left + 1;
+                Glyph { font, cell, left, width, advance: width + self.unit() }
+            }
+            _ => Glyph { font, cell, left: 0, width: 0, advance: self.space() },
+        }
+    }
+
+    fn baseline_offset(&self, baseline: Baseline) -> i32 {
+        let height = self.font.character_size.height.saturating_sub(1) as i32;
+        match baseline {
+            Baseline::Top => 0,
+            Baseline::Bottom => height,
+            Baseline::Middle => height / 2,
+            Baseline::Alphabetic => self.font.baseline as i32,
+        }
+    }
+
+    fn width(&self, text: &str) -> u32 {
+        let mut width = 0i32;
+        let mut previous = None;
+        for c in text.chars() {
+            if let Some(p) = previous {
+                width -= self.kerning(p, c);
+            }
+            width += self.glyph(c).advance as i32;
+            previous = Some(c);
+        }
+        width.max(0) as u32
+    }
+
+    /// Lines `text` wraps to in a box `width` wide, breaking at spaces and newlines like
+    /// embedded-text. A word wider than the box is broken wherever it runs out of room.
+    pub fn wrapped_lines(&self, text: &str, width: u32) -> u32 {
+        let space = self.space();
+        let mut lines = 0;
+        for paragraph in text.split('\n') {
+            lines += 1;
+            let mut used = 0;
+            for word in paragraph.split(' ') {
+                let word_width = self.width(word);
+                if used > 0 && used + space + word_width <= width {
+                    used += space + word_width;
+                    continue;
+                }
+                if used > 0 {
+                    lines += 1;
+                    used = 0;
+                }
+                if word_width <= width {
+                    used = word_width;
+                    continue;
+                }
+                for c in word.chars() {
+                    let advance = self.glyph(c).advance;
+                    if used > 0 && used + advance > width {
+                        lines += 1;
+                        used = 0;
+                    }
+                    used += advance;
+                }
+            }
+        }
+        lines
+    }
+}
+
+impl CharacterStyle for TextStyle<'_> {
+    type Color = BinaryColor;
+
+    fn set_text_color(&mut self, text_color: Option<BinaryColor>) {
+        self.text_color = text_color;
+    }
+
+    fn set_background_color(&mut self, background_color: Option<BinaryColor>) {
+        self.background_color = background_color;
+    }
+}
+
+impl TextRenderer for TextStyle<'_> {
+    type Color = BinaryColor;
+
+    fn draw_string<D>(&self, text: &str, position: Point, baseline: Baseline, target: &mut D) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let top = position.y - self.baseline_offset(baseline);
+        let alphabetic = top + self.font.baseline as i32;
+        let mut x = position.x;
+        let mut previous = None;
+
+        for c in text.chars() {
+            if let Some(p) = previous {
+                x -= self.kerning(p, c);
+            }
+            let glyph = self.glyph(c);
+
+            if let Some(background) = self.background_color {
+                target.fill_solid(&Rectangle::new(Point::new(x, top), Size::new(glyph.advance, self.line_height())), background)?;
+            }
+            if let Some(color) = self.text_color {
+                // Fallback glyphs sit on the same baseline as the primary font.
+                let origin = Point::new(x, alphabetic - glyph.font.baseline as i32);
+                target.draw_iter(glyph.ink().map(|p| Pixel(origin + p, color)))?;
+            }
+
+            x += glyph.advance as i32;
+            previous = Some(c);
+        }
+
+        Ok(Point::new(x, position.y))
+    }
+
+    fn draw_whitespace<D>(&self, width: u32, position: Point, baseline: Baseline, target: &mut D) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let top = position.y - self.baseline_offset(baseline);
+        if let Some(background) = self.background_color {
+            target.fill_solid(&Rectangle::new(Point::new(position.x, top), Size::new(width, self.line_height())), background)?;
+        }
+        Ok(position + Point::new(width as i32, 0))
+    }
+
+    fn measure_string(&self, text: &str, position: Point, baseline: Baseline) -> TextMetrics {
+        let width = self.width(text);
+        let top = position.y - self.baseline_offset(baseline);
+        TextMetrics {
+            bounding_box: Rectangle::new(Point::new(position.x, top), Size::new(width, self.line_height())),
+            next_position: position + Point::new(width as i32, 0),
+        }
+    }
+
+    fn line_height(&self) -> u32 {
+        self.font.character_size.height
+    }
+}
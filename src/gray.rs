@@ -0,0 +1,219 @@
+// Four-level grayscale for the UC8151.
+//
+// The panel only has two bits of RAM per pixel: the "old" plane (DTM1) and the "new" plane
+// (DTM2). Normally the driver uses them to pick a waveform for a 1-bit transition, here they are
+// loaded with two halves of a 2-bit level instead and the four transition LUTs are reprogrammed so
+// each combination settles at a different shade. Afterwards the regular LUTs are put back, so
+// every other screen keeps drawing 1-bit through the driver as usual.
+
+use embedded_graphics::{
+    pixelcolor::{BinaryColor, Gray2},
+    prelude::*,
+};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use uc8151::{Uc8151, HEIGHT, WIDTH};
+
+const PIXELS: usize = (WIDTH * HEIGHT) as usize;
+const PLANE_SIZE: usize = PIXELS / 8;
+
+// Commands
+const PON: u8 = 0x04;
+const DTM1: u8 = 0x10;
+const DRF: u8 = 0x12;
+const DTM2: u8 = 0x13;
+const LUT_VCOM: u8 = 0x20;
+const LUT_WW: u8 = 0x21;
+const LUT_BW: u8 = 0x22;
+const LUT_WB: u8 = 0x23;
+const LUT_BB: u8 = 0x24;
+
+// Waveforms, seven phases of six bytes each. Every level goes through the same clearing phases
+// first, the last phases then drive it towards its shade.
+const GRAY_LUT_VCOM: [u8; 44] = [
+    0x00, 0x0a, 0x00, 0x00, 0x00, 0x01,
+    0x60, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x00, 0x14, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x13, 0x0a, 0x01, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00,
+];
+
+// White
+const GRAY_LUT_WW: [u8; 42] = [
+    0x40, 0x0a, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x10, 0x14, 0x0a, 0x00, 0x00, 0x01,
+    0xa0, 0x13, 0x01, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+// Dark gray
+const GRAY_LUT_BW: [u8; 42] = [
+    0x40, 0x0a, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x00, 0x14, 0x0a, 0x00, 0x00, 0x01,
+    0x99, 0x0c, 0x01, 0x03, 0x04, 0x01,
+    0x02, 0x04, 0x01, 0x03, 0x04, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+// Light gray
+const GRAY_LUT_WB: [u8; 42] = [
+    0x40, 0x0a, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x00, 0x14, 0x0a, 0x00, 0x00, 0x01,
+    0x99, 0x0b, 0x04, 0x04, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+// Black
+const GRAY_LUT_BB: [u8; 42] = [
+    0x80, 0x0a, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x20, 0x14, 0x0a, 0x00, 0x00, 0x01,
+    0x50, 0x13, 0x01, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// A full screen at 2 bits per pixel, row-major. Level 0 is black, 3 is white.
+pub struct GrayFrame {
+    data: [u8; PIXELS / 4],
+}
+
+impl GrayFrame {
+    /// A blank (white) frame.
+    pub fn new() -> Self {
+        GrayFrame { data: [0xff; PIXELS / 4] }
+    }
+
+    fn set(&mut self, x: u32, y: u32, level: u8) {
+        let i = (y * WIDTH + x) as usize;
+        let shift = 6 - (i % 4) * 2;
+        self.data[i / 4] = (self.data[i / 4] & !(0b11 << shift)) | (level << shift);
+    }
+
+    fn get(&self, x: u32, y: u32) -> u8 {
+        let i = (y * WIDTH + x) as usize;
+        (self.data[i / 4] >> (6 - (i % 4) * 2)) & 0b11
+    }
+
+    /// 1-bit view of the frame, for drawing text and icons with the usual helpers.
+    /// `On` is white and `Off` is black, same as on the display.
+    pub fn binary(&mut self) -> BinaryView<'_> {
+        BinaryView { frame: self }
+    }
+}
+
+impl Default for GrayFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OriginDimensions for GrayFrame {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
+
+impl DrawTarget for GrayFrame {
+    type Color = Gray2;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Gray2>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 && (point.x as u32) < WIDTH && (point.y as u32) < HEIGHT {
+                self.set(point.x as u32, point.y as u32, color.luma());
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct BinaryView<'a> {
+    frame: &'a mut GrayFrame,
+}
+
+impl OriginDimensions for BinaryView<'_> {
+    fn size(&self) -> Size {
+        self.frame.size()
+    }
+}
+
+impl DrawTarget for BinaryView<'_> {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        self.frame.draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+            Pixel(point, if color.is_on() { Gray2::WHITE } else { Gray2::BLACK })
+        }))
+    }
+}
+
+// Packs one bit per pixel in the panel's RAM order: a byte is 8 pixels down a column, top pixel
+// in the MSB, and columns follow each other left to right.
+fn fill_plane(frame: &GrayFrame, plane: &mut [u8; PLANE_SIZE], bit: impl Fn(u8) -> bool) {
+    let column_bytes = (HEIGHT / 8) as usize;
+    for x in 0..WIDTH {
+        for y in 0..HEIGHT {
+            let i = (y / 8) as usize + x as usize * column_bytes;
+            let mask = 0x80 >> (y % 8);
+            if bit(frame.get(x, y)) {
+                plane[i] |= mask;
+            } else {
+                plane[i] &= !mask;
+            }
+        }
+    }
+}
+
+/// Refreshes the whole panel with `frame` in four shades, then puts the 1-bit LUTs back.
+pub fn show<SPI, CS, DC, BUSY, RESET>(
+    display: &mut Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+    frame: &GrayFrame,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let _ = display.command(LUT_VCOM, &GRAY_LUT_VCOM);
+    let _ = display.command(LUT_WW, &GRAY_LUT_WW);
+    let _ = display.command(LUT_BW, &GRAY_LUT_BW);
+    let _ = display.command(LUT_WB, &GRAY_LUT_WB);
+    let _ = display.command(LUT_BB, &GRAY_LUT_BB);
+
+    // Old plane holds the high bit, new plane the low bit:
+    // white 11 -> WW, light 10 -> WB, dark 01 -> BW, black 00 -> BB.
+    let mut plane = [0u8; PLANE_SIZE];
+    fill_plane(frame, &mut plane, |level| level & 0b10 != 0);
+    let _ = display.command(DTM1, &plane);
+    fill_plane(frame, &mut plane, |level| level & 0b01 != 0);
+    let _ = display.command(DTM2, &plane);
+
+    let _ = display.command(PON, &[]);
+    while display.is_busy() {}
+    let _ = display.command(DRF, &[]);
+    while display.is_busy() {}
+
+    let _ = display.setup(delay, uc8151::LUT::Fast);
+}
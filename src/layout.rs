@@ -0,0 +1,136 @@
+// Screen templates.
+//
+// Screens describe what they show as a `Layout` and `draw` works out where everything goes from
+// the target's size, the images and the fonts, instead of each program repeating coordinates.
+// Portrait targets (taller than wide) stack the picture above the text.
+
+use embedded_graphics::{
+    mono_font::MonoFont,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyleBuilder, Rectangle},
+};
+use embedded_text::alignment::HorizontalAlignment;
+use profont::*;
+
+use crate::assets::Asset;
+use crate::draw;
+
+pub const TITLE_FONT: MonoFont = PROFONT_24_POINT;
+pub const HEADING_FONT: MonoFont = PROFONT_18_POINT;
+pub const BODY_FONT: MonoFont = PROFONT_14_POINT;
+pub const STATUS_FONT: MonoFont = PROFONT_9_POINT;
+
+// Space between a picture and the text next to it, and between stacked blocks.
+const GAP: i32 = 10;
+const LINE_GAP: i32 = 4;
+
+pub enum Layout<'a> {
+    /// Program screen: icon in the corner, title beside it, body text underneath.
+    App { icon: &'a Asset, title: &'a str, body: &'a str },
+    /// Name card: photo, name and details beside it, a row of small badges along the bottom.
+    Card { photo: &'a Asset, name: &'a str, details: &'a str, badges: &'a [&'a Asset] },
+    /// A code to scan with a heading and caption beside it.
+    Qr { code: &'a Asset, heading: &'a str, caption: &'a str },
+    /// App header over a page of selectable rows, `footer` in the bottom right corner.
+    List { icon: &'a Asset, title: &'a str, items: &'a [&'a str], selected: usize, footer: &'a str },
+}
+
+pub fn draw<D>(display: &mut D, layout: &Layout) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let screen = display.bounding_box().size;
+
+    match *layout {
+        Layout::App { icon, title, body } => {
+            let top = header(display, icon, title);
+            draw::draw_textbox(display, body, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, screen.width, 0);
+        }
+        Layout::Card { photo, name, details, badges } => {
+            let text = beside(display, photo, screen);
+            draw::draw_textbox(display, name, TITLE_FONT, BinaryColor::Off, HorizontalAlignment::Left, text.top_left.x, text.top_left.y, text.size.width, 0);
+            let details_y = text.top_left.y + line_height(&TITLE_FONT) + LINE_GAP;
+            draw::draw_textbox(display, details, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Left, text.top_left.x, details_y, text.size.width, 0);
+
+            // Badges share a baseline at the bottom of the text column.
+            let row = badges.iter().map(|badge| badge.height).max().unwrap_or(0) as i32;
+            let y = (text.top_left.y + text.size.height as i32) - row - LINE_GAP;
+            let mut x = text.top_left.x;
+            for badge in badges {
+                draw::draw_image(display, badge, x, y + row - badge.height as i32);
+                x += badge.width as i32 + GAP;
+            }
+        }
+        Layout::Qr { code, heading, caption } => {
+            let text = beside(display, code, screen);
+            let (x, y, width) = (text.top_left.x, text.top_left.y + LINE_GAP, text.size.width);
+            draw::draw_textbox(display, heading, HEADING_FONT, BinaryColor::Off, HorizontalAlignment::Left, x, y, width, 0);
+            let caption_y = y + line_height(&HEADING_FONT) + LINE_GAP * 2;
+            draw::draw_textbox(display, caption, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Left, x, caption_y, width, 0);
+        }
+        Layout::List { icon, title, items, selected, footer } => {
+            let top = header(display, icon, title);
+            let row = line_height(&BODY_FONT) + 2;
+            let marker = (line_height(&BODY_FONT) * 2 / 3) as u32;
+
+            for (index, item) in items.iter().enumerate() {
+                let y = top + index as i32 * row;
+                let fill = if index == selected { BinaryColor::Off } else { BinaryColor::On };
+                let style = PrimitiveStyleBuilder::new()
+                    .stroke_color(BinaryColor::Off)
+                    .stroke_width(3)
+                    .fill_color(fill)
+                    .build();
+                let _ = Rectangle::new(Point::new(GAP, y + (row - marker as i32) / 2), Size::new(marker, marker))
+                    .into_styled(style)
+                    .draw(display);
+                draw::draw_textbox(display, item, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Left, GAP * 2 + marker as i32, y, screen.width - (GAP * 2) as u32 - marker, 0);
+            }
+
+            let footer_y = screen.height as i32 - line_height(&BODY_FONT);
+            draw::draw_textbox(display, footer, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Right, 0, footer_y, screen.width - LINE_GAP as u32, 0);
+        }
+    }
+}
+
+/// Icon and title across the top of the screen, returns where the content below it starts.
+pub fn header<D>(display: &mut D, icon: &Asset, title: &str) -> i32 where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let width = display.bounding_box().size.width;
+    let title_y = (icon.height as i32 - line_height(&TITLE_FONT)).max(0) / 2;
+    let x = icon.width as i32 + GAP;
+
+    draw::draw_image(display, icon, 0, 0);
+    draw::draw_textbox(display, title, TITLE_FONT, BinaryColor::Off, HorizontalAlignment::Left, x, title_y, width.saturating_sub(x as u32), 0);
+
+    (icon.height as i32).max(title_y + line_height(&TITLE_FONT)) + LINE_GAP + 2
+}
+
+/// Small text in the top right corner, over the header's title row, e.g. the time.
+pub fn status<D>(display: &mut D, text: &str) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let width = display.bounding_box().size.width;
+    draw::draw_textbox(display, text, STATUS_FONT, BinaryColor::Off, HorizontalAlignment::Right, 0, LINE_GAP, width - LINE_GAP as u32, 0);
+}
+
+// Draws `picture` in the top left corner and returns the area left for text: to its right, or
+// below it on a portrait screen.
+fn beside<D>(display: &mut D, picture: &Asset, screen: Size) -> Rectangle where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    draw::draw_image(display, picture, 0, 0);
+
+    if screen.height > screen.width {
+        let y = picture.height + GAP as u32;
+        Rectangle::new(Point::new(0, y as i32), Size::new(screen.width, screen.height.saturating_sub(y)))
+    } else {
+        let x = picture.width + GAP as u32 + 5;
+        Rectangle::new(Point::new(x as i32, 0), Size::new(screen.width.saturating_sub(x), screen.height))
+    }
+}
+
+fn line_height(font: &MonoFont) -> i32 {
+    font.character_size.height as i32
+}
@@ -12,10 +12,26 @@
 #![no_std]
 #![no_main]
 
+mod assets;
+mod barcode;
+mod calendar;
+mod clock;
 mod draw;
+mod entropy;
+mod entry;
+mod event;
+mod font;
+mod gray;
+mod layout;
 mod usb;
 mod libs;
+mod pin;
+mod profile;
 mod programs;
+mod schedule;
+mod screens;
+mod slide;
+mod store;
 
 // The macro for our start-up function
 use rp_pico::entry;
@@ -45,6 +61,11 @@ use usb_device::{class_prelude::*, prelude::*};
 // USB Communications Class Device support
 use usbd_serial::SerialPort;
 
+// USB HID for the security key, and the keyboard for macros
+use usbd_hid::hid_class::HIDClass;
+#[cfg(feature = "keyboard-macros")]
+use usbd_hid::descriptor::{KeyboardReport, SerializedDescriptor};
+
 /// The USB Device Driver (shared with the interrupt).
 static mut USB_DEVICE: Option<UsbDevice<hal::usb::UsbBus>> = None;
 
@@ -54,6 +75,13 @@ static mut USB_BUS: Option<UsbBusAllocator<hal::usb::UsbBus>> = None;
 /// The USB Serial Device Driver (shared with the interrupt).
 static mut USB_SERIAL: Option<SerialPort<hal::usb::UsbBus>> = None;
 
+/// The USB HID Device Driver for the security key (shared with the interrupt).
+static mut USB_HID: Option<HIDClass<hal::usb::UsbBus>> = None;
+
+/// The USB HID Device Driver for the macro keyboard (shared with the interrupt).
+#[cfg(feature = "keyboard-macros")]
+static mut USB_KEYBOARD: Option<HIDClass<hal::usb::UsbBus>> = None;
+
 // Display
 use fugit::RateExtU32;
 use fugit::ExtU32;
@@ -66,16 +94,32 @@ use embedded_graphics::{
 use embedded_hal::blocking::spi::Write;
 
 // Programs
+use crate::programs::authenticator::{draw_authenticator_screen, handle_authenticator_program, Authenticator};
+use crate::programs::barcodes::{draw_barcodes_screen, handle_barcodes_program};
 use crate::programs::blinky::{draw_blinky_screen, handle_blinky_program};
 use crate::programs::ccnb::draw_ccnb_screen;
 use crate::programs::error::draw_error_screen;
+use crate::programs::identity::{draw_identity_screen, DeviceKey};
 use crate::programs::info::draw_info_screen;
-use crate::programs::main::draw_main_screen;
+use crate::programs::clock::{draw_clock_screen, handle_clock_program, ClockFace};
+use crate::programs::countdown::{draw_countdown_screen, handle_countdown_program, Countdowns};
+use crate::programs::crypto::{draw_crypto_screen, handle_crypto_program, Crypto};
+#[cfg(feature = "keyboard-macros")]
+use crate::programs::macros::{draw_macros_screen, handle_macros_program, Macros};
 use crate::programs::menu;
+use crate::programs::notes::{draw_notes_screen, handle_notes_program, Notes};
+use crate::programs::passwords::{draw_passwords_screen, handle_passwords_program, Passwords};
+use crate::programs::playlist::{draw_playlist_screen, handle_playlist_program, schedule_command, Playlist};
+use crate::programs::privacy::{draw_reveal_screen, handle_reveal_program, Privacy};
+use crate::programs::security_key::{draw_security_key_screen, handle_security_key_program, SecurityKey};
+use crate::programs::settings::{draw_settings_screen, handle_settings_program, Settings};
 use crate::programs::socials::draw_socials_screen;
+use crate::programs::workbench::{draw_workbench_screen, handle_workbench_program, Workbench};
+use crate::clock::Clock;
 
 use panic_halt as _;
 
+#[derive(Clone, Copy)]
 pub enum ProgramState {
     Menu,
     Lynix,
@@ -83,6 +127,21 @@ pub enum ProgramState {
     Socials,
     Info,
     Blinky,
+    Barcodes,
+    Crypto,
+    Settings,
+    Clock,
+    Countdown,
+    Workbench,
+    Authenticator,
+    Identity,
+    Passwords,
+    Notes,
+    SecurityKey,
+    #[cfg(feature = "keyboard-macros")]
+    KeyboardMacros,
+    // PIN entry to show contact details in privacy mode, not in the menu.
+    Reveal,
     NotFound,
 }
 
@@ -123,6 +182,68 @@ fn main() -> ! {
     .ok()
     .unwrap();
 
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    unsafe {
+        // Note (safety): This is safe as interrupts haven't been started yet
+        USB_BUS = Some(usb_bus);
+    }
+
+    // Grab a reference to the USB Bus allocator. We are promising to the
+    // compiler not to take mutable access to this global variable whilst this
+    // reference exists!
+    let bus_ref = unsafe { USB_BUS.as_ref().unwrap() };
+
+    // Set up the USB Communications Class Device driver
+    let serial = SerialPort::new(bus_ref);
+    unsafe {
+        USB_SERIAL = Some(serial);
+    }
+
+    // Set up the USB HID driver, a FIDO security key polled every 5ms
+    let hid = HIDClass::new(bus_ref, usb::FIDO_REPORT_DESCRIPTOR, 5);
+    unsafe {
+        USB_HID = Some(hid);
+    }
+
+    // Set up the USB keyboard for Keyboard Macros, which only types once armed on screen
+    #[cfg(feature = "keyboard-macros")]
+    {
+        let keyboard = HIDClass::new(bus_ref, KeyboardReport::desc(), 10);
+        unsafe {
+            USB_KEYBOARD = Some(keyboard);
+        }
+    }
+
+    // Create a USB device with a fake VID and PID
+    let usb_dev = UsbDeviceBuilder::new(bus_ref, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Lynix")
+        .product("Lynix Badger")
+        .serial_number("LYNIXFW")
+        .composite_with_iads() // serial and HID, from: https://www.usb.org/defined-class-codes
+        .build();
+    unsafe {
+        // Note (safety): This is safe as interrupts haven't been started yet
+        USB_DEVICE = Some(usb_dev);
+    }
+
+    // Enable the USB interrupt
+    unsafe {
+        pac::NVIC::unmask(hal::pac::Interrupt::USBCTRL_IRQ);
+    };
+
+    // Wall clock, counting from a placeholder until the host sets it
+    let rtc = hal::rtc::RealTimeClock::new(pac.RTC, clocks.rtc_clock, &mut pac.RESETS, clock::PLACEHOLDER)
+        .ok()
+        .unwrap();
+    let mut clock = Clock::new(rtc);
+
     let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
     let timer = Timer::new(pac.TIMER, &mut pac.RESETS);
 
@@ -137,6 +258,15 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
+    // Badger 2040 W: the battery-backed RTC on I2C0 keeps the time while the badge is off
+    #[cfg(feature = "badger2040w")]
+    {
+        let sda = pins.gpio4.into_mode::<hal::gpio::FunctionI2C>();
+        let scl = pins.gpio5.into_mode::<hal::gpio::FunctionI2C>();
+        let i2c = hal::I2C::i2c0(pac.I2C0, sda, scl, RateExtU32::kHz(400), &mut pac.RESETS, &clocks.system_clock);
+        clock.attach(pcf85063a::Pcf85063a::new(i2c));
+    }
+
     // Set up the pins for the e-ink display
     let _spi_sclk = pins.gpio18.into_mode::<hal::gpio::FunctionSpi>();
     let _spi_mosi = pins.gpio19.into_mode::<hal::gpio::FunctionSpi>();
@@ -192,10 +322,11 @@ fn main() -> ! {
 
     let _ = display.update();
 
-    let items = ["Lynix Badge", "CCNB", "Socials + QR", "Device Info", "Blinky", "DEFCON Furs", "Cryptography", "Settings"];
+    let items = ["Lynix Badge", "CCNB", "Socials + QR", "Device Info", "Blinky", "Barcodes", "DEFCON Furs", "Cryptography", "Settings", "Clock", "Countdown", "Workbench", "Authenticator", "Identity", "Passwords", "Notes", "Security Key", #[cfg(feature = "keyboard-macros")] "Keyboard Macros"];
 
     // Draw menu items.
     let mut selected_item = 0;
+    let mut selected_code = 0;
 
     //crate::programs::menu::draw_menu(&mut display, items, selected_item, 0);
 
@@ -205,11 +336,76 @@ fn main() -> ! {
     let mut initial_screen_drawn = false;
     let mut current_program = ProgramState::Lynix;
 
-    // Countdown
-    let mut counter = 0;
+    // Badge rotation
+    let mut playlist = Playlist::default();
+
+    // Cryptography program
+    let mut crypto = Crypto::default();
+
+    // Settings program
+    let mut settings = Settings::default();
+
+    // Clock program
+    let mut clock_face = ClockFace::default();
+
+    // Countdown program
+    let mut countdowns = Countdowns::default();
+
+    // Cipher workbench program
+    let mut workbench = Workbench::default();
+
+    // Authenticator program, accounts saved in flash
+    let mut authenticator = Authenticator::load();
+
+    // Hardware random numbers, None if the ring oscillator looks broken
+    let rosc = hal::rosc::RingOscillator::new(pac.ROSC).initialize();
+    let mut rng = entropy::start(rosc);
+
+    // Identity program, the keypair is made on first boot
+    let device_key = DeviceKey::load_or_create(rng.as_mut());
+
+    // Password generator program
+    let mut passwords = Passwords::default();
+
+    // Notes program, encrypted in flash
+    let mut notes = Notes::load();
+
+    // Security key program, the secrets are made on first boot
+    let mut security_key = SecurityKey::load_or_create(rng.as_mut());
+
+    // Privacy mode, and the screen to go back to after the PIN
+    let mut privacy = Privacy::load();
+    let mut revealing = ProgramState::Lynix;
+
+    // Keyboard Macros program, disarmed whenever it's left
+    #[cfg(feature = "keyboard-macros")]
+    let mut macros = Macros::default();
 
     loop {
         count_down.start(1u32.secs());
+        let uptime = timer.get_counter().ticks() / 1_000_000;
+
+        // Serial console
+        while let Some(line) = usb::take_command() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("test") => usb::write(b"lynix is cute.\r\n"),
+                Some("playlist") => playlist.command(words),
+                Some("time") => clock.command(words),
+                Some("hash") => crypto.command(&line),
+                Some("bake") => workbench.command(&line),
+                Some("otp") => authenticator.command(words, uptime),
+                Some("identity" | "sign" | "sign-hex") => match &device_key {
+                    Some(device_key) => device_key.command(&line),
+                    None => usb::write(b"no identity key\r\n"),
+                },
+                Some("random") => entropy::command(rng.as_mut(), words),
+                Some("notes") => notes.command(&line, rng.as_mut()),
+                Some("privacy") => privacy.command(words, uptime),
+                Some("schedule") => schedule_command(profile::SCHEDULE, clock.now().as_ref()),
+                _ => usb::write(b"unknown command\r\n"),
+            }
+        }
 
         // Read Buttons
         let btn_up_pressed = btn_up.is_high().unwrap();
@@ -219,11 +415,32 @@ fn main() -> ! {
         //let btn_c_pressed = btn_b.is_high().unwrap();
 
         if btn_b_pressed {
+            notes.lock();
+            passwords.forget();
+            #[cfg(feature = "keyboard-macros")]
+            macros.disarm();
             initial_screen_drawn = false;
+            led_pin.set_low().unwrap();
             current_program = ProgramState::Menu;
             let _ = display.clear(BinaryColor::On);
         }
 
+        // Privacy mode: Up and Down together show or hide the contact details, which hide again
+        // after the timeout
+        let private = matches!(current_program, ProgramState::Lynix | ProgramState::Socials);
+        if privacy.update(uptime) && private {
+            initial_screen_drawn = false;
+        }
+        let chord = private && privacy.enabled() && btn_up_pressed && btn_down_pressed;
+        if chord {
+            if privacy.toggle(uptime) {
+                revealing = current_program;
+                current_program = ProgramState::Reveal;
+            }
+            initial_screen_drawn = false;
+        }
+        let (btn_up_pressed, btn_down_pressed) = (btn_up_pressed && !chord, btn_down_pressed && !chord);
+
         match current_program {
             ProgramState::Menu => {
                 count_down.start(1u32.millis());
@@ -237,7 +454,7 @@ fn main() -> ! {
                     let _ = display.update();
                     let _ = display.update();
 
-                    menu::draw_menu(&mut display, items, selected_item, 0);
+                    menu::draw_menu(&mut display, &items, selected_item, 0, &clock::status(clock.now().as_ref()));
                     let _ = display.update();
                     let _ = display.update();
                     initial_screen_drawn = true;
@@ -245,10 +462,11 @@ fn main() -> ! {
 
                 menu::handle_menu_program(
                     &mut display,
-                    items,
+                    &items,
                     &mut selected_item,
                     btn_up_pressed,
                     btn_down_pressed,
+                    &clock::status(clock.now().as_ref()),
                 );
 
                 let new_program = menu::launch_selected_program(
@@ -273,62 +491,249 @@ fn main() -> ! {
                 // Handle Blinky program logic
                 handle_blinky_program(&mut led_pin, &mut delay, false);
             }
+            ProgramState::Barcodes => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_barcodes_screen(&mut display, selected_code);
+                    initial_screen_drawn = true;
+                }
+
+                handle_barcodes_program(
+                    &mut display,
+                    &mut selected_code,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                );
+            }
             ProgramState::Lynix => {
+                playlist.schedule(schedule::active(profile::SCHEDULE, clock.now().as_ref()));
+
                 // Draw Screen
                 if !initial_screen_drawn {
-                    draw_main_screen(&mut display);
+                    draw_playlist_screen(&mut display, &mut delay, &mut led_pin, &mut playlist, privacy.hidden());
                     initial_screen_drawn = true;
                 }
-                counter += 1;
 
-                if counter == 10 {
-                    let _ = display.clear(BinaryColor::On);
-                    draw_socials_screen(&mut display);
-                    led_pin.set_high().unwrap();
+                handle_playlist_program(
+                    &mut display,
+                    &mut delay,
+                    &mut led_pin,
+                    &mut playlist,
+                    privacy.hidden(),
+                    uptime,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+            }
+            ProgramState::Ccnb => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_ccnb_screen(&mut display, &mut delay);
+                    initial_screen_drawn = true;
+                }
+            }
+            ProgramState::Socials => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_socials_screen(&mut display, privacy.hidden());
+                    initial_screen_drawn = true;
+                }
+            }
+            ProgramState::Info => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_info_screen(&mut display);
+                    initial_screen_drawn = true;
+                }
+            }
+            ProgramState::Crypto => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_crypto_screen(&mut display, &mut crypto);
+                    initial_screen_drawn = true;
                 }
 
-                if counter == 20 {
-                    counter = 0;
-                    initial_screen_drawn = false;
-                    let _ = display.clear(BinaryColor::On);
-                    led_pin.set_low().unwrap();
+                handle_crypto_program(
+                    &mut display,
+                    &mut crypto,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+            }
+            ProgramState::Settings => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_settings_screen(&mut display, &clock, &mut settings);
+                    initial_screen_drawn = true;
                 }
+
+                handle_settings_program(
+                    &mut display,
+                    &mut clock,
+                    &mut settings,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
             }
-            ProgramState::Ccnb => {
+            ProgramState::Clock => {
                 // Draw Screen
                 if !initial_screen_drawn {
-                    draw_ccnb_screen(&mut display);
+                    draw_clock_screen(&mut display, &clock, &mut clock_face);
                     initial_screen_drawn = true;
                 }
 
-                counter += 1;
+                handle_clock_program(
+                    &mut display,
+                    &clock,
+                    &mut clock_face,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+            }
+            ProgramState::Countdown => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_countdown_screen(&mut display, &clock, &mut countdowns);
+                    initial_screen_drawn = true;
+                }
 
-                if counter == 10 {
-                    let _ = display.clear(BinaryColor::On);
-                    draw_socials_screen(&mut display);
-                    led_pin.set_high().unwrap();
+                handle_countdown_program(
+                    &mut display,
+                    &clock,
+                    &mut countdowns,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                );
+            }
+            ProgramState::Workbench => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_workbench_screen(&mut display, &mut workbench);
+                    initial_screen_drawn = true;
                 }
 
-                if counter == 20 {
-                    counter = 0;
-                    initial_screen_drawn = false;
-                    let _ = display.clear(BinaryColor::On);
-                    led_pin.set_low().unwrap();
+                handle_workbench_program(
+                    &mut display,
+                    &mut workbench,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+            }
+            ProgramState::Authenticator => {
+                // Draw Screen, locked again every time it's opened
+                if !initial_screen_drawn {
+                    authenticator.lock();
+                    draw_authenticator_screen(&mut display, &clock, &mut authenticator, uptime);
+                    initial_screen_drawn = true;
                 }
+
+                handle_authenticator_program(
+                    &mut display,
+                    &clock,
+                    &mut authenticator,
+                    uptime,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
             }
-            ProgramState::Socials => {
+            ProgramState::Identity => {
                 // Draw Screen
                 if !initial_screen_drawn {
-                    draw_socials_screen(&mut display);
+                    draw_identity_screen(&mut display, device_key.as_ref());
                     initial_screen_drawn = true;
                 }
             }
-            ProgramState::Info => {
+            ProgramState::Passwords => {
+                // Draw Screen, nothing from last time is kept
+                if !initial_screen_drawn {
+                    passwords.forget();
+                    draw_passwords_screen(&mut display, &passwords, rng.is_some());
+                    initial_screen_drawn = true;
+                }
+
+                handle_passwords_program(
+                    &mut display,
+                    &mut passwords,
+                    rng.as_mut(),
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+            }
+            ProgramState::Notes => {
+                // Draw Screen, locked again every time it's opened
+                if !initial_screen_drawn {
+                    notes.lock();
+                    draw_notes_screen(&mut display, &mut notes, uptime);
+                    initial_screen_drawn = true;
+                }
+
+                handle_notes_program(
+                    &mut display,
+                    &mut notes,
+                    uptime,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+            }
+            ProgramState::SecurityKey => {
                 // Draw Screen
                 if !initial_screen_drawn {
-                    draw_info_screen(&mut display);
+                    draw_security_key_screen(&mut display, security_key.as_mut().map_err(|unavailable| *unavailable), uptime);
+                    initial_screen_drawn = true;
+                }
+
+                handle_security_key_program(
+                    &mut display,
+                    security_key.as_mut().map_err(|unavailable| *unavailable),
+                    uptime,
+                    btn_a_pressed,
+                );
+            }
+            #[cfg(feature = "keyboard-macros")]
+            ProgramState::KeyboardMacros => {
+                // Draw Screen, never armed on opening
+                if !initial_screen_drawn {
+                    macros.disarm();
+                    draw_macros_screen(&mut display, &macros);
                     initial_screen_drawn = true;
                 }
+
+                handle_macros_program(
+                    &mut display,
+                    &mut delay,
+                    &mut macros,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+            }
+            ProgramState::Reveal => {
+                // Draw Screen
+                if !initial_screen_drawn {
+                    draw_reveal_screen(&mut display, &mut privacy, uptime);
+                    initial_screen_drawn = true;
+                }
+
+                let done = handle_reveal_program(
+                    &mut display,
+                    &mut privacy,
+                    uptime,
+                    btn_up_pressed,
+                    btn_down_pressed,
+                    btn_a_pressed,
+                );
+                if done {
+                    current_program = revealing;
+                    initial_screen_drawn = false;
+                }
             }
             ProgramState::NotFound => {
                 if !initial_screen_drawn {
@@ -344,6 +749,12 @@ fn main() -> ! {
             }
             // Handle programs that are not found
         }
-        let _ = nb::block!(count_down.wait());
+
+        // Answer the security key while waiting, hosts expect replies within moments
+        while count_down.wait().is_err() {
+            if let (Ok(security_key), Some(rng)) = (security_key.as_mut(), rng.as_mut()) {
+                security_key.poll(rng, uptime);
+            }
+        }
     }
 }
\ No newline at end of file
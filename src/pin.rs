@@ -0,0 +1,100 @@
+// PIN entry with the badge's buttons, for the programs that lock.
+//
+// Up/Down change the digit in brackets and A adds it. Past 9 are "del", which removes the last
+// digit, and "ok", which finishes. Digits already entered show as `*`. After three wrong PINs in
+// a row each try has to wait, 30 seconds and doubling (see `wait`).
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::hash::{self, Algorithm};
+use heapless::String;
+
+use crate::store;
+
+pub const MIN_PIN: usize = 4;
+pub const MAX_PIN: usize = 8;
+
+// Wrong PINs that don't have to wait.
+const FREE_TRIES: u8 = 3;
+// First wait in seconds, doubled for every wrong PIN after it up to a day.
+const FIRST_WAIT: u64 = 30;
+const MAX_WAIT: u64 = 24 * 60 * 60;
+
+// Candidates past the digits.
+const DELETE: u8 = 10;
+const DONE: u8 = 11;
+
+/// Whether `pin` is 4 to 8 digits.
+pub fn valid(pin: &str) -> bool {
+    (MIN_PIN..=MAX_PIN).contains(&pin.len()) && pin.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Seconds before another PIN can be tried after `failures` wrong ones in a row.
+pub fn wait(failures: u8) -> u64 {
+    match failures.checked_sub(FREE_TRIES) {
+        Some(extra) => (FIRST_WAIT << extra.min(16)).min(MAX_WAIT),
+        None => 0,
+    }
+}
+
+/// HMAC-SHA256 of `pin` keyed with the flash chip's unique ID, what's kept in flash instead of
+/// the PIN. The key keeps one table of every PIN's hash from working on every badge.
+pub fn hash(pin: &str) -> [u8; 32] {
+    let mut hash = [0; 32];
+    // Only CRC32 has no HMAC.
+    let mac = hash::hmac(Algorithm::Sha256, &store::unique_id(), pin.as_bytes()).unwrap();
+    hash.copy_from_slice(mac.as_bytes());
+    hash
+}
+
+#[derive(Default)]
+pub struct PinPad {
+    entered: String<MAX_PIN>,
+    candidate: u8,
+}
+
+impl PinPad {
+    pub fn clear(&mut self) {
+        self.entered.clear();
+        self.candidate = 0;
+    }
+
+    /// Handles the buttons, returns the PIN once "ok" is picked and starts over.
+    pub fn handle(&mut self, btn_up_pressed: bool, btn_down_pressed: bool, btn_a_pressed: bool) -> Option<String<MAX_PIN>> {
+        let candidates = DONE + 1;
+        if btn_up_pressed {
+            self.candidate = (self.candidate + candidates - 1) % candidates;
+        } else if btn_down_pressed {
+            self.candidate = (self.candidate + 1) % candidates;
+        } else if btn_a_pressed {
+            match self.candidate {
+                DELETE => {
+                    self.entered.pop();
+                }
+                DONE => {
+                    let pin = self.entered.clone();
+                    self.clear();
+                    return Some(pin);
+                }
+                digit => {
+                    let _ = self.entered.push((b'0' + digit) as char);
+                }
+            }
+        }
+        None
+    }
+
+    /// What's entered so far and the candidate, like `* * [7]`.
+    pub fn label(&self) -> String<32> {
+        let mut label = String::new();
+        for _ in 0..self.entered.len() {
+            let _ = label.push_str("* ");
+        }
+        let _ = match self.candidate {
+            DELETE => write!(label, "[del]"),
+            DONE => write!(label, "[ok]"),
+            digit => write!(label, "[{}]", digit),
+        };
+        label
+    }
+}
@@ -0,0 +1,7 @@
+// The badge owner's name, details, badges, socials, time zone, schedule, events, privacy mode, barcodes and keyboard macros, generated by build.rs from `profiles/*.toml`.
+
+use crate::assets::{self, Asset, GrayAsset};
+use crate::barcode::Symbology;
+use crate::calendar::{Dst, UtcOffset};
+
+include!(concat!(env!("OUT_DIR"), "/profile.rs"));
@@ -0,0 +1,482 @@
+// Authenticator: TOTP and HOTP codes, like a phone authenticator app, behind a PIN.
+//
+// Accounts are added over serial from the `otpauth://` URIs sites show as QR codes, and kept in
+// flash (see `store`) with a hash of the PIN (see `pin::hash`). The program opens locked every time, the PIN is
+// entered with the buttons (see `pin`). Wrong PINs, on the buttons or to `otp pin OLD NEW`, count
+// towards the same waits as Notes, and the count is kept in flash. Unlocked, Up/Down browse the
+// accounts. TOTP codes need
+// the clock set to UTC (`time utc ...`) and show a bar for the seconds left; HOTP codes stay
+// hidden until A, which shows the next code and counts it.
+//
+//   otp pin 1234                      sets the PIN, 4 to 8 digits. Changing it: otp pin OLD NEW
+//   otp add otpauth://totp/...        adds an account, or replaces one with the same name
+//   otp list                          account names, never the secrets or codes
+//   otp remove 2                      removes an account by its number in the list
+//   otp clear                         forgets every account and the PIN
+//
+// The secrets aren't encrypted in flash, the PIN only keeps the codes off the screen.
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::otp::{self, Algorithm, Kind, Uri, MAX_PERIOD, MAX_SECRET};
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::{String, Vec};
+use uc8151::UpdateRegion;
+
+use crate::assets;
+use crate::clock::Clock;
+use crate::draw;
+use crate::layout::{self, BODY_FONT, HEADING_FONT};
+use crate::pin::{self, PinPad};
+use crate::store::{self, Sector};
+use crate::usb;
+
+const MAX_ACCOUNTS: usize = 16;
+
+// Height of the time-left bar along the bottom, a whole number of the panel's 8-row bytes.
+const BAR_HEIGHT: u32 = 8;
+// Seconds between bar updates.
+const BAR_STEP: u32 = 5;
+
+struct Account {
+    name: String<32>,
+    secret: Vec<u8, MAX_SECRET>,
+    algorithm: Algorithm,
+    digits: u8,
+    kind: Kind,
+}
+
+#[derive(Default)]
+pub struct Authenticator {
+    accounts: Vec<Account, MAX_ACCOUNTS>,
+    // `pin::hash` of the PIN, once one is set.
+    pin: Option<[u8; 32]>,
+    unlocked: bool,
+    pad: PinPad,
+    wrong: bool,
+    // Wrong PINs in a row.
+    failures: u8,
+    // Uptime in seconds before the next PIN can be tried.
+    wait_until: u64,
+    // A wait was showing when the screen was drawn.
+    waiting: bool,
+    selected: usize,
+    // The HOTP code showing after A.
+    revealed: Option<u32>,
+    // TOTP time step and bar on screen, redrawn when they change.
+    shown: Option<(u64, u32)>,
+    // Changed over serial, the screen needs redrawing.
+    stale: bool,
+}
+
+// Splits `len` bytes off the front of `data`.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    let bytes = data.get(..len)?;
+    *data = &data[len..];
+    Some(bytes)
+}
+
+// Record layout, integers little-endian:
+//
+//   has_pin: u8  pin_hash: [u8; 32]  accounts: u8
+//   account  kind: u8 (0 TOTP, 1 HOTP)  algorithm: u8 (0 SHA1, 1 SHA256, 2 SHA512)  digits: u8
+//            period or counter: u64  name_len: u8  name  secret_len: u8  secret
+//   failures: u8 (wrong PINs in a row, none if the record stops before it)
+//
+// Digits and periods are checked as `Uri::parse` does, the codes can't be worked out otherwise.
+fn parse(mut data: &[u8]) -> Option<Authenticator> {
+    let data = &mut data;
+    let mut authenticator = Authenticator::default();
+    let has_pin = take(data, 1)?[0] != 0;
+    let pin = take(data, 32)?;
+    if has_pin {
+        authenticator.pin = Some(pin.try_into().ok()?);
+    }
+
+    for _ in 0..take(data, 1)?[0] {
+        let header = take(data, 11)?;
+        let value = u64::from_le_bytes(header[3..11].try_into().ok()?);
+        let kind = match header[0] {
+            0 => Kind::Totp { period: u32::try_from(value).ok().filter(|period| (1..=MAX_PERIOD).contains(period))? },
+            _ => Kind::Hotp { counter: value },
+        };
+        let digits = Some(header[2]).filter(|digits| (6..=8).contains(digits))?;
+        let algorithm = [Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512].get(header[1] as usize).copied()?;
+        let name_len = take(data, 1)?[0] as usize;
+        let name = core::str::from_utf8(take(data, name_len)?).ok()?;
+        let secret_len = take(data, 1)?[0] as usize;
+        let secret = take(data, secret_len)?;
+
+        let account = Account {
+            name: name.parse().ok()?,
+            secret: Vec::from_slice(secret).ok()?,
+            algorithm,
+            digits,
+            kind,
+        };
+        authenticator.accounts.push(account).ok()?;
+    }
+
+    authenticator.failures = take(data, 1).map_or(0, |failures| failures[0]);
+    authenticator.wait_until = pin::wait(authenticator.failures);
+    Some(authenticator)
+}
+
+impl Authenticator {
+    /// The accounts and PIN saved in flash.
+    pub fn load() -> Self {
+        store::load(Sector::Otp).and_then(parse).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let mut data: Vec<u8, { store::MAX_RECORD }> = Vec::new();
+        let _ = data.push(self.pin.is_some() as u8);
+        let _ = data.extend_from_slice(&self.pin.unwrap_or([0; 32]));
+        let _ = data.push(self.accounts.len() as u8);
+        for account in &self.accounts {
+            let (kind, value) = match account.kind {
+                Kind::Totp { period } => (0, period as u64),
+                Kind::Hotp { counter } => (1, counter),
+            };
+            let algorithm = match account.algorithm {
+                Algorithm::Sha1 => 0,
+                Algorithm::Sha256 => 1,
+                Algorithm::Sha512 => 2,
+            };
+            let _ = data.extend_from_slice(&[kind, algorithm, account.digits]);
+            let _ = data.extend_from_slice(&value.to_le_bytes());
+            let _ = data.push(account.name.len() as u8);
+            let _ = data.extend_from_slice(account.name.as_bytes());
+            let _ = data.push(account.secret.len() as u8);
+            let _ = data.extend_from_slice(&account.secret);
+        }
+        let _ = data.push(self.failures);
+
+        if store::save(Sector::Otp, &data).is_err() {
+            usb::write(b"couldn't save to flash\r\n");
+        }
+    }
+
+    /// Locks the codes away again, for when the program is opened.
+    pub fn lock(&mut self) {
+        self.unlocked = false;
+        self.pad.clear();
+        self.wrong = false;
+        self.revealed = None;
+    }
+
+    // Checks `entered` against the PIN and counts it if it's wrong. `None` while a wait from
+    // earlier wrong PINs is still running.
+    fn check_pin(&mut self, entered: &str, now: u64) -> Option<bool> {
+        if now < self.wait_until {
+            return None;
+        }
+        let right = self.pin == Some(pin::hash(entered));
+        let failures = if right { 0 } else { self.failures.saturating_add(1) };
+        if failures != self.failures {
+            self.failures = failures;
+            self.wait_until = now + pin::wait(failures);
+            self.save();
+        }
+        Some(right)
+    }
+
+    fn add(&mut self, uri: &str) -> Result<(), &'static str> {
+        let uri = Uri::parse(uri).map_err(|_| "not a valid otpauth:// URI")?;
+        let mut name = [0; 64];
+        let name = uri.name(&mut name).and_then(|name| name.parse::<String<32>>().ok()).ok_or("name longer than 32 characters")?;
+        let mut secret = [0; MAX_SECRET];
+        let secret = uri.decode_secret(&mut secret).map_err(|_| "secret isn't valid base32")?;
+
+        let account = Account {
+            name,
+            secret: Vec::from_slice(secret).map_err(|_| "secret too long")?,
+            algorithm: uri.algorithm,
+            digits: uri.digits,
+            kind: uri.kind,
+        };
+        match self.accounts.iter().position(|existing| existing.name == account.name) {
+            Some(index) => self.accounts[index] = account,
+            None => self.accounts.push(account).map_err(|_| "no room for more accounts")?,
+        }
+        Ok(())
+    }
+
+    /// Handles `otp ...` typed on the serial console.
+    pub fn command<'a>(&mut self, mut args: impl Iterator<Item = &'a str>, now: u64) {
+        let result = match (args.next(), args.next(), args.next()) {
+            (Some("pin"), Some(new), None) if self.pin.is_none() && pin::valid(new) => {
+                self.pin = Some(pin::hash(new));
+                Ok("PIN set")
+            }
+            (Some("pin"), Some(old), Some(new)) if pin::valid(new) => match self.check_pin(old, now) {
+                Some(true) => {
+                    self.pin = Some(pin::hash(new));
+                    Ok("PIN changed")
+                }
+                Some(false) => {
+                    self.stale = true;
+                    Err("wrong PIN")
+                }
+                None => Err("too many wrong PINs, try again later"),
+            },
+            (Some("pin"), ..) => Err("usage: otp pin NEW, or otp pin OLD NEW to change it. PINs are 4 to 8 digits"),
+            (Some("add"), Some(uri), None) if self.pin.is_some() => self.add(uri).map(|_| "added"),
+            (Some("add"), Some(_), None) => Err("set a PIN first: otp pin 1234"),
+            (Some("list"), None, None) => {
+                for (index, account) in self.accounts.iter().enumerate() {
+                    let mut line: String<48> = String::new();
+                    let _ = write!(line, "{} {}\r\n", index + 1, account.name);
+                    usb::write(line.as_bytes());
+                }
+                return;
+            }
+            (Some("remove"), Some(number), None) => {
+                match number.parse::<usize>().ok().filter(|&number| (1..=self.accounts.len()).contains(&number)) {
+                    Some(number) => {
+                        self.accounts.remove(number - 1);
+                        Ok("removed")
+                    }
+                    None => Err("no account with that number, see otp list"),
+                }
+            }
+            (Some("clear"), None, None) => {
+                self.accounts.clear();
+                self.pin = None;
+                self.failures = 0;
+                self.wait_until = 0;
+                Ok("cleared")
+            }
+            _ => Err("usage: otp pin|add|list|remove|clear"),
+        };
+
+        match result {
+            Ok(message) => {
+                self.save();
+                self.selected = self.selected.min(self.accounts.len().saturating_sub(1));
+                self.lock();
+                self.stale = true;
+                usb::write(message.as_bytes());
+            }
+            Err(message) => usb::write(message.as_bytes()),
+        }
+        usb::write(b"\r\n");
+    }
+}
+
+// "123 456", "1234 5678".
+fn format_code(code: u32, digits: u8) -> String<12> {
+    let mut text: String<12> = String::new();
+    let _ = write!(text, "{:0width$}", code, width = digits as usize);
+    let split = digits as usize / 2;
+    let mut spaced = String::new();
+    let _ = spaced.push_str(&text[..split]);
+    let _ = spaced.push(' ');
+    let _ = spaced.push_str(&text[split..]);
+    spaced
+}
+
+// Time-left bar along the bottom, full when the code is new. Returns its area.
+fn draw_bar<D>(display: &mut D, remaining: u32, period: u32) -> Rectangle where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let size = display.bounding_box().size;
+    let area = Rectangle::new(Point::new(0, (size.height - BAR_HEIGHT) as i32), Size::new(size.width, BAR_HEIGHT));
+    let filled = Size::new(size.width * remaining / period, BAR_HEIGHT);
+
+    let _ = display.fill_solid(&area, BinaryColor::On);
+    let _ = area.into_styled(PrimitiveStyle::with_stroke(BinaryColor::Off, 1)).draw(display);
+    let _ = display.fill_solid(&Rectangle::new(area.top_left, filled), BinaryColor::Off);
+    area
+}
+
+fn draw_locked<D>(display: &mut D, authenticator: &mut Authenticator, now: u64) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let width = display.bounding_box().size.width;
+    let top = layout::header(display, &assets::LOCK, "Authenticator");
+
+    let mut message: String<64> = String::new();
+    authenticator.waiting = authenticator.pin.is_some() && now < authenticator.wait_until;
+    let _ = if authenticator.pin.is_none() {
+        write!(message, "No PIN set. Set one and add accounts over USB: otp pin 1234")
+    } else if authenticator.waiting {
+        let minutes = (authenticator.wait_until - now).div_ceil(60);
+        write!(message, "Too many wrong PINs. Try again in {} min", minutes)
+    } else if authenticator.wrong {
+        write!(message, "Wrong PIN")
+    } else {
+        write!(message, "Enter PIN")
+    };
+    draw::draw_textbox(display, &message, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, top, width, 0);
+    if authenticator.pin.is_none() || authenticator.waiting {
+        return;
+    }
+
+    let y = top + 2 * BODY_FONT.character_size.height as i32;
+    draw::draw_textbox(display, &authenticator.pad.label(), HEADING_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, y, width, 0);
+}
+
+// The selected account and its code. Returns the TOTP time step and bar shown, if any.
+fn draw_code<D>(display: &mut D, clock: &Clock, authenticator: &Authenticator) -> Option<(u64, u32)> where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let width = display.bounding_box().size.width;
+    let top = layout::header(display, &assets::LOCK, "Authenticator");
+
+    let Some(account) = authenticator.accounts.get(authenticator.selected) else {
+        let message = "No accounts. Add one over USB: otp add otpauth://...";
+        draw::draw_textbox(display, message, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, top, width, 0);
+        return None;
+    };
+
+    let mut status: String<12> = String::new();
+    let _ = write!(status, "[{}/{}]", authenticator.selected + 1, authenticator.accounts.len());
+    layout::status(display, &status);
+    draw::draw_textbox(display, &account.name, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, top, width, 0);
+    let code_y = top + BODY_FONT.character_size.height as i32 + 4;
+
+    let hidden = || {
+        let mut text: String<12> = String::new();
+        let _ = text.push_str(&"--------"[..account.digits as usize / 2]);
+        let _ = text.push(' ');
+        let _ = text.push_str(&"--------"[..account.digits as usize - account.digits as usize / 2]);
+        text
+    };
+
+    match account.kind {
+        Kind::Totp { period } => {
+            let Some(now) = clock.utc() else {
+                let _ = draw::draw_large_text(display, &hidden(), HEADING_FONT, 2, BinaryColor::Off, code_y);
+                let footer_y = code_y + HEADING_FONT.character_size.height as i32 * 2;
+                draw::draw_textbox(display, "Clock not set", BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, footer_y, width, 0);
+                return None;
+            };
+            let time = now.timestamp() as u64;
+            let code = otp::totp(account.algorithm, &account.secret, time, period, account.digits);
+            let _ = draw::draw_large_text(display, &format_code(code, account.digits), HEADING_FONT, 2, BinaryColor::Off, code_y);
+
+            let remaining = otp::remaining(time, period);
+            let bar = remaining.div_ceil(BAR_STEP) * BAR_STEP;
+            draw_bar(display, bar.min(period), period);
+            Some((time / period as u64, bar))
+        }
+        Kind::Hotp { counter } => {
+            let code = match authenticator.revealed {
+                Some(code) => format_code(code, account.digits),
+                None => hidden(),
+            };
+            let _ = draw::draw_large_text(display, &code, HEADING_FONT, 2, BinaryColor::Off, code_y);
+
+            let mut footer: String<32> = String::new();
+            let _ = write!(footer, "[A] next code, #{}", counter);
+            let footer_y = code_y + HEADING_FONT.character_size.height as i32 * 2;
+            draw::draw_textbox(display, &footer, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, footer_y, width, 0);
+            None
+        }
+    }
+}
+
+pub fn draw_authenticator_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &Clock,
+    authenticator: &mut Authenticator,
+    now: u64,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    authenticator.stale = false;
+    let _ = display.clear(BinaryColor::On);
+
+    authenticator.shown = if authenticator.unlocked {
+        draw_code(display, clock, authenticator)
+    } else {
+        draw_locked(display, authenticator, now);
+        None
+    };
+    let _ = display.update();
+}
+
+pub fn handle_authenticator_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &Clock,
+    authenticator: &mut Authenticator,
+    now: u64,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let pressed = btn_up_pressed || btn_down_pressed || btn_a_pressed;
+    let accounts = authenticator.accounts.len().max(1);
+
+    if !authenticator.unlocked {
+        if authenticator.pin.is_none() || now < authenticator.wait_until {
+            if !authenticator.stale {
+                return;
+            }
+        } else if let Some(entered) = authenticator.pad.handle(btn_up_pressed, btn_down_pressed, btn_a_pressed) {
+            authenticator.unlocked = authenticator.check_pin(&entered, now) == Some(true);
+            authenticator.wrong = !authenticator.unlocked;
+        } else if !pressed && !authenticator.stale && !authenticator.waiting {
+            // Still showing a wait that's over, otherwise nothing changed.
+            return;
+        }
+    } else if btn_up_pressed {
+        authenticator.selected = (authenticator.selected + accounts - 1) % accounts;
+        authenticator.revealed = None;
+    } else if btn_down_pressed {
+        authenticator.selected = (authenticator.selected + 1) % accounts;
+        authenticator.revealed = None;
+    } else if btn_a_pressed {
+        let Some(account) = authenticator.accounts.get_mut(authenticator.selected) else {
+            return;
+        };
+        let Kind::Hotp { counter } = &mut account.kind else {
+            return;
+        };
+        // Counted before it's shown, so a code is never reused even if power is lost.
+        authenticator.revealed = Some(otp::hotp(account.algorithm, &account.secret, *counter, account.digits));
+        *counter += 1;
+        authenticator.save();
+    } else if !authenticator.stale {
+        let period = match authenticator.accounts.get(authenticator.selected) {
+            Some(Account { kind: Kind::Totp { period }, .. }) => *period,
+            _ => return,
+        };
+        let Some(now) = clock.utc() else {
+            return;
+        };
+        let time = now.timestamp() as u64;
+        if let Some((step, bar)) = authenticator.shown.filter(|&(step, _)| step == time / period as u64) {
+            // Same code, only the bar moves.
+            let remaining = otp::remaining(time, period).div_ceil(BAR_STEP) * BAR_STEP;
+            if remaining != bar {
+                authenticator.shown = Some((step, remaining));
+                let area = draw_bar(display, remaining.min(period), period);
+                let region = UpdateRegion { x: 0, y: area.top_left.y as u32, width: area.size.width, height: area.size.height };
+                let _ = display.partial_update(region);
+            }
+            return;
+        }
+        // A new code, or the clock was just set.
+    }
+
+    draw_authenticator_screen(display, clock, authenticator, now);
+}
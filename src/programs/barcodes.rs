@@ -0,0 +1,105 @@
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Graphics
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+};
+use embedded_text::{
+    alignment::HorizontalAlignment,
+};
+use heapless::String;
+use core::fmt::Write as FmtWrite;
+use profont::*;
+use uc8151::{HEIGHT, WIDTH};
+
+use crate::barcode;
+use crate::assets;
+use crate::draw;
+use crate::layout;
+use crate::profile;
+
+pub fn draw_barcodes_screen<SPI, CS, DC, BUSY, RESET>(display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>, index: usize) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    // Draw Initial Screen
+    let top = layout::header(display, &assets::APP, "Barcodes");
+
+    // The profile's codes, in order. Up/Down cycles through them.
+    let Some(&(name, symbology, payload)) = profile::BARCODES.get(index) else {
+        draw::draw_textbox(display, "No codes in this profile.", PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, WIDTH, 0);
+        let _ = display.update();
+        return;
+    };
+
+    let mut label: String<32> = String::new();
+    let _ = write!(label, "{} [{}/{}]", name, index + 1, profile::BARCODES.len());
+
+    // A bar code wider than the screen can't be drawn one pixel per module. build.rs turns away
+    // codes that don't fit, this only keeps a bad one from drawing garbage.
+    let symbol = match barcode::encode(symbology, payload) {
+        Ok(symbol) if symbol.width() as u32 <= WIDTH => symbol,
+        _ => {
+            draw::draw_textbox(display, "Code too long to encode.", PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, (WIDTH), 0);
+            let _ = display.update();
+            return;
+        }
+    };
+
+    if symbol.height() == 1 {
+        // 1D: label under the header, bars across the bottom with the human readable text.
+        let scale = ((WIDTH - 8) / symbol.width() as u32).clamp(1, 3);
+        let x = WIDTH.saturating_sub(symbol.width() as u32 * scale) / 2;
+        draw::draw_textbox(display, &label, PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, (WIDTH), 0);
+        barcode::draw_symbol(display, &symbol, x as i32, top + 22, scale, 46);
+        draw::draw_textbox(display, payload, PROFONT_9_POINT, BinaryColor::Off, HorizontalAlignment::Center, 0, 110, (WIDTH), 0);
+    } else {
+        // 2D: symbol on the right, as large as fits, text on the left.
+        let scale = ((HEIGHT - 8) / symbol.width() as u32).clamp(1, 6);
+        let size = symbol.width() as u32 * scale;
+        let (Some(text_width), Some(y)) = (WIDTH.checked_sub(size + 8), HEIGHT.checked_sub(size)) else {
+            draw::draw_textbox(display, "Code too large to show.", PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, WIDTH, 0);
+            let _ = display.update();
+            return;
+        };
+        let x = text_width + 4;
+        draw::draw_textbox(display, &label, PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, text_width, 0);
+        draw::draw_textbox(display, payload, PROFONT_9_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top + 22, text_width, 0);
+        barcode::draw_symbol(display, &symbol, x as i32, (y / 2) as i32, scale, scale);
+    }
+
+    let _ = display.update();
+}
+
+pub fn handle_barcodes_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    index: &mut usize,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    if (!btn_up_pressed && !btn_down_pressed) || profile::BARCODES.is_empty() {
+        return;
+    }
+
+    if btn_up_pressed {
+        *index = (*index + profile::BARCODES.len() - 1) % profile::BARCODES.len();
+    }
+
+    if btn_down_pressed {
+        *index = (*index + 1) % profile::BARCODES.len();
+    }
+
+    let _ = display.clear(BinaryColor::On);
+    draw_barcodes_screen(display, *index);
+}
@@ -2,21 +2,12 @@ use cortex_m::delay::Delay;
 use embedded_hal::blocking::spi::Write;
 use embedded_hal::digital::v2::{InputPin, OutputPin};
 
-// Graphics
-use embedded_graphics::{
-    pixelcolor::BinaryColor,
-    prelude::*,
-};
 use embedded_hal::blocking::delay::DelayMs;
-use embedded_text::{
-    alignment::HorizontalAlignment,
-};
-use profont::*;
 use rp2040_hal::gpio::{Pin, PushPullOutput};
 use rp2040_hal::gpio::bank0::{Gpio25};
-use uc8151::{WIDTH};
 
-use crate::draw;
+use crate::assets;
+use crate::layout::{self, Layout};
 
 pub fn draw_blinky_screen<SPI, CS, DC, BUSY, RESET>(display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>) where
     SPI: Write<u8>,
@@ -26,9 +17,11 @@ pub fn draw_blinky_screen<SPI, CS, DC, BUSY, RESET>(display: &mut uc8151::Uc8151
     RESET: OutputPin,
 {
     // Draw Initial Screen
-    draw::draw_image(display, include_bytes!("../../assets/blink.bmp"), 0, 0);
-    draw::draw_textbox(display, "Blinky Test", PROFONT_24_POINT, BinaryColor::Off, HorizontalAlignment::Left, 42, 3, (WIDTH - 42), 0);
-    draw::draw_textbox(display, "OwO, led go blink.", PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, 38, (WIDTH), 0);
+    layout::draw(display, &Layout::App {
+        icon: &assets::BLINK,
+        title: "Blinky Test",
+        body: "OwO, led go blink.",
+    });
     let _ = display.update();
 }
 
@@ -3,31 +3,41 @@ use embedded_hal::digital::v2::{InputPin, OutputPin};
 
 // Graphics
 use embedded_graphics::{
-    pixelcolor::BinaryColor,
+    image::Image,
     prelude::*,
 };
-use embedded_text::{
-    alignment::HorizontalAlignment,
-};
-use profont::*;
-use uc8151::WIDTH;
 
-use crate::draw;
+use crate::assets;
+use crate::layout::{self, Layout};
+use crate::gray::{self, GrayFrame};
+use crate::screens;
 
-// TODO: Used for the first day of college (REMOVE AFTER FIRST WEEk)
-pub fn draw_ccnb_screen<SPI, CS, DC, BUSY, RESET>(display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>) where
+// Shown whenever the playlist or a schedule rule picks it, see `schedule` in the profile.
+pub fn draw_ccnb_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+) where
     SPI: Write<u8>,
     CS: OutputPin,
     DC: OutputPin,
     BUSY: InputPin,
     RESET: OutputPin,
 {
-    // Draw Initial Screen
-    draw::draw_image(display, include_bytes!("../../assets/anthony2.bmp"), 0, 0);
-    draw::draw_textbox(display, "Anthony", PROFONT_24_POINT, BinaryColor::Off, HorizontalAlignment::Left, 140, 0, (WIDTH - 130), 0);
-    draw::draw_textbox(display, "Programme: Cybersécurité\nBonne Rentrée!", PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 140, 32, (WIDTH - 130), 0);
-    draw::draw_image(display, include_bytes!("../../assets/lock.bmp"), 140, 100);
-    draw::draw_image(display, include_bytes!("../../assets/isc.bmp"), 174, 100);
-    draw::draw_image(display, include_bytes!("../../assets/dcf.bmp"), 235, 100);
-    let _ = display.update();
+    // A personalised badge flashed with tools/screenc replaces the built-in one.
+    if let Some(screen) = screens::stored().and_then(|screens| screens.get(1)) {
+        screens::show(display, delay, &screen);
+        return;
+    }
+
+    // Draw Initial Screen, laid out 1-bit, then the photo redrawn over it in grayscale
+    let mut frame = GrayFrame::new();
+    layout::draw(&mut frame.binary(), &Layout::Card {
+        photo: &assets::ANTHONY2,
+        name: "Anthony",
+        details: "Programme: Cybersécurité\nBonne Rentrée!",
+        badges: &[&assets::LOCK, &assets::ISC, &assets::DCF],
+    });
+    let _ = Image::new(&assets::ANTHONY2_GRAY, Point::zero()).draw(&mut frame);
+
+    gray::show(display, delay, &frame);
 }
\ No newline at end of file
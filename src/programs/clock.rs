@@ -0,0 +1,178 @@
+// Clock: the time in large digits over the date, and a month calendar.
+//
+// A switches between the two. On the calendar Up/Down page back and forward through the months
+// and today is marked. The panel is left alone until the minute changes, then only the digits
+// are refreshed with a partial update; the whole screen is redrawn on the hour and at midnight,
+// which also clears the ghosting partial updates leave behind.
+
+use core::fmt::Write as FmtWrite;
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::String;
+use uc8151::UpdateRegion;
+
+use crate::assets;
+use crate::calendar::{self, Date, Moment};
+use crate::clock::Clock;
+use crate::draw;
+use crate::layout::{self, BODY_FONT, STATUS_FONT, TITLE_FONT};
+
+// Calendar month shown while the clock isn't set.
+const UNSET: Date = Date { year: 2024, month: 1, day: 1 };
+
+// Calendar grid, one row of weekday names and up to six weeks.
+const ROW: i32 = 12;
+const COLUMN: i32 = 42;
+
+#[derive(Default)]
+pub struct ClockFace {
+    calendar: bool,
+    // Months before (negative) or after the current one on the calendar.
+    months: i32,
+    // Time on screen, to the minute.
+    shown: Option<Moment>,
+}
+
+// Year and month `months` away from `date`'s.
+fn month_from(date: Date, months: i32) -> (u16, u8) {
+    let index = (date.year as i32 * 12 + date.month as i32 - 1 + months).clamp(calendar::FIRST_YEAR as i32 * 12, calendar::LAST_YEAR as i32 * 12 + 11);
+    ((index / 12) as u16, (index % 12 + 1) as u8)
+}
+
+// Header, large time and the date under it. Returns the area of the time, for partial updates.
+fn draw_face<D>(display: &mut D, now: Option<&Moment>) -> Rectangle where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let width = display.bounding_box().size.width;
+    let top = layout::header(display, &assets::APP, "Clock");
+
+    let mut time: String<8> = String::new();
+    let mut date: String<32> = String::new();
+    match now {
+        Some(now) => {
+            let _ = write!(time, "{:02}:{:02}", now.hour, now.minute);
+            let day = calendar::DAY_NAMES[now.date.weekday() as usize];
+            let month = calendar::MONTHS[now.date.month as usize - 1];
+            let _ = write!(date, "{} {} {} {}", day, now.date.day, month, now.date.year);
+        }
+        None => {
+            let _ = time.push_str("--:--");
+            let _ = date.push_str("Not set, see Settings");
+        }
+    }
+
+    // Clear the digits' band first, partial updates draw over the last minute.
+    let band = Rectangle::new(Point::new(0, top), Size::new(width, TITLE_FONT.character_size.height * 2));
+    let _ = display.fill_solid(&band, BinaryColor::On);
+    let digits = draw::draw_large_text(display, &time, TITLE_FONT, 2, BinaryColor::Off, top);
+
+    let date_y = digits.top_left.y + digits.size.height as i32;
+    draw::draw_textbox(display, &date, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, date_y, width, 0);
+
+    band
+}
+
+// Month grid with weeks starting on Monday, `today` marked if it's in the month.
+fn draw_calendar<D>(display: &mut D, year: u16, month: u8, today: Option<Date>) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut title: String<16> = String::new();
+    let _ = write!(title, "{} {}", calendar::MONTHS[month as usize - 1], year);
+    let top = layout::header(display, &assets::APP, &title);
+    let left = (display.bounding_box().size.width as i32 - COLUMN * 7) / 2;
+
+    for (column, name) in calendar::DAY_NAMES.iter().enumerate() {
+        let x = left + column as i32 * COLUMN;
+        draw::draw_textbox(display, name, STATUS_FONT, BinaryColor::Off, HorizontalAlignment::Center, x, top, COLUMN as u32, 0);
+    }
+
+    let first = Date { year, month, day: 1 };
+    for day in 1..=calendar::days_in_month(year, month) {
+        let cell = (first.weekday() + day - 1) as i32;
+        let x = left + cell % 7 * COLUMN;
+        let y = top + ROW * (1 + cell / 7);
+
+        let mut number: String<4> = String::new();
+        let _ = write!(number, "{}", day);
+        let color = if today == Some(Date { day, ..first }) {
+            let _ = display.fill_solid(&Rectangle::new(Point::new(x + 8, y), Size::new(COLUMN as u32 - 16, ROW as u32)), BinaryColor::Off);
+            BinaryColor::On
+        } else {
+            BinaryColor::Off
+        };
+        draw::draw_textbox(display, &number, STATUS_FONT, color, HorizontalAlignment::Center, x, y, COLUMN as u32, 0);
+    }
+}
+
+pub fn draw_clock_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &Clock,
+    face: &mut ClockFace,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let now = clock.now();
+    face.shown = now.map(|now| Moment { second: 0, ..now });
+
+    let _ = display.clear(BinaryColor::On);
+    if face.calendar {
+        let (year, month) = month_from(now.map_or(UNSET, |now| now.date), face.months);
+        draw_calendar(display, year, month, now.map(|now| now.date));
+        layout::status(display, "[A] clock");
+    } else {
+        draw_face(display, now.as_ref());
+    }
+    let _ = display.update();
+}
+
+pub fn handle_clock_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &Clock,
+    face: &mut ClockFace,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let now = clock.now().map(|now| Moment { second: 0, ..now });
+
+    if btn_a_pressed {
+        face.calendar = !face.calendar;
+        face.months = 0;
+    } else if face.calendar && (btn_up_pressed || btn_down_pressed) {
+        face.months += if btn_up_pressed { -1 } else { 1 };
+    } else if face.calendar {
+        // Only today's mark moves.
+        if now.map(|now| now.date) == face.shown.map(|shown| shown.date) {
+            return;
+        }
+    } else {
+        match (now, face.shown) {
+            (now, shown) if now == shown => return,
+            (Some(now), Some(shown)) if now.date == shown.date && now.hour == shown.hour => {
+                face.shown = Some(now);
+                let band = draw_face(display, Some(&now));
+                // The panel updates in whole bytes of eight rows.
+                let y = band.top_left.y as u32 / 8 * 8;
+                let height = (band.top_left.y as u32 + band.size.height).div_ceil(8) * 8 - y;
+                let _ = display.partial_update(UpdateRegion { x: 0, y, width: band.size.width, height });
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    draw_clock_screen(display, clock, face);
+}
@@ -0,0 +1,120 @@
+// Countdown: time left until the profile's events, Up/Down to pick one.
+//
+// It opens on the next event that isn't over. More than a day out it counts days and hours, then
+// hours and minutes, and the screen is only redrawn when that text changes, so the panel refreshes
+// hourly until the last day. Events say "Happening now" until they end.
+
+use core::fmt::Write as FmtWrite;
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::String;
+
+use crate::assets;
+use crate::calendar::Moment;
+use crate::clock::{self, Clock};
+use crate::draw;
+use crate::event::{Countdown, Event};
+use crate::layout::{self, BODY_FONT, HEADING_FONT, TITLE_FONT};
+use crate::profile;
+
+#[derive(Default)]
+pub struct Countdowns {
+    selected: Option<usize>,
+    // Countdown on screen, redrawn when it reads differently.
+    shown: String<24>,
+}
+
+fn remaining(event: &Event, now: Option<&Moment>) -> String<24> {
+    let mut text = String::new();
+    let _ = match now.map(|now| event.countdown(now)) {
+        None => write!(text, "Clock not set"),
+        Some(Countdown::Now) => write!(text, "Happening now"),
+        Some(Countdown::Over) => write!(text, "Over"),
+        Some(Countdown::Until { days: 0, hours, minutes }) => write!(text, "{}h {:02}m", hours, minutes),
+        Some(Countdown::Until { days, hours, .. }) => write!(text, "{}d {}h", days, hours),
+    };
+    text
+}
+
+pub fn draw_countdown_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &Clock,
+    countdowns: &mut Countdowns,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let now = clock.now();
+    let events = profile::EVENTS;
+    let selected = *countdowns.selected.get_or_insert_with(|| {
+        // The first event that isn't over yet.
+        now.and_then(|now| events.iter().position(|line| Event::parse(line).is_ok_and(|event| now < event.end)))
+            .unwrap_or(0)
+    });
+
+    let _ = display.clear(BinaryColor::On);
+    let width = display.bounding_box().size.width;
+    let top = layout::header(display, &assets::APP, "Countdown");
+
+    match events.get(selected).and_then(|line| Event::parse(line).ok()) {
+        Some(event) => {
+            countdowns.shown = remaining(&event, now.as_ref());
+            let mut page: String<16> = String::new();
+            let _ = write!(page, "[{}/{}]", selected + 1, events.len());
+            layout::status(display, &page);
+
+            let mut when: String<32> = String::new();
+            let _ = match now.map(|now| event.countdown(&now)) {
+                Some(Countdown::Now) => write!(when, "until {}", clock::status(Some(&event.end))),
+                _ => write!(when, "{}", clock::status(Some(&event.start))),
+            };
+
+            let time_y = top + HEADING_FONT.character_size.height as i32;
+            let when_y = time_y + TITLE_FONT.character_size.height as i32 + 4;
+            draw::draw_textbox(display, event.name, HEADING_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, top, width, 0);
+            draw::draw_textbox(display, &countdowns.shown, TITLE_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, time_y, width, 0);
+            draw::draw_textbox(display, &when, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, when_y, width, 0);
+        }
+        None => {
+            countdowns.shown.clear();
+            draw::draw_textbox(display, "No events, add some to the profile's `events`.", BODY_FONT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, width, 0);
+        }
+    }
+    let _ = display.update();
+}
+
+pub fn handle_countdown_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &Clock,
+    countdowns: &mut Countdowns,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let count = profile::EVENTS.len();
+    let selected = countdowns.selected.unwrap_or(0);
+
+    if count > 1 && btn_up_pressed {
+        countdowns.selected = Some((selected + count - 1) % count);
+    } else if count > 1 && btn_down_pressed {
+        countdowns.selected = Some((selected + 1) % count);
+    } else {
+        match profile::EVENTS.get(selected).and_then(|line| Event::parse(line).ok()) {
+            Some(event) if remaining(&event, clock.now().as_ref()) != countdowns.shown => {}
+            _ => return,
+        }
+    }
+
+    draw_countdown_screen(display, clock, countdowns);
+}
@@ -0,0 +1,208 @@
+// Cryptography: hashes, HMAC and CRC32 of text typed on the badge or sent over serial.
+//
+// Up/Down pick a row and A changes it: the mode cycles through the algorithms, text and key are
+// typed with the button keyboard (see `entry`), and "Compute" shows the result in hex and as a
+// QR code. Any button goes back from the result. The key only matters in the HMAC modes.
+//
+//   hash sha256 some text             sha256, sha1, md5 or crc32 of everything after the mode
+//   hash hmac-sha256 key some text    HMAC, the key is one word
+//
+// Serial results are printed and shown on the badge as well.
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::hash::{self, Algorithm, Output};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::String;
+use profont::*;
+use uc8151::{HEIGHT, WIDTH};
+
+use crate::assets;
+use crate::barcode::{self, Symbology};
+use crate::draw;
+use crate::entry::Entry;
+use crate::layout::{self, Layout};
+use crate::usb;
+
+const MODE: usize = 0;
+const TEXT: usize = 1;
+const KEY: usize = 2;
+const COMPUTE: usize = 3;
+const ROWS: usize = 4;
+
+// Plain hashes first, then the HMACs.
+const MODES: [(Algorithm, bool); 7] = [
+    (Algorithm::Sha256, false),
+    (Algorithm::Sha1, false),
+    (Algorithm::Md5, false),
+    (Algorithm::Crc32, false),
+    (Algorithm::Sha256, true),
+    (Algorithm::Sha1, true),
+    (Algorithm::Md5, true),
+];
+
+#[derive(Default)]
+pub struct Crypto {
+    mode: usize,
+    text: Entry<96>,
+    key: Entry<32>,
+    row: usize,
+    typing: bool,
+    result: Option<Output>,
+    // Changed over serial, the screen needs redrawing.
+    stale: bool,
+}
+
+fn mode_name(mode: usize) -> String<16> {
+    let (algorithm, hmac) = MODES[mode];
+    let mut name = String::new();
+    let _ = write!(name, "{}{}", if hmac { "hmac-" } else { "" }, algorithm.name());
+    name
+}
+
+fn compute(mode: usize, key: &str, text: &str) -> Option<Output> {
+    match MODES[mode] {
+        (algorithm, true) => hash::hmac(algorithm, key.as_bytes(), text.as_bytes()),
+        (algorithm, false) => Some(hash::hash(algorithm, text.as_bytes())),
+    }
+}
+
+impl Crypto {
+    /// Handles `hash ...` typed on the serial console, `line` is the whole line.
+    pub fn command(&mut self, line: &str) {
+        let mut parts = line.trim_start().splitn(3, ' ');
+        let _ = parts.next();
+        let mode = parts.next().and_then(|name| (0..MODES.len()).find(|&mode| mode_name(mode) == name));
+        let Some(mode) = mode else {
+            usb::write(b"usage: hash sha256|sha1|md5|crc32 TEXT, or hash hmac-sha256|hmac-sha1|hmac-md5 KEY TEXT\r\n");
+            return;
+        };
+
+        let rest = parts.next().unwrap_or("");
+        let (key, text) = if MODES[mode].1 { rest.split_once(' ').unwrap_or((rest, "")) } else { ("", rest) };
+        let (Ok(key), Ok(text)) = (key.parse(), text.parse()) else {
+            usb::write(b"text is limited to 96 characters and keys to 32\r\n");
+            return;
+        };
+
+        self.mode = mode;
+        self.key.text = key;
+        self.text.text = text;
+        self.typing = false;
+        self.result = compute(mode, &self.key.text, &self.text.text);
+        self.stale = true;
+
+        let mut line: String<80> = String::new();
+        if let Some(result) = &self.result {
+            let _ = write!(line, "{}\r\n", result);
+        }
+        usb::write(line.as_bytes());
+    }
+}
+
+fn draw_result<D>(display: &mut D, name: &str, result: &Output) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut hex: String<80> = String::new();
+    let _ = write!(hex, "{}", result);
+
+    // QR on the right, as large as fits, the hex wrapped beside it.
+    let top = layout::header(display, &assets::APP, name);
+    let left = match barcode::encode(Symbology::Qr, &hex) {
+        Ok(symbol) => {
+            let scale = ((HEIGHT - 8) / symbol.width() as u32).clamp(1, 6);
+            let size = symbol.width() as u32 * scale;
+            let x = WIDTH - size - 4;
+            barcode::draw_symbol(display, &symbol, x as i32, ((HEIGHT - size) / 2) as i32, scale, scale);
+            x - 4
+        }
+        Err(_) => WIDTH,
+    };
+    draw::draw_textbox(display, &hex, PROFONT_12_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, left, 0);
+}
+
+pub fn draw_crypto_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    crypto: &mut Crypto,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    crypto.stale = false;
+    let _ = display.clear(BinaryColor::On);
+
+    match &crypto.result {
+        Some(result) => draw_result(display, &mode_name(crypto.mode), result),
+        None => {
+            let typing = |row| crypto.typing && crypto.row == row;
+
+            let mut mode: String<32> = String::from("Mode ");
+            let _ = mode.push_str(&mode_name(crypto.mode));
+            let mut text: String<48> = String::from("Text ");
+            let _ = text.push_str(&crypto.text.label(typing(TEXT)));
+            let mut key: String<48> = String::from("Key ");
+            if MODES[crypto.mode].1 {
+                let _ = key.push_str(&crypto.key.label(typing(KEY)));
+            } else {
+                let _ = key.push('-');
+            }
+
+            layout::draw(display, &Layout::List {
+                icon: &assets::LOCK,
+                title: "Cryptography",
+                items: &[mode.as_str(), text.as_str(), key.as_str(), "Compute"],
+                selected: crypto.row,
+                footer: if crypto.typing { "[A] type" } else { "[A] change" },
+            });
+        }
+    }
+    let _ = display.update();
+}
+
+pub fn handle_crypto_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    crypto: &mut Crypto,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let pressed = btn_up_pressed || btn_down_pressed || btn_a_pressed;
+
+    if crypto.result.is_some() && pressed {
+        crypto.result = None;
+    } else if crypto.typing && pressed {
+        let entry_done = match crypto.row {
+            TEXT => crypto.text.handle(btn_up_pressed, btn_down_pressed, btn_a_pressed),
+            _ => crypto.key.handle(btn_up_pressed, btn_down_pressed, btn_a_pressed),
+        };
+        crypto.typing = !entry_done;
+    } else if btn_up_pressed {
+        crypto.row = (crypto.row + ROWS - 1) % ROWS;
+    } else if btn_down_pressed {
+        crypto.row = (crypto.row + 1) % ROWS;
+    } else if btn_a_pressed {
+        match crypto.row {
+            MODE => crypto.mode = (crypto.mode + 1) % MODES.len(),
+            TEXT => crypto.typing = true,
+            KEY => crypto.typing = MODES[crypto.mode].1,
+            COMPUTE => crypto.result = compute(crypto.mode, &crypto.key.text, &crypto.text.text),
+            _ => {}
+        }
+    } else if !crypto.stale {
+        return;
+    }
+
+    draw_crypto_screen(display, crypto);
+}
@@ -0,0 +1,146 @@
+// Identity: the badge's own Ed25519 keypair, to prove at an event which badge is yours.
+//
+// The key is made on first boot from the hardware random number generator (see `entropy`) and
+// kept in flash (see `store`), so it stays the same until the STORE region is erased. The screen shows the public
+// key's fingerprint and a QR code of the key in hex. Over serial:
+//
+//   identity                          prints the public key and fingerprint
+//   sign some message                 signs everything after `sign `
+//   sign-hex 5f3a...                  signs raw bytes, e.g. a challenge from a verifier
+//
+// Signatures are printed in hex and cover `badge_crypto::sign::CONTEXT` followed by the message,
+// tools/badgeverify checks them.
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::cipher::Transform;
+use badge_crypto::sign::{Fingerprint, Identity, MAX_MESSAGE, PUBLIC_KEY_LEN};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::String;
+use profont::PROFONT_12_POINT;
+use rand_core::RngCore;
+use uc8151::{HEIGHT, WIDTH};
+
+use crate::assets;
+use crate::barcode::{self, Symbology};
+use crate::draw;
+use crate::entropy;
+use crate::layout::{self, STATUS_FONT};
+use crate::store::{self, Sector};
+use crate::usb;
+
+pub struct DeviceKey {
+    identity: Identity,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+fn hex<const N: usize>(bytes: &[u8]) -> String<N> {
+    let mut text = String::new();
+    for byte in bytes {
+        let _ = write!(text, "{:02x}", byte);
+    }
+    text
+}
+
+impl DeviceKey {
+    /// The key saved in flash, or a new one, saved for next time. `None` if there's no saved key
+    /// and no random number generator to make one.
+    pub fn load_or_create(rng: Option<&mut entropy::Rng>) -> Option<Self> {
+        let seed = match store::load(Sector::Identity).and_then(|data| <[u8; 32]>::try_from(data).ok()) {
+            Some(seed) => seed,
+            None => {
+                let mut seed = [0; 32];
+                rng?.fill_bytes(&mut seed);
+                // Still usable for this session if it couldn't be saved.
+                let _ = store::save(Sector::Identity, &seed);
+                seed
+            }
+        };
+        let identity = Identity::from_seed(&seed);
+        Some(DeviceKey { public_key: identity.public_key(), identity })
+    }
+
+    fn sign(&self, message: &[u8]) {
+        match self.identity.sign(message) {
+            Ok(signature) => {
+                usb::write(hex::<128>(&signature).as_bytes());
+                usb::write(b"\r\n");
+            }
+            Err(e) => {
+                let mut line: String<48> = String::new();
+                let _ = write!(line, "{}\r\n", e);
+                usb::write(line.as_bytes());
+            }
+        }
+    }
+
+    /// Handles `identity`, `sign ...` and `sign-hex ...` typed on the serial console, `line` is
+    /// the whole line.
+    pub fn command(&self, line: &str) {
+        let line = line.trim_start();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "identity" => {
+                let mut text: String<160> = String::new();
+                let _ = write!(text, "public key {}\r\nfingerprint {}\r\n", hex::<64>(&self.public_key), Fingerprint::of(&self.public_key));
+                usb::write(text.as_bytes());
+            }
+            "sign" => self.sign(rest.as_bytes()),
+            _ => {
+                let mut bytes = [0; MAX_MESSAGE];
+                match Transform::FromHex.apply(rest.trim().as_bytes(), &mut bytes) {
+                    Ok(len) => self.sign(&bytes[..len]),
+                    Err(_) => usb::write(b"usage: sign-hex HEX\r\n"),
+                }
+            }
+        }
+    }
+}
+
+pub fn draw_identity_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    key: Option<&DeviceKey>,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    // QR of the key on the right, the fingerprint beside it, four groups to a line.
+    let top = layout::header(display, &assets::LOCK, "Identity");
+    let Some(key) = key else {
+        let text = "No key yet: the hardware random number generator failed its health tests";
+        draw::draw_textbox(display, text, STATUS_FONT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, WIDTH, 0);
+        let _ = display.update();
+        return;
+    };
+    let public_key = hex::<64>(&key.public_key);
+    let left = match barcode::encode(Symbology::Qr, &public_key) {
+        Ok(symbol) => {
+            let scale = ((HEIGHT - 8) / symbol.width() as u32).clamp(1, 6);
+            let size = symbol.width() as u32 * scale;
+            let x = WIDTH - size - 4;
+            barcode::draw_symbol(display, &symbol, x as i32, ((HEIGHT - size) / 2) as i32, scale, scale);
+            x - 4
+        }
+        Err(_) => WIDTH,
+    };
+
+    let mut fingerprint: String<48> = String::new();
+    let _ = write!(fingerprint, "{}", Fingerprint::of(&key.public_key));
+    let (first, second) = fingerprint.split_at(fingerprint.len() / 2);
+    let mut lines: String<48> = String::new();
+    let _ = write!(lines, "{}\n{}", first.trim(), second.trim());
+
+    let line_height = PROFONT_12_POINT.character_size.height as i32;
+    draw::draw_textbox(display, "Ed25519 fingerprint", STATUS_FONT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, left, 0);
+    let y = top + STATUS_FONT.character_size.height as i32 + 4;
+    draw::draw_textbox(display, &lines, PROFONT_12_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, y, left, 0);
+    let y = y + line_height * 2 + 4;
+    draw::draw_textbox(display, "Verify signatures with tools/badgeverify", STATUS_FONT, BinaryColor::Off, HorizontalAlignment::Left, 0, y, left, 0);
+    let _ = display.update();
+}
@@ -0,0 +1,138 @@
+// Keyboard Macros: the badge as a USB keyboard, typing the profile's `macros` into the computer
+// it's plugged into. Only in builds with the `keyboard-macros` feature.
+//
+// The scripts are in `duckyscript`'s language, checked by build.rs and typed with the profile's
+// `keyboard` layout. Nothing is typed until A arms it, then A, Up and Down type the first, second
+// and third macro. B disarms it by leaving the program. The buttons are only read once a loop, so
+// after arming nothing is typed until every button has been let go: A held a little too long
+// doesn't type the first macro.
+
+use core::fmt::Write as FmtWrite;
+
+use duckyscript::{Action, Layout, Script};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use heapless::String;
+
+use crate::assets;
+use crate::layout::{self, Layout as Screen};
+use crate::profile;
+use crate::usb;
+
+// Which button types which macro, in the profile's order.
+const BUTTONS: [&str; 3] = ["A", "Up", "Down"];
+
+#[derive(Default)]
+pub struct Macros {
+    armed: bool,
+    // Every button has been let go since arming or typing the last macro.
+    released: bool,
+    // The last macro couldn't be typed.
+    failed: bool,
+}
+
+impl Macros {
+    pub fn disarm(&mut self) {
+        self.armed = false;
+        self.released = false;
+        self.failed = false;
+    }
+}
+
+// Types `script`, returning false if the host stopped taking keys.
+fn type_script(delay: &mut cortex_m::delay::Delay, script: &str) -> bool {
+    let layout = Layout::from_name(profile::KEYBOARD).unwrap_or_default();
+    for action in Script::new(script, layout) {
+        match action {
+            Ok(Action::Report(report)) => {
+                if !usb::send_key(report.modifiers, report.key) {
+                    // Don't leave a key held down.
+                    let _ = usb::send_key(0, 0);
+                    return false;
+                }
+            }
+            Ok(Action::Delay(ms)) => delay.delay_ms(ms),
+            // build.rs checked the scripts, so there are none of these.
+            Err(_) => return false,
+        }
+    }
+    true
+}
+
+pub fn draw_macros_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    macros: &Macros,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let mut body: String<160> = String::new();
+    if profile::MACROS.is_empty() {
+        let _ = body.push_str("No macros. Add scripts to `macros` in the profile.");
+    } else {
+        for (button, (name, _)) in BUTTONS.iter().zip(profile::MACROS) {
+            let _ = writeln!(body, "{}: {}", button, name);
+        }
+        let _ = body.push_str(match (macros.armed, macros.failed) {
+            (_, true) => "Not typed, is USB plugged in?",
+            (true, false) => "[B] disarm",
+            (false, false) => "[A] arm to type",
+        });
+    }
+
+    let _ = display.clear(BinaryColor::On);
+    layout::draw(display, &Screen::App {
+        icon: &assets::APP,
+        title: if macros.armed { "Armed" } else { "Macros" },
+        body: &body,
+    });
+    let _ = display.update();
+}
+
+pub fn handle_macros_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+    macros: &mut Macros,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    if !macros.armed {
+        if btn_a_pressed && !profile::MACROS.is_empty() {
+            macros.armed = true;
+            draw_macros_screen(display, macros);
+        }
+        return;
+    }
+    if !macros.released {
+        macros.released = !(btn_up_pressed || btn_down_pressed || btn_a_pressed);
+        return;
+    }
+
+    let pressed = [btn_a_pressed, btn_up_pressed, btn_down_pressed];
+    let Some(&(name, script)) = pressed.iter().zip(profile::MACROS).find_map(|(&pressed, script)| pressed.then_some(script)) else {
+        return;
+    };
+
+    let _ = display.clear(BinaryColor::On);
+    layout::draw(display, &Screen::App {
+        icon: &assets::APP,
+        title: "Typing",
+        body: name,
+    });
+    let _ = display.update();
+    macros.failed = !type_script(delay, script);
+    // A button still held once it's typed mustn't type it again.
+    macros.released = false;
+    draw_macros_screen(display, macros);
+}
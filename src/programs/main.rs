@@ -3,29 +3,51 @@ use embedded_hal::digital::v2::{InputPin, OutputPin};
 
 // Graphics
 use embedded_graphics::{
-    pixelcolor::BinaryColor,
+    image::Image,
     prelude::*,
 };
-use embedded_text::{
-    alignment::HorizontalAlignment,
-};
-use profont::*;
-use uc8151::WIDTH;
 
-use crate::draw;
+use crate::layout::{self, Layout};
+use crate::gray::{self, GrayFrame};
+use crate::profile;
+use crate::screens;
 
-pub fn draw_main_screen<SPI, CS, DC, BUSY, RESET>(display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>) where
+pub fn draw_main_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+    hidden: bool,
+) where
     SPI: Write<u8>,
     CS: OutputPin,
     DC: OutputPin,
     BUSY: InputPin,
     RESET: OutputPin,
 {
-    // Draw Initial Screen
-    draw::draw_image(display, include_bytes!("../../assets/lynix.bmp"), 0, 0);
-    draw::draw_textbox(display, "Lynix", PROFONT_24_POINT, BinaryColor::Off, HorizontalAlignment::Left, 140, 0, (WIDTH - 130), 0);
-    draw::draw_textbox(display, "Cybersecurity Student\nCanadian\n[lynix.ca]", PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 140, 32, (WIDTH - 130), 0);
+    // A personalised badge flashed with tools/screenc replaces the built-in one, except in
+    // privacy mode where there's no telling what it shows.
+    if let Some(screen) = screens::stored().and_then(|screens| screens.get(0)).filter(|_| !hidden) {
+        screens::show(display, delay, &screen);
+        return;
+    }
+
+    let card = Layout::Card {
+        photo: profile::PHOTO,
+        name: profile::NAME,
+        details: if hidden { profile::PRONOUNS } else { profile::DETAILS },
+        badges: profile::BADGES,
+    };
+
+    // Draw Initial Screen, grayscale when the profile photo has a gray version
+    let Some(photo) = profile::PHOTO_GRAY else {
+        layout::draw(display, &card);
+        let _ = display.update();
+        return;
+    };
 
+    // Laid out 1-bit, then the photo redrawn over it in grayscale
+    let mut frame = GrayFrame::new();
+    layout::draw(&mut frame.binary(), &card);
+    let _ = Image::new(photo, Point::zero()).draw(&mut frame);
 
-    let _ = display.update();
+    gray::show(display, delay, &frame);
 }
\ No newline at end of file
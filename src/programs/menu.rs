@@ -2,30 +2,26 @@
 use embedded_graphics::{
     pixelcolor::BinaryColor,
     prelude::*,
-    primitives::{Rectangle},
 };
-use embedded_graphics::primitives::PrimitiveStyleBuilder;
 use embedded_hal::blocking::spi::Write as SpiWrite;
 use core::fmt::Write as FmtWrite;
-use embedded_text::{
-    alignment::HorizontalAlignment,
-};
 
-use profont::*;
-use uc8151::{HEIGHT, Uc8151, WIDTH};
+use uc8151::Uc8151;
 
 
 // GPIO traits
 use embedded_hal::digital::v2::OutputPin;
 use embedded_hal::digital::v2::InputPin;
 use heapless::String;
-use crate::{draw, ProgramState};
+use crate::{assets, ProgramState};
+use crate::layout::{self, Layout};
 
 pub fn draw_menu<SPI, CS, DC, BUSY, RESET>(
     display: &mut Uc8151<SPI, CS, DC, BUSY, RESET>,
-    items: [&str; 8],
+    items: &[&str],
     selected_item: usize,
-    page: usize
+    page: usize,
+    status: &str,
 ) where
     SPI: SpiWrite<u8>,
     CS: OutputPin,
@@ -33,54 +29,30 @@ pub fn draw_menu<SPI, CS, DC, BUSY, RESET>(
     BUSY: InputPin,
     RESET: OutputPin,
 {
-    draw::draw_image(display, include_bytes!("../../assets/app.bmp"), 0, 0);
-    draw::draw_textbox(display, "Programs", PROFONT_24_POINT, BinaryColor::Off, HorizontalAlignment::Left, 42, 3, (WIDTH - 42), 0);
-
     let items_per_page = 4;
     let start_index = page * items_per_page;
+    let end_index = (start_index + items_per_page).min(items.len());
 
     let mut page_text: String<32> = String::from("[");
-
-    write!(page_text, "{}/{}]", page + 1, (items.len() / items_per_page) + 1).unwrap();
-    // Now draw the text with the formatted page number
-    draw::draw_text(display, &page_text, BinaryColor::Off, (WIDTH - 50) as i32, (HEIGHT - 5) as i32);
-
-    for (index, item) in items.iter().enumerate().skip(start_index).take(items_per_page) {
-        let y_position = 40 + (14 + (index as i32 - start_index as i32) * 20); // Adjust positioning as needed.
-
-        // Highlight the selected item.
-        if index == selected_item {
-            let style = PrimitiveStyleBuilder::new()
-                .stroke_color(BinaryColor::Off)
-                .stroke_width(3)
-                .fill_color(BinaryColor::Off)
-                .build();
-
-            Rectangle::new(Point::new(10, y_position - 9), Size::new(10, 10))
-                .into_styled(style)
-                .draw(display).unwrap();
-        } else {
-            let style = PrimitiveStyleBuilder::new()
-                .stroke_color(BinaryColor::Off)
-                .stroke_width(3)
-                .fill_color(BinaryColor::On)
-                .build();
-
-            Rectangle::new(Point::new(10, y_position - 9), Size::new(10, 10))
-                .into_styled(style)
-                .draw(display).unwrap();
-        }
-
-        draw::draw_text(display, item, BinaryColor::Off, 27, y_position);
-    }
+    write!(page_text, "{}/{}]", page + 1, items.len().div_ceil(items_per_page)).unwrap();
+
+    layout::draw(display, &Layout::List {
+        icon: &assets::APP,
+        title: "Programs",
+        items: &items[start_index..end_index],
+        selected: selected_item.wrapping_sub(start_index),
+        footer: &page_text,
+    });
+    layout::status(display, status);
 }
 
 pub fn handle_menu_program<SPI, CS, DC, BUSY, RESET>(
     display: &mut Uc8151<SPI, CS, DC, BUSY, RESET>,
-    items: [&str; 8],
+    items: &[&str],
     current_item: &mut usize,
     btn_up_pressed: bool,
     btn_down_pressed: bool,
+    status: &str,
 ) where
     SPI: SpiWrite<u8>,
     CS: OutputPin,
@@ -108,7 +80,7 @@ pub fn handle_menu_program<SPI, CS, DC, BUSY, RESET>(
     let _ = display.clear(BinaryColor::On);
 
     // Draw the menu for the current page
-    draw_menu(display, items, *current_item, current_page);
+    draw_menu(display, items, *current_item, current_page, status);
 
     // Update the display if any button was pressed
     if btn_up_pressed || btn_down_pressed {
@@ -121,7 +93,6 @@ pub fn launch_selected_program(
     btn_a_pressed: bool,
 ) -> Option<ProgramState> {
     if btn_a_pressed {
-        // let items = ["Lynix Badge", "CCNB", "Socials + QR", "Device Info", "Blinky", "Settings"];
 
         match current_item {
             // Handle each case and return the corresponding program state
@@ -130,6 +101,19 @@ pub fn launch_selected_program(
             2 => Some(ProgramState::Socials),
             3 => Some(ProgramState::Info),
             4 => Some(ProgramState::Blinky),
+            5 => Some(ProgramState::Barcodes),
+            7 => Some(ProgramState::Crypto),
+            8 => Some(ProgramState::Settings),
+            9 => Some(ProgramState::Clock),
+            10 => Some(ProgramState::Countdown),
+            11 => Some(ProgramState::Workbench),
+            12 => Some(ProgramState::Authenticator),
+            13 => Some(ProgramState::Identity),
+            14 => Some(ProgramState::Passwords),
+            15 => Some(ProgramState::Notes),
+            16 => Some(ProgramState::SecurityKey),
+            #[cfg(feature = "keyboard-macros")]
+            17 => Some(ProgramState::KeyboardMacros),
             _ => Some(ProgramState::NotFound)
         }
     } else {
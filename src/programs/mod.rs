@@ -5,3 +5,18 @@ pub mod main;
 pub mod ccnb;
 pub mod info;
 pub mod socials;
+pub mod barcodes;
+pub mod playlist;
+pub mod settings;
+pub mod clock;
+pub mod countdown;
+pub mod crypto;
+pub mod workbench;
+pub mod authenticator;
+pub mod identity;
+pub mod passwords;
+pub mod notes;
+pub mod privacy;
+pub mod security_key;
+#[cfg(feature = "keyboard-macros")]
+pub mod macros;
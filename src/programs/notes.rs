@@ -0,0 +1,350 @@
+// Notes: short private notes, encrypted in flash under a PIN (see `badge_crypto::vault`).
+//
+// The program opens locked every time and locks again when B goes back to the menu. The PIN is
+// entered with the buttons (see `pin`). After three wrong PINs in a row each try has to wait,
+// 30 seconds and doubling, and the tenth wipes the notes. The count is kept in flash, so turning
+// the badge off doesn't reset it. Unlocked, Up/Down page through the notes. Over serial:
+//
+//   notes pin 1234                    makes an empty vault, or changes the PIN while unlocked
+//   notes add some text               adds a note, while unlocked
+//   notes list                        prints the notes, while unlocked
+//   notes remove 2                    removes a note by its number, while unlocked
+//   notes wipe                        erases the vault and its PIN
+//
+// The decrypted notes and key only stay in memory while unlocked. PINs are short, so this keeps
+// the notes from anyone who picks the badge up, not from someone who can read out its flash.
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::vault::{self, Vault, NONCE_LEN, OVERHEAD, SALT_LEN};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::{String, Vec};
+use rand_core::RngCore;
+use zeroize::Zeroize;
+
+use crate::assets;
+use crate::draw;
+use crate::entropy;
+use crate::layout::{self, BODY_FONT, HEADING_FONT};
+use crate::pin::{self, PinPad};
+use crate::store::{self, Sector};
+use crate::usb;
+
+// All the notes together, a newline between each.
+const NOTES_LEN: usize = 2048;
+
+// The wrong PIN that wipes the notes.
+const MAX_TRIES: u8 = 10;
+
+#[derive(Default)]
+pub struct Notes {
+    // A vault is saved in flash.
+    exists: bool,
+    // Wrong PINs in a row.
+    failures: u8,
+    // Uptime in seconds before the next PIN can be tried.
+    wait_until: u64,
+    pad: PinPad,
+    wrong: bool,
+    // The key and notes while unlocked.
+    vault: Option<Vault>,
+    text: Vec<u8, NOTES_LEN>,
+    selected: usize,
+    // A wait was showing when the screen was drawn.
+    waiting: bool,
+    // Changed over serial, the screen needs redrawing.
+    stale: bool,
+}
+
+// Record layout: failures: u8, then the sealed vault.
+fn sealed() -> Option<(u8, &'static [u8])> {
+    let (&failures, sealed) = store::load(Sector::Vault)?.split_first()?;
+    Some((failures, sealed))
+}
+
+impl Notes {
+    /// Whether there's a vault and how many wrong PINs it has had, from flash.
+    pub fn load() -> Self {
+        let mut notes = Notes::default();
+        if let Some((failures, _)) = sealed() {
+            notes.exists = true;
+            notes.failures = failures;
+            notes.wait_until = pin::wait(failures);
+        }
+        notes
+    }
+
+    /// Wipes the key and notes from memory.
+    pub fn lock(&mut self) {
+        self.vault = None;
+        self.text.as_mut_slice().zeroize();
+        self.text.clear();
+        self.pad.clear();
+        self.wrong = false;
+        self.selected = 0;
+    }
+
+    fn notes(&self) -> impl Iterator<Item = &str> {
+        core::str::from_utf8(&self.text).unwrap_or_default().split('\n').filter(|note| !note.is_empty())
+    }
+
+    fn save_failures(&self) {
+        let mut record = [0; 1 + NOTES_LEN + OVERHEAD];
+        if let Some((_, sealed)) = sealed() {
+            record[0] = self.failures;
+            record[1..1 + sealed.len()].copy_from_slice(sealed);
+            let _ = store::save(Sector::Vault, &record[..1 + sealed.len()]);
+        }
+    }
+
+    // Seals the notes with a new nonce and saves them.
+    fn save(&self, rng: &mut entropy::Rng) -> Result<(), &'static str> {
+        let vault = self.vault.as_ref().ok_or("locked")?;
+        let mut nonce = [0; NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+        let mut record = [0; 1 + NOTES_LEN + OVERHEAD];
+        record[0] = self.failures;
+        let len = vault.seal(nonce, &self.text, &mut record[1..]).map_err(|_| "notes too long")?;
+        store::save(Sector::Vault, &record[..1 + len]).map_err(|_| "couldn't save to flash")
+    }
+
+    fn wipe(&mut self) {
+        self.lock();
+        *self = Notes::default();
+        // An empty record reads as no vault.
+        let _ = store::save(Sector::Vault, &[]);
+    }
+
+    fn unlock(&mut self, entered: &str, now: u64) {
+        if sealed().is_none() {
+            return;
+        }
+        // Counted as wrong before the key takes its second to derive, so cutting the power in
+        // the meantime doesn't get a free try.
+        self.failures = self.failures.saturating_add(1);
+        self.save_failures();
+        let Some((_, sealed)) = sealed() else {
+            return;
+        };
+
+        let mut text = [0; NOTES_LEN];
+        match Vault::open(entered.as_bytes(), sealed, &mut text) {
+            Ok((vault, len)) => {
+                self.vault = Some(vault);
+                self.text.clear();
+                let _ = self.text.extend_from_slice(&text[..len]);
+                text.zeroize();
+                self.wrong = false;
+                self.failures = 0;
+                self.save_failures();
+            }
+            Err(_) => {
+                self.wrong = true;
+                if self.failures >= MAX_TRIES {
+                    self.wipe();
+                } else {
+                    self.wait_until = now + pin::wait(self.failures);
+                }
+            }
+        }
+    }
+
+    fn run(&mut self, line: &str, rng: Option<&mut entropy::Rng>) -> Result<&'static str, &'static str> {
+        let line = line.trim_start();
+        let mut parts = line.splitn(3, ' ');
+        let _ = parts.next();
+        let (command, rest) = (parts.next(), parts.next().unwrap_or("").trim());
+
+        match command {
+            Some("wipe") => {
+                self.wipe();
+                return Ok("notes wiped");
+            }
+            Some("pin" | "add" | "list" | "remove") => {}
+            _ => return Err("usage: notes pin|add|list|remove|wipe"),
+        }
+        let rng = rng.ok_or("no random numbers: the ring oscillator failed its health tests")?;
+
+        if command == Some("pin") {
+            if !pin::valid(rest) {
+                return Err("usage: notes pin NEW. PINs are 4 to 8 digits");
+            }
+            if self.exists && self.vault.is_none() {
+                return Err("unlock Notes on the badge first");
+            }
+            let mut salt = [0; SALT_LEN];
+            rng.fill_bytes(&mut salt);
+            let old = self.vault.replace(Vault::create(rest.as_bytes(), salt, vault::ITERATIONS));
+            if let Err(e) = self.save(rng) {
+                self.vault = old;
+                return Err(e);
+            }
+            if self.exists {
+                return Ok("PIN changed");
+            }
+            // Only the badge unlocks it.
+            self.lock();
+            self.exists = true;
+            return Ok("vault made, unlock it on the badge to add notes");
+        }
+
+        if self.vault.is_none() {
+            return Err(if self.exists { "unlock Notes on the badge first" } else { "no vault yet: notes pin 1234" });
+        }
+        match command {
+            Some("add") if !rest.is_empty() => {
+                let mut text = self.text.clone();
+                if !text.is_empty() {
+                    text.push(b'\n').map_err(|_| "no room for more notes")?;
+                }
+                text.extend_from_slice(rest.as_bytes()).map_err(|_| "no room for more notes")?;
+                self.text.as_mut_slice().zeroize();
+                self.text = text;
+                self.save(rng).map(|_| "added")
+            }
+            Some("add") => Err("usage: notes add TEXT"),
+            Some("list") => {
+                for (index, note) in self.notes().enumerate() {
+                    let mut line: String<16> = String::new();
+                    let _ = write!(line, "{} ", index + 1);
+                    usb::write(line.as_bytes());
+                    usb::write(note.as_bytes());
+                    usb::write(b"\r\n");
+                }
+                Ok("")
+            }
+            _ => {
+                let count = self.notes().count();
+                let index = rest.parse::<usize>().ok().filter(|index| (1..=count).contains(index)).ok_or("usage: notes remove NUMBER, from notes list")?;
+                let mut text: Vec<u8, NOTES_LEN> = Vec::new();
+                for note in self.notes().enumerate().filter(|&(number, _)| number + 1 != index).map(|(_, note)| note) {
+                    if !text.is_empty() {
+                        let _ = text.push(b'\n');
+                    }
+                    let _ = text.extend_from_slice(note.as_bytes());
+                }
+                self.text.as_mut_slice().zeroize();
+                self.text = text;
+                self.selected = 0;
+                self.save(rng).map(|_| "removed")
+            }
+        }
+    }
+
+    /// Handles `notes ...` typed on the serial console, `line` is the whole line.
+    pub fn command(&mut self, line: &str, rng: Option<&mut entropy::Rng>) {
+        let result = self.run(line, rng);
+        self.stale = true;
+        let mut text: String<80> = String::new();
+        let _ = match result {
+            Ok("") => Ok(()),
+            Ok(message) | Err(message) => write!(text, "{}\r\n", message),
+        };
+        usb::write(text.as_bytes());
+    }
+}
+
+fn draw_locked<D>(display: &mut D, notes: &mut Notes, now: u64) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let width = display.bounding_box().size.width;
+    let top = layout::header(display, &assets::LOCK, "Notes");
+
+    let mut message: String<64> = String::new();
+    notes.waiting = notes.exists && now < notes.wait_until;
+    let _ = if !notes.exists {
+        write!(message, "No notes yet. Set a PIN over USB: notes pin 1234")
+    } else if notes.waiting {
+        let minutes = (notes.wait_until - now).div_ceil(60);
+        write!(message, "Too many wrong PINs. Try again in {} min", minutes)
+    } else if notes.wrong {
+        write!(message, "Wrong PIN, {} tries left", MAX_TRIES - notes.failures)
+    } else {
+        write!(message, "Enter PIN")
+    };
+    draw::draw_textbox(display, &message, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, top, width, 0);
+    if !notes.exists || notes.waiting {
+        return;
+    }
+
+    let y = top + 2 * BODY_FONT.character_size.height as i32;
+    draw::draw_textbox(display, &notes.pad.label(), HEADING_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, y, width, 0);
+}
+
+pub fn draw_notes_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    notes: &mut Notes,
+    now: u64,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let _ = display.clear(BinaryColor::On);
+    notes.stale = false;
+
+    if notes.vault.is_none() {
+        draw_locked(display, notes, now);
+        let _ = display.update();
+        return;
+    }
+
+    let width = display.bounding_box().size.width;
+    let top = layout::header(display, &assets::LOCK, "Notes");
+    let count = notes.notes().count();
+    match notes.notes().nth(notes.selected) {
+        Some(note) => {
+            let mut status: String<16> = String::new();
+            let _ = write!(status, "[{}/{}]", notes.selected + 1, count);
+            layout::status(display, &status);
+            draw::draw_textbox(display, note, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, width, 0);
+        }
+        None => {
+            let text = "No notes. Add some over USB: notes add TEXT";
+            draw::draw_textbox(display, text, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, top, width, 0);
+        }
+    }
+    let _ = display.update();
+}
+
+pub fn handle_notes_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    notes: &mut Notes,
+    now: u64,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let pressed = btn_up_pressed || btn_down_pressed || btn_a_pressed;
+
+    if notes.vault.is_none() {
+        if !notes.exists || now < notes.wait_until {
+            if !notes.stale {
+                return;
+            }
+        } else if let Some(entered) = notes.pad.handle(btn_up_pressed, btn_down_pressed, btn_a_pressed) {
+            notes.unlock(&entered, now);
+        } else if !pressed && !notes.stale && !notes.waiting {
+            // Still showing a wait that's over, otherwise nothing changed.
+            return;
+        }
+    } else if btn_up_pressed || btn_down_pressed {
+        let count = notes.notes().count().max(1);
+        notes.selected = if btn_up_pressed { (notes.selected + count - 1) % count } else { (notes.selected + 1) % count };
+    } else if !notes.stale {
+        return;
+    }
+
+    draw_notes_screen(display, notes, now);
+}
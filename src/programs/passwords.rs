@@ -0,0 +1,213 @@
+// Passwords: diceware passphrases and random passwords from the hardware random number generator.
+//
+// Rows pick a passphrase or a password, and which characters passwords use (A cycles through the
+// sets). A on a kind makes one and shows it large, with a QR code to scan it into a phone. There
+// A makes another, Up/Down change the number of words or characters and make another, and Up and
+// Down together go back to the rows. Nothing is saved or sent over serial, and what's shown is
+// wiped from memory when B leaves the program.
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::passgen::{self, DIGITS, LOWER, SYMBOLS, UPPER};
+use embedded_graphics::{mono_font::MonoFont, pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::String;
+use profont::PROFONT_12_POINT;
+use uc8151::{HEIGHT, WIDTH};
+use zeroize::Zeroize;
+
+use crate::assets;
+use crate::barcode::{self, Symbology};
+use crate::draw;
+use crate::entropy;
+use crate::font::TextStyle;
+use crate::layout::{self, Layout, BODY_FONT, HEADING_FONT, STATUS_FONT, TITLE_FONT};
+
+const PASSPHRASE: usize = 0;
+const PASSWORD: usize = 1;
+const CHARACTERS: usize = 2;
+const ROWS: usize = 3;
+
+const WORDS: (usize, usize) = (3, 10);
+const LENGTH: (usize, usize) = (4, 64);
+
+// Longest passphrase: the most words, all of the longest length, and the spaces between them.
+const SECRET: usize = WORDS.1 * (passgen::MAX_WORD + 1);
+
+// Character sets passwords can use, with their names.
+const CHARACTER_SETS: [(&str, &[&str]); 5] = [
+    ("a-z A-Z 0-9 !#", &[LOWER, UPPER, DIGITS, SYMBOLS]),
+    ("a-z A-Z 0-9", &[LOWER, UPPER, DIGITS]),
+    ("a-z 0-9", &[LOWER, DIGITS]),
+    ("a-z", &[LOWER]),
+    ("0-9", &[DIGITS]),
+];
+
+// Fonts for what's shown, largest first, the first it fits in is used.
+const FONTS: [MonoFont; 4] = [TITLE_FONT, HEADING_FONT, BODY_FONT, PROFONT_12_POINT];
+
+pub struct Passwords {
+    row: usize,
+    characters: usize,
+    words: usize,
+    length: usize,
+    // Kind on screen, PASSPHRASE or PASSWORD.
+    showing: Option<usize>,
+    secret: [u8; SECRET],
+    secret_len: usize,
+}
+
+impl Default for Passwords {
+    fn default() -> Self {
+        Passwords { row: 0, characters: 0, words: 6, length: 20, showing: None, secret: [0; SECRET], secret_len: 0 }
+    }
+}
+
+impl Passwords {
+    /// Wipes whatever was shown last and goes back to the rows.
+    pub fn forget(&mut self) {
+        self.secret.zeroize();
+        self.secret_len = 0;
+        self.showing = None;
+    }
+
+    fn generate(&mut self, rng: &mut entropy::Rng, kind: usize) {
+        self.secret.zeroize();
+        let result = match kind {
+            PASSPHRASE => passgen::passphrase(rng, self.words, b' ', &mut self.secret),
+            _ => passgen::password(rng, CHARACTER_SETS[self.characters].1, &mut self.secret[..self.length]),
+        };
+        self.secret_len = result.map_or(0, |secret| secret.len());
+        self.showing = Some(kind);
+    }
+
+    fn secret(&self) -> &str {
+        core::str::from_utf8(&self.secret[..self.secret_len]).unwrap_or_default()
+    }
+}
+
+pub fn draw_passwords_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    passwords: &Passwords,
+    rng_ok: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let _ = display.clear(BinaryColor::On);
+
+    let Some(kind) = passwords.showing else {
+        let mut passphrase: String<32> = String::new();
+        let _ = write!(passphrase, "Passphrase, {} words", passwords.words);
+        let mut password: String<32> = String::new();
+        let _ = write!(password, "Password, {} long", passwords.length);
+        let mut characters: String<32> = String::new();
+        let _ = write!(characters, "Using {}", CHARACTER_SETS[passwords.characters].0);
+
+        layout::draw(display, &Layout::List {
+            icon: &assets::LOCK,
+            title: "Passwords",
+            items: &[&passphrase, &password, &characters].map(|row| row.as_str()),
+            selected: passwords.row,
+            footer: if !rng_ok { "no RNG" } else if passwords.row == CHARACTERS { "[A] change" } else { "[A] make" },
+        });
+        let _ = display.update();
+        return;
+    };
+
+    let secret = passwords.secret();
+
+    // QR on the right, the secret beside it in the largest font that fits.
+    let left = match barcode::encode(Symbology::Qr, secret) {
+        Ok(symbol) => {
+            let scale = ((HEIGHT - 8) / symbol.width() as u32).clamp(1, 6);
+            let size = symbol.width() as u32 * scale;
+            let x = WIDTH - size - 4;
+            barcode::draw_symbol(display, &symbol, x as i32, ((HEIGHT - size) / 2) as i32, scale, scale);
+            x - 4
+        }
+        Err(_) => WIDTH,
+    };
+
+    let mut info: String<48> = String::new();
+    let _ = match kind {
+        PASSPHRASE => write!(info, "{} words, {} bits", passwords.words, passgen::entropy_bits(passgen::WORDS, passwords.words)),
+        _ => {
+            let choices = CHARACTER_SETS[passwords.characters].1.iter().map(|class| class.len()).sum();
+            write!(info, "{} long, {} bits", passwords.length, passgen::entropy_bits(choices, passwords.length))
+        }
+    };
+    let _ = info.push_str("\n[A] new  [Up/Down] length\n[Up+Down] back");
+
+    let info_height = STATUS_FONT.character_size.height as i32 * 3;
+    let room = HEIGHT as i32 - info_height - 4;
+    let font = FONTS
+        .iter()
+        .find(|font| {
+            let lines = TextStyle::new(font, BinaryColor::Off).wrapped_lines(secret, left);
+            lines as i32 * font.character_size.height as i32 <= room
+        })
+        .unwrap_or(&PROFONT_12_POINT);
+
+    draw::draw_textbox(display, secret, *font, BinaryColor::Off, HorizontalAlignment::Left, 0, 0, left, 0);
+    draw::draw_textbox(display, &info, STATUS_FONT, BinaryColor::Off, HorizontalAlignment::Left, 0, HEIGHT as i32 - info_height, left, 0);
+    let _ = display.update();
+}
+
+pub fn handle_passwords_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    passwords: &mut Passwords,
+    rng: Option<&mut entropy::Rng>,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let rng_ok = rng.is_some();
+
+    if let Some(kind) = passwords.showing {
+        let (value, (min, max)) = match kind {
+            PASSPHRASE => (&mut passwords.words, WORDS),
+            _ => (&mut passwords.length, LENGTH),
+        };
+        if btn_up_pressed && btn_down_pressed {
+            passwords.forget();
+        } else if btn_up_pressed || btn_down_pressed || btn_a_pressed {
+            if btn_up_pressed {
+                *value = (*value + 1).min(max);
+            } else if btn_down_pressed {
+                *value = value.saturating_sub(1).max(min);
+            }
+            match rng {
+                Some(rng) => passwords.generate(rng, kind),
+                None => passwords.forget(),
+            }
+        } else {
+            return;
+        }
+    } else if btn_up_pressed {
+        passwords.row = (passwords.row + ROWS - 1) % ROWS;
+    } else if btn_down_pressed {
+        passwords.row = (passwords.row + 1) % ROWS;
+    } else if btn_a_pressed {
+        match (passwords.row, rng) {
+            (CHARACTERS, _) => passwords.characters = (passwords.characters + 1) % CHARACTER_SETS.len(),
+            (kind @ (PASSPHRASE | PASSWORD), Some(rng)) => passwords.generate(rng, kind),
+            _ => {}
+        }
+    } else {
+        return;
+    }
+
+    draw_passwords_screen(display, passwords, rng_ok);
+}
@@ -0,0 +1,520 @@
+// Badge rotation.
+//
+// The Lynix Badge program cycles through an ordered playlist of screens, each shown for its own
+// number of seconds and optionally with the LED lit. A skips to the next screen, Up pauses and
+// resumes, Down opens the editor. There A on an entry opens it to change its screen and dwell
+// time, move it up or down or remove it, and Add puts a new one at the end. The `playlist` serial
+// command edits the same list over USB. Edits are kept in RAM, a reset brings back the default
+// playlist.
+//
+// While a schedule rule picks a single screen, that screen is shown instead of the rotation.
+
+use core::fmt::{self, Write as FmtWrite};
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use heapless::{String, Vec};
+use rp2040_hal::gpio::{Pin, PushPullOutput};
+use rp2040_hal::gpio::bank0::Gpio25;
+
+use crate::assets;
+use crate::layout::{self, Layout};
+use crate::programs::ccnb::draw_ccnb_screen;
+use crate::programs::info::draw_info_screen;
+use crate::programs::main::draw_main_screen;
+use crate::programs::socials::draw_socials_screen;
+use crate::calendar::Moment;
+use crate::schedule::{Rule, Show};
+use crate::screens;
+use crate::slide::Slide;
+use crate::usb;
+
+pub const MAX_ENTRIES: usize = 8;
+
+// The editor steps an entry through these dwell times, then the same again with the LED, then
+// back to skipped.
+const PRESETS: [u16; 5] = [5, 10, 20, 30, 60];
+
+// What the editor can do with an entry, after the rows for its screen and dwell time.
+const ACTIONS: [&str; 4] = ["Move up", "Move down", "Remove", "Back"];
+
+const ROWS_PER_PAGE: usize = 4;
+
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub slide: Slide,
+    /// Dwell time, 0 skips the entry.
+    pub seconds: u16,
+    /// LED on while the entry is shown.
+    pub led: bool,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.seconds == 0 {
+            return write!(f, "{} skip", self.slide);
+        }
+        write!(f, "{} {}s", self.slide, self.seconds)?;
+        if self.led {
+            f.write_str(" led")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Playlist {
+    entries: Vec<Entry, MAX_ENTRIES>,
+    current: usize,
+    // Uptime when the current entry went up, `None` until the pass after it was drawn.
+    shown_at: Option<u64>,
+    paused: bool,
+    // Row selected in the on-device editor, `None` while rotating.
+    editing: Option<usize>,
+    // Row selected in the editor's entry, `None` while picking an entry.
+    action: Option<usize>,
+    // Screen chosen by the schedule, which overrides the rotation.
+    pinned: Option<Slide>,
+    // The panel no longer shows what it should.
+    stale: bool,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        let mut entries = Vec::new();
+        let _ = entries.push(Entry { slide: Slide::Card, seconds: 10, led: false });
+        let _ = entries.push(Entry { slide: Slide::Socials, seconds: 10, led: true });
+        let _ = entries.push(Entry { slide: Slide::Ccnb, seconds: 0, led: false });
+        let _ = entries.push(Entry { slide: Slide::Info, seconds: 0, led: false });
+
+        Playlist { entries, current: 0, shown_at: None, paused: false, editing: None, action: None, pinned: None, stale: true }
+    }
+}
+
+impl Playlist {
+    fn showable(entry: &Entry) -> bool {
+        entry.seconds > 0 && available(entry.slide)
+    }
+
+    // Moves to the next entry that can be shown, staying put when there is none.
+    fn advance(&mut self) {
+        let len = self.entries.len();
+        if let Some(next) = (1..=len).map(|step| (self.current + step) % len).find(|&i| Self::showable(&self.entries[i])) {
+            // A playlist with a single screen left on it isn't redrawn every time round.
+            if next != self.current {
+                self.current = next;
+                self.stale = true;
+            }
+        }
+        self.shown_at = None;
+    }
+
+    /// Applies what the schedule wants on screen right now.
+    pub fn schedule(&mut self, show: Show) {
+        let pinned = match show {
+            Show::Slide(slide) if available(slide) => Some(slide),
+            _ => None,
+        };
+        if pinned != self.pinned {
+            self.pinned = pinned;
+            self.shown_at = None;
+            self.stale = true;
+        }
+    }
+
+    // After the entries were edited.
+    fn changed(&mut self) {
+        if self.current >= self.entries.len() {
+            self.current = 0;
+        }
+        if let Some(row) = self.editing {
+            self.editing = Some(row.min(self.entries.len()));
+            self.action = None;
+        }
+        self.shown_at = None;
+        self.stale = true;
+    }
+
+    // Does the editor's `action` row to entry `row`.
+    fn act(&mut self, row: usize, action: usize) {
+        let len = self.entries.len();
+        let Some(entry) = self.entries.get_mut(row) else {
+            return;
+        };
+        match action {
+            0 => entry.slide = next_slide(entry.slide),
+            1 => cycle(entry),
+            2 if row > 0 => {
+                self.entries.swap(row, row - 1);
+                self.editing = Some(row - 1);
+            }
+            3 if row + 1 < len => {
+                self.entries.swap(row, row + 1);
+                self.editing = Some(row + 1);
+            }
+            4 => {
+                self.entries.remove(row);
+                self.action = None;
+            }
+            5 => self.action = None,
+            _ => {}
+        }
+    }
+
+    /// Handles `playlist ...` typed on the serial console.
+    pub fn command<'a>(&mut self, mut args: impl Iterator<Item = &'a str>) {
+        let result = match args.next() {
+            None | Some("list") => {
+                self.list();
+                return;
+            }
+            Some("set") => args
+                .next()
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.entries.get_mut(index.wrapping_sub(1)))
+                .ok_or("no such entry")
+                .and_then(|entry| {
+                    *entry = parse_entry(args)?;
+                    Ok(())
+                }),
+            Some("add") => parse_entry(args).and_then(|entry| self.entries.push(entry).map_err(|_| "playlist is full")),
+            Some("remove") => match args.next().and_then(|index| index.parse::<usize>().ok()) {
+                Some(index) if (1..=self.entries.len()).contains(&index) => {
+                    self.entries.remove(index - 1);
+                    Ok(())
+                }
+                _ => Err("no such entry"),
+            },
+            Some("clear") => {
+                self.entries.clear();
+                Ok(())
+            }
+            Some(command @ ("pause" | "resume" | "next")) => {
+                match command {
+                    "pause" => self.paused = true,
+                    "resume" => self.paused = false,
+                    _ => self.advance(),
+                }
+                self.shown_at = None;
+                self.list();
+                return;
+            }
+            Some(_) => Err("usage: playlist [list|set N SLIDE SECS [led]|add SLIDE SECS [led]|remove N|clear|pause|resume|next]"),
+        };
+
+        match result {
+            Ok(()) => {
+                self.changed();
+                self.list();
+            }
+            Err(message) => {
+                usb::write(message.as_bytes());
+                usb::write(b"\r\n");
+            }
+        }
+    }
+
+    fn list(&self) {
+        let mut line: String<64> = String::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            line.clear();
+            let marker = if index == self.current { '>' } else { ' ' };
+            let _ = write!(line, "{}{}. {}\r\n", marker, index + 1, entry);
+            usb::write(line.as_bytes());
+        }
+        if self.paused {
+            usb::write(b"(paused)\r\n");
+        }
+    }
+}
+
+/// Handles `schedule` typed on the serial console: lists the rules, marking the one in effect.
+pub fn schedule_command(rules: &[&str], now: Option<&Moment>) {
+    let active = now.and_then(|now| rules.iter().position(|line| Rule::parse(line).is_ok_and(|rule| rule.matches(now))));
+    for (index, line) in rules.iter().enumerate() {
+        usb::write(if Some(index) == active { b">" } else { b" " });
+        usb::write(line.as_bytes());
+        usb::write(b"\r\n");
+    }
+
+    if rules.is_empty() {
+        usb::write(b"no rules, the playlist runs\r\n");
+    } else if now.is_none() {
+        usb::write(b"clock not set, the playlist runs\r\n");
+    }
+}
+
+// Screens flashed with tools/screenc may not be there.
+fn available(slide: Slide) -> bool {
+    match slide {
+        Slide::Stored(index) => screens::stored().is_some_and(|screens| (index as usize) < screens.len()),
+        _ => true,
+    }
+}
+
+// SLIDE SECS [led]
+fn parse_entry<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<Entry, &'static str> {
+    let slide = args.next().and_then(Slide::parse).ok_or("slide is card, ccnb, socials, info or screenN")?;
+    let seconds = args.next().and_then(|seconds| seconds.parse().ok()).ok_or("seconds missing")?;
+    let led = match args.next() {
+        None => false,
+        Some("led") => true,
+        Some(_) => return Err("expected `led`"),
+    };
+    Ok(Entry { slide, seconds, led })
+}
+
+// Next screen in the editor's cycle, through whichever stored screens there are.
+fn next_slide(slide: Slide) -> Slide {
+    let stored = screens::stored().map_or(0, |screens| screens.len());
+    match slide {
+        Slide::Card => Slide::Socials,
+        Slide::Socials => Slide::Ccnb,
+        Slide::Ccnb => Slide::Info,
+        Slide::Info if stored > 0 => Slide::Stored(0),
+        Slide::Stored(index) if (index as usize) + 1 < stored => Slide::Stored(index + 1),
+        _ => Slide::Card,
+    }
+}
+
+// Next step in the editor's cycle of dwell times.
+fn cycle(entry: &mut Entry) {
+    let position = PRESETS.iter().position(|&seconds| seconds == entry.seconds);
+    (entry.seconds, entry.led) = match (position, entry.led) {
+        (Some(i), led) if i + 1 < PRESETS.len() => (PRESETS[i + 1], led),
+        (Some(_), false) => (PRESETS[0], true),
+        (Some(_), true) => (0, false),
+        (None, _) => (PRESETS[0], false),
+    };
+}
+
+pub fn draw_playlist_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+    led_pin: &mut Pin<Gpio25, PushPullOutput>,
+    playlist: &mut Playlist,
+    hidden: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    playlist.stale = false;
+    playlist.shown_at = None;
+    let _ = display.clear(BinaryColor::On);
+
+    if let Some(row) = playlist.editing {
+        led_pin.set_low().unwrap();
+        draw_editor(display, playlist, row);
+        return;
+    }
+
+    if let Some(slide) = playlist.pinned {
+        led_pin.set_low().unwrap();
+        draw_slide(display, delay, slide, hidden);
+        return;
+    }
+
+    if !playlist.entries.get(playlist.current).is_some_and(Playlist::showable) {
+        playlist.advance();
+        playlist.stale = false;
+    }
+
+    let Some(entry) = playlist.entries.get(playlist.current).filter(|entry| Playlist::showable(entry)).copied() else {
+        led_pin.set_low().unwrap();
+        layout::draw(display, &Layout::App {
+            icon: &assets::APP,
+            title: "Playlist",
+            body: "Nothing to show.\nPress Down to edit the playlist.",
+        });
+        let _ = display.update();
+        return;
+    };
+
+    if entry.led {
+        led_pin.set_high().unwrap();
+    } else {
+        led_pin.set_low().unwrap();
+    }
+
+    draw_slide(display, delay, entry.slide, hidden);
+}
+
+fn draw_slide<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+    slide: Slide,
+    hidden: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    match slide {
+        Slide::Card => draw_main_screen(display, delay, hidden),
+        Slide::Ccnb => draw_ccnb_screen(display, delay),
+        Slide::Socials => draw_socials_screen(display, hidden),
+        Slide::Info => draw_info_screen(display),
+        // Stored screens may show anything, the card stands in for them in privacy mode.
+        Slide::Stored(_) if hidden => draw_main_screen(display, delay, true),
+        Slide::Stored(index) => {
+            if let Some(screen) = screens::stored().and_then(|screens| screens.get(index as usize)) {
+                screens::show(display, delay, &screen);
+            }
+        }
+    }
+}
+
+fn draw_editor<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    playlist: &Playlist,
+    row: usize,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    if let (Some(action), Some(entry)) = (playlist.action, playlist.entries.get(row)) {
+        let mut title: String<16> = String::new();
+        let _ = write!(title, "Entry {}", row + 1);
+        let mut slide: String<24> = String::new();
+        let _ = write!(slide, "Show {}", entry.slide);
+        let mut time: String<24> = String::new();
+        let _ = match (entry.seconds, entry.led) {
+            (0, _) => write!(time, "Skipped"),
+            (seconds, false) => write!(time, "{}s", seconds),
+            (seconds, true) => write!(time, "{}s with LED", seconds),
+        };
+
+        let mut rows: Vec<&str, { ACTIONS.len() + 2 }> = Vec::new();
+        let _ = rows.push(&slide);
+        let _ = rows.push(&time);
+        let _ = rows.extend_from_slice(&ACTIONS);
+        draw_page(display, &title, &rows, action);
+        return;
+    }
+
+    let mut labels: Vec<String<24>, { MAX_ENTRIES + 2 }> = Vec::new();
+    for entry in &playlist.entries {
+        let mut label = String::new();
+        let _ = write!(label, "{}", entry);
+        let _ = labels.push(label);
+    }
+    if !playlist.entries.is_full() {
+        let _ = labels.push(String::from("Add"));
+    }
+    let _ = labels.push(String::from("Done"));
+    let rows: Vec<&str, { MAX_ENTRIES + 2 }> = labels.iter().map(|label| label.as_str()).collect();
+    draw_page(display, "Playlist", &rows, row);
+}
+
+// The page of `rows` with `selected` on it.
+fn draw_page<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    title: &str,
+    rows: &[&str],
+    selected: usize,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let start = selected / ROWS_PER_PAGE * ROWS_PER_PAGE;
+    let end = (start + ROWS_PER_PAGE).min(rows.len());
+
+    layout::draw(display, &Layout::List {
+        icon: &assets::APP,
+        title,
+        items: &rows[start..end],
+        selected: selected - start,
+        footer: "[A] change",
+    });
+    let _ = display.update();
+}
+
+pub fn handle_playlist_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+    led_pin: &mut Pin<Gpio25, PushPullOutput>,
+    playlist: &mut Playlist,
+    hidden: bool,
+    uptime: u64,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    if let (Some(row), Some(action)) = (playlist.editing, playlist.action) {
+        // The entry's screen and dwell time, then the actions.
+        let rows = ACTIONS.len() + 2;
+
+        if btn_up_pressed {
+            playlist.action = Some((action + rows - 1) % rows);
+        } else if btn_down_pressed {
+            playlist.action = Some((action + 1) % rows);
+        } else if btn_a_pressed {
+            playlist.act(row, action);
+        } else {
+            return;
+        }
+        playlist.stale = true;
+    } else if let Some(row) = playlist.editing {
+        // Entries, "Add" while there's room, then "Done".
+        let len = playlist.entries.len();
+        let full = playlist.entries.is_full();
+        let rows = len + usize::from(!full) + 1;
+
+        if btn_up_pressed {
+            playlist.editing = Some((row + rows - 1) % rows);
+        } else if btn_down_pressed {
+            playlist.editing = Some((row + 1) % rows);
+        } else if btn_a_pressed {
+            if row == len && !full {
+                let _ = playlist.entries.push(Entry { slide: Slide::Card, seconds: PRESETS[1], led: false });
+            }
+            if row < playlist.entries.len() {
+                playlist.action = Some(0);
+            } else {
+                playlist.editing = None;
+                playlist.changed();
+            }
+        } else {
+            return;
+        }
+        playlist.stale = true;
+    } else if btn_down_pressed {
+        playlist.editing = Some(0);
+        playlist.stale = true;
+    } else if playlist.pinned.is_some() {
+        // The schedule decides, nothing to rotate.
+    } else if btn_up_pressed {
+        playlist.paused = !playlist.paused;
+        playlist.shown_at = None;
+    } else if btn_a_pressed {
+        playlist.advance();
+    } else if !playlist.paused {
+        // Redraws take seconds, so dwell times count from when the entry was up on the panel.
+        let shown_at = *playlist.shown_at.get_or_insert(uptime);
+        let seconds = playlist.entries.get(playlist.current).map_or(0, |entry| entry.seconds);
+        if uptime.saturating_sub(shown_at) >= seconds as u64 {
+            playlist.advance();
+        }
+    }
+
+    if playlist.stale {
+        draw_playlist_screen(display, delay, led_pin, playlist, hidden);
+    }
+}
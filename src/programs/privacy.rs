@@ -0,0 +1,315 @@
+// Privacy mode: the Lynix Badge card shows only the name and pronouns and Socials shows nothing,
+// until Up and Down are pressed together on them. With a PIN set that asks for it first (see
+// `pin`). The details hide again after the timeout, or when Up and Down are pressed together again.
+// Wrong PINs, on the buttons or to `privacy off` and `privacy pin`, count towards the same waits
+// as the Authenticator's, and the count is kept in flash.
+//
+//   privacy                           whether it's on, the timeout and if there's a PIN
+//   privacy on                        hides the details
+//   privacy off                       always shows them, with the PIN if one is set: privacy off 1234
+//   privacy pin 1234                  asks for a PIN to show them. Changing it: privacy pin OLD NEW,
+//                                     removing it: privacy pin OLD none
+//   privacy timeout 60                seconds the details stay shown, 10 to 3600
+//
+// The settings are kept in flash (see `store`), until they're first changed the profile's
+// `privacy` decides whether the badge starts hidden.
+
+use core::fmt::Write as FmtWrite;
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::String;
+
+use crate::assets;
+use crate::draw;
+use crate::layout::{self, BODY_FONT, HEADING_FONT};
+use crate::pin::{self, PinPad};
+use crate::profile;
+use crate::store::{self, Sector};
+use crate::usb;
+
+const DEFAULT_TIMEOUT: u16 = 60;
+const TIMEOUT: (u16, u16) = (10, 3600);
+
+pub struct Privacy {
+    enabled: bool,
+    // `pin::hash` of the PIN, once one is set.
+    pin: Option<[u8; 32]>,
+    timeout: u16,
+    // Wrong PINs in a row.
+    failures: u8,
+    // Uptime in seconds before the next PIN can be tried.
+    wait_until: u64,
+    // A wait was showing when the screen was drawn.
+    waiting: bool,
+    // Uptime the details hide again at, while they're shown.
+    revealed_until: Option<u64>,
+    pad: PinPad,
+    wrong: bool,
+    // Changed over serial or timed out, the screen needs redrawing.
+    stale: bool,
+}
+
+impl Default for Privacy {
+    fn default() -> Self {
+        Privacy {
+            enabled: profile::PRIVACY,
+            pin: None,
+            timeout: DEFAULT_TIMEOUT,
+            failures: 0,
+            wait_until: 0,
+            waiting: false,
+            revealed_until: None,
+            pad: PinPad::default(),
+            wrong: false,
+            stale: false,
+        }
+    }
+}
+
+// Record layout, integers little-endian:
+//
+//   enabled: u8  timeout: u16  has_pin: u8  pin_hash: [u8; 32]
+//   failures: u8 (wrong PINs in a row, none if the record stops before it)
+fn parse(data: &[u8]) -> Option<Privacy> {
+    if !(36..=37).contains(&data.len()) {
+        return None;
+    }
+    let failures = data.get(36).copied().unwrap_or(0);
+    Some(Privacy {
+        enabled: data[0] != 0,
+        timeout: u16::from_le_bytes([data[1], data[2]]),
+        pin: if data[3] != 0 { data[4..36].try_into().ok() } else { None },
+        failures,
+        wait_until: pin::wait(failures),
+        ..Privacy::default()
+    })
+}
+
+impl Privacy {
+    /// The settings saved in flash, or the profile's.
+    pub fn load() -> Self {
+        store::load(Sector::Privacy).and_then(parse).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let mut data = [0; 37];
+        data[0] = self.enabled as u8;
+        data[1..3].copy_from_slice(&self.timeout.to_le_bytes());
+        data[3] = self.pin.is_some() as u8;
+        data[4..36].copy_from_slice(&self.pin.unwrap_or([0; 32]));
+        data[36] = self.failures;
+        if store::save(Sector::Privacy, &data).is_err() {
+            usb::write(b"couldn't save to flash\r\n");
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether contact details should be left off the screen right now.
+    pub fn hidden(&self) -> bool {
+        self.enabled && self.revealed_until.is_none()
+    }
+
+    fn reveal(&mut self, now: u64) {
+        self.revealed_until = Some(now + self.timeout as u64);
+    }
+
+    /// Up and Down pressed together on a screen with contact details: hides them if they're
+    /// shown, otherwise shows them. Returns true if the PIN has to be entered first.
+    pub fn toggle(&mut self, now: u64) -> bool {
+        if self.revealed_until.take().is_some() {
+            return false;
+        }
+        if self.pin.is_some() {
+            self.pad.clear();
+            self.wrong = false;
+            return true;
+        }
+        self.reveal(now);
+        false
+    }
+
+    /// Hides the details again once the timeout has passed. Returns true if the screen needs
+    /// redrawing, for that or for a change over serial.
+    pub fn update(&mut self, now: u64) -> bool {
+        if self.revealed_until.is_some_and(|until| now >= until) {
+            self.revealed_until = None;
+            self.stale = true;
+        }
+        core::mem::take(&mut self.stale)
+    }
+
+    // Checks `entered` against the PIN and counts it if it's wrong, right if there's no PIN.
+    // `None` while a wait from earlier wrong PINs is still running.
+    fn check_pin(&mut self, entered: &str, now: u64) -> Option<bool> {
+        let Some(hash) = self.pin else {
+            return Some(true);
+        };
+        if now < self.wait_until {
+            return None;
+        }
+        let right = pin::hash(entered) == hash;
+        let failures = if right { 0 } else { self.failures.saturating_add(1) };
+        if failures != self.failures {
+            self.failures = failures;
+            self.wait_until = now + pin::wait(failures);
+            self.save();
+        }
+        Some(right)
+    }
+
+    /// Handles `privacy ...` typed on the serial console.
+    pub fn command<'a>(&mut self, mut args: impl Iterator<Item = &'a str>, now: u64) {
+        let result = match (args.next(), args.next(), args.next()) {
+            (None, ..) => {
+                let mut line: String<64> = String::new();
+                let _ = write!(
+                    line,
+                    "privacy {}, shown for {}s, {}\r\n",
+                    if self.enabled { "on" } else { "off" },
+                    self.timeout,
+                    if self.pin.is_some() { "PIN set" } else { "no PIN" },
+                );
+                usb::write(line.as_bytes());
+                return;
+            }
+            (Some("on"), None, None) => {
+                self.enabled = true;
+                Ok("privacy on")
+            }
+            (Some("off"), None, None) if self.pin.is_some() => Err("the PIN is needed: privacy off PIN"),
+            (Some("off"), pin, None) => match self.check_pin(pin.unwrap_or(""), now) {
+                Some(true) => {
+                    self.enabled = false;
+                    Ok("privacy off")
+                }
+                Some(false) => {
+                    self.stale = true;
+                    Err("wrong PIN")
+                }
+                None => Err("too many wrong PINs, try again later"),
+            },
+            (Some("pin"), Some(new), None) if self.pin.is_none() && pin::valid(new) => {
+                self.pin = Some(pin::hash(new));
+                Ok("PIN set")
+            }
+            (Some("pin"), Some(_), Some(_)) if self.pin.is_none() => Err("no PIN set: privacy pin NEW"),
+            (Some("pin"), Some(old), Some(new)) if new == "none" || pin::valid(new) => match self.check_pin(old, now) {
+                Some(true) => {
+                    self.pin = pin::valid(new).then(|| pin::hash(new));
+                    Ok(if new == "none" { "PIN removed" } else { "PIN changed" })
+                }
+                Some(false) => {
+                    self.stale = true;
+                    Err("wrong PIN")
+                }
+                None => Err("too many wrong PINs, try again later"),
+            },
+            (Some("pin"), ..) => Err("usage: privacy pin NEW, privacy pin OLD NEW, or privacy pin OLD none. PINs are 4 to 8 digits"),
+            (Some("timeout"), Some(seconds), None) => {
+                match seconds.parse::<u16>().ok().filter(|seconds| (TIMEOUT.0..=TIMEOUT.1).contains(seconds)) {
+                    Some(seconds) => {
+                        self.timeout = seconds;
+                        Ok("timeout set")
+                    }
+                    None => Err("timeout is 10 to 3600 seconds"),
+                }
+            }
+            _ => Err("usage: privacy on|off|pin|timeout"),
+        };
+
+        match result {
+            Ok(message) => {
+                self.save();
+                self.revealed_until = None;
+                self.stale = true;
+                usb::write(message.as_bytes());
+            }
+            Err(message) => usb::write(message.as_bytes()),
+        }
+        usb::write(b"\r\n");
+    }
+}
+
+pub fn draw_reveal_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    privacy: &mut Privacy,
+    now: u64,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let _ = display.clear(BinaryColor::On);
+    let width = display.bounding_box().size.width;
+    let top = layout::header(display, &assets::LOCK, "Privacy");
+
+    let mut message: String<64> = String::new();
+    privacy.waiting = now < privacy.wait_until;
+    let _ = if privacy.waiting {
+        let minutes = (privacy.wait_until - now).div_ceil(60);
+        write!(message, "Too many wrong PINs. Try again in {} min", minutes)
+    } else if privacy.wrong {
+        write!(message, "Wrong PIN")
+    } else {
+        write!(message, "Enter PIN to show details")
+    };
+    draw::draw_textbox(display, &message, BODY_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, top, width, 0);
+    if privacy.waiting {
+        let _ = display.update();
+        return;
+    }
+    let y = top + 2 * BODY_FONT.character_size.height as i32;
+    draw::draw_textbox(display, &privacy.pad.label(), HEADING_FONT, BinaryColor::Off, HorizontalAlignment::Center, 0, y, width, 0);
+    let _ = display.update();
+}
+
+/// PIN entry for showing the details. Returns true once it's done, with the details shown if the
+/// PIN was right; Up and Down together give up.
+pub fn handle_reveal_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    privacy: &mut Privacy,
+    now: u64,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) -> bool where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    if btn_up_pressed && btn_down_pressed {
+        privacy.pad.clear();
+        return true;
+    }
+    let waiting = now < privacy.wait_until;
+    if waiting || !(btn_up_pressed || btn_down_pressed || btn_a_pressed) {
+        // Redrawn when a wait starts over serial or the one showing is over.
+        if waiting != privacy.waiting {
+            draw_reveal_screen(display, privacy, now);
+        }
+        return false;
+    }
+
+    if let Some(entered) = privacy.pad.handle(btn_up_pressed, btn_down_pressed, btn_a_pressed) {
+        match privacy.check_pin(&entered, now) {
+            Some(true) => {
+                privacy.reveal(now);
+                return true;
+            }
+            Some(false) => privacy.wrong = true,
+            None => {}
+        }
+    }
+    draw_reveal_screen(display, privacy, now);
+    false
+}
@@ -0,0 +1,226 @@
+// Security Key: the badge as a FIDO U2F security key over USB HID, for sites that take one as a
+// second factor.
+//
+// The messages and the keys for sites are in `badge_crypto::u2f`, the HID packets in
+// `badge_crypto::ctaphid`. Two secrets are made on first boot from the hardware random number
+// generator (see `entropy`) and kept in flash, and the signature counter in two sectors of its
+// own so signing never rewrites the secrets (see `store`). Requests are answered whichever
+// program is open, but registering and signing in need A pressed with this program open, which
+// shows when a site is waiting for it.
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::ctaphid::{self, Event, Transport};
+use badge_crypto::u2f::{self, Presence};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use heapless::String;
+use rand_core::RngCore;
+
+use crate::assets;
+use crate::entropy;
+use crate::layout::{self, Layout};
+use crate::store::{self, Sector};
+use crate::usb;
+
+// Seconds a press of A counts for.
+const PRESENCE: u64 = 10;
+// Seconds without a request before a site is taken to have given up.
+const WAITING: u64 = 5;
+
+pub struct SecurityKey {
+    key: u2f::SecurityKey,
+    transport: Transport,
+    counter: u32,
+    // Uptime a site last asked for a press of A.
+    wanted: Option<u64>,
+    // Uptime the last press of A counts until.
+    present_until: Option<u64>,
+    // Changed since drawn, the screen needs redrawing.
+    stale: bool,
+}
+
+/// Why there's no security key this session.
+#[derive(Clone, Copy)]
+pub enum Unavailable {
+    /// Nothing saved and no random number generator to make the secrets.
+    NoSecrets,
+    /// The secrets or the counter in flash are damaged. New secrets would lock the owner out of
+    /// every site registered with the old ones, so none are made.
+    Damaged,
+}
+
+impl Unavailable {
+    fn message(self) -> &'static str {
+        match self {
+            Unavailable::NoSecrets => "No secrets yet. They're made on a boot with a working random number generator.",
+            Unavailable::Damaged => "The secrets or counter saved in flash are damaged. Sites registered with them can't be signed in to.",
+        }
+    }
+}
+
+// The counter goes in the even or odd sector by its value, so the write that goes up erases the
+// other one and power loss mid-write leaves the one before it to carry on from. Each record is
+// the counter as a u32 LE.
+fn counter_sector(counter: u32) -> Sector {
+    if counter % 2 == 0 { Sector::SecurityKeyCounterEven } else { Sector::SecurityKeyCounterOdd }
+}
+
+fn save_counter(counter: u32) -> Result<(), ()> {
+    store::save(counter_sector(counter), &counter.to_le_bytes())
+}
+
+// The highest counter saved, 0 if none ever was. `None` if both sectors have been written but
+// neither holds a counter.
+fn load_counter() -> Option<u32> {
+    let sectors = [Sector::SecurityKeyCounterEven, Sector::SecurityKeyCounterOdd];
+    let saved = sectors
+        .iter()
+        .filter_map(|&sector| store::load(sector))
+        .filter_map(|data| Some(u32::from_le_bytes(data.try_into().ok()?)))
+        .max();
+    match saved {
+        Some(counter) => Some(counter),
+        None if sectors.iter().any(|&sector| store::erased(sector)) => Some(0),
+        None => None,
+    }
+}
+
+impl SecurityKey {
+    /// The secrets saved in flash, or new ones saved for next time if none ever were.
+    pub fn load_or_create(rng: Option<&mut entropy::Rng>) -> Result<Self, Unavailable> {
+        // Record layout: master: [u8; 32]  attestation: [u8; 32]
+        let secrets: [u8; 64] = match store::load(Sector::SecurityKey) {
+            Some(data) => data.try_into().map_err(|_| Unavailable::Damaged)?,
+            None if store::erased(Sector::SecurityKey) => {
+                let mut secrets = [0; 64];
+                rng.ok_or(Unavailable::NoSecrets)?.fill_bytes(&mut secrets);
+                // Still usable for this session if it couldn't be saved.
+                let _ = store::save(Sector::SecurityKey, &secrets);
+                secrets
+            }
+            None => return Err(Unavailable::Damaged),
+        };
+        let counter = load_counter().ok_or(Unavailable::Damaged)?;
+
+        let (master, attestation) = secrets.split_at(32);
+        let master = master.try_into().map_err(|_| Unavailable::Damaged)?;
+        let attestation = attestation.try_into().map_err(|_| Unavailable::Damaged)?;
+        Ok(SecurityKey {
+            key: u2f::SecurityKey::new(master, attestation).ok_or(Unavailable::Damaged)?,
+            transport: Transport::default(),
+            counter,
+            wanted: None,
+            present_until: None,
+            stale: false,
+        })
+    }
+
+    fn present(&self, now: u64) -> bool {
+        self.present_until.is_some_and(|until| now < until)
+    }
+
+    /// Answers whatever the host sent over HID. Called often, hosts expect answers quickly.
+    pub fn poll(&mut self, rng: &mut entropy::Rng, now: u64) {
+        while let Some(packet) = usb::take_packet() {
+            let present = self.present(now);
+            let Some(Event::Message { channel, data }) = self.transport.receive(&packet, now, usb::send_packet) else {
+                continue;
+            };
+
+            let mut response = [0; u2f::MAX_RESPONSE];
+            let counter = self.counter;
+            let (mut len, presence) = self.key.process(rng, data, &mut self.counter, present, &mut response);
+            // The counter has to go up for good before a signature with it leaves the badge,
+            // otherwise the site gets an error instead and can try again.
+            if self.counter != counter && save_counter(self.counter).is_err() {
+                self.counter = counter;
+                response[..2].copy_from_slice(&u2f::SW_CONDITIONS_NOT_SATISFIED.to_be_bytes());
+                len = 2;
+            }
+            match presence {
+                Presence::Wanted => {
+                    self.stale |= self.wanted.is_none();
+                    self.wanted = Some(now);
+                }
+                Presence::Used => {
+                    self.wanted = None;
+                    self.present_until = None;
+                    self.stale = true;
+                }
+                Presence::NotNeeded => {}
+            }
+            ctaphid::reply(channel, ctaphid::MSG, &response[..len], usb::send_packet);
+        }
+
+        // A host that stopped partway through a message has given up on it.
+        self.transport.expire(now, usb::send_packet);
+
+        // A site that stopped asking has given up.
+        if self.wanted.is_some_and(|at| now > at + WAITING) {
+            self.wanted = None;
+            self.present_until = None;
+            self.stale = true;
+        }
+    }
+}
+
+pub fn draw_security_key_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    security_key: Result<&mut SecurityKey, Unavailable>,
+    now: u64,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let mut body: String<128> = String::new();
+    match security_key {
+        Err(unavailable) => {
+            let _ = body.push_str(unavailable.message());
+        }
+        Ok(security_key) => {
+            security_key.stale = false;
+            let _ = match (security_key.wanted.is_some(), security_key.present(now)) {
+                (true, true) => write!(body, "Confirmed, sending to the site."),
+                (true, false) => write!(body, "A site is asking.\nPress A to confirm."),
+                (false, _) => write!(body, "Ready. Register or sign in to a site over USB.\n{} signatures so far", security_key.counter),
+            };
+        }
+    }
+
+    let _ = display.clear(BinaryColor::On);
+    layout::draw(display, &Layout::App {
+        icon: &assets::LOCK,
+        title: "Security Key",
+        body: &body,
+    });
+    let _ = display.update();
+}
+
+pub fn handle_security_key_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    security_key: Result<&mut SecurityKey, Unavailable>,
+    now: u64,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let Ok(security_key) = security_key else {
+        return;
+    };
+    if btn_a_pressed && security_key.wanted.is_some() {
+        security_key.present_until = Some(now + PRESENCE);
+        security_key.stale = true;
+    }
+    if security_key.stale {
+        draw_security_key_screen(display, Ok(security_key), now);
+    }
+}
@@ -0,0 +1,188 @@
+// Settings: the clock's date, time and time zone.
+//
+// Up/Down pick a row and A changes it. Date and time are edited a field at a time: Up/Down change
+// the field in brackets, A moves to the next one, and the clock is set after the last. The UTC
+// offset moves in half hours and A cycles through the daylight saving rules. Changes last until
+// the badge is reset.
+
+use core::fmt::Write as FmtWrite;
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use heapless::String;
+
+use crate::assets;
+use crate::calendar::{self, Date, Dst, Moment, UtcOffset};
+use crate::clock::{self, Clock};
+use crate::layout::{self, Layout};
+
+const DATE: usize = 0;
+const TIME: usize = 1;
+const ZONE: usize = 2;
+const DAYLIGHT: usize = 3;
+const ROWS: usize = 4;
+
+// Where the date and time pickers start when the clock was never set.
+const START: Moment = Moment { date: Date { year: 2024, month: 1, day: 1 }, hour: 12, minute: 0, second: 0 };
+
+#[derive(Default)]
+pub struct Settings {
+    row: usize,
+    // Field being edited in the selected row.
+    field: Option<usize>,
+    // Date and time being picked, the clock is only set once done.
+    draft: Option<Moment>,
+    // Minute on screen, so the time shown follows the clock.
+    shown: Option<u8>,
+}
+
+fn fields(row: usize) -> usize {
+    match row {
+        DATE => 3,
+        TIME => 2,
+        _ => 1,
+    }
+}
+
+// `value` moved one step up or down, wrapping around within `min..=max`.
+fn step(value: u8, up: bool, min: u8, max: u8) -> u8 {
+    match (up, value) {
+        (true, value) if value >= max => min,
+        (true, value) => value + 1,
+        (false, value) if value <= min => max,
+        (false, value) => value - 1,
+    }
+}
+
+fn adjust(moment: &mut Moment, row: usize, field: usize, up: bool) {
+    match (row, field) {
+        (DATE, 0) => moment.date.year = if up { (moment.date.year + 1).min(calendar::LAST_YEAR) } else { (moment.date.year - 1).max(calendar::FIRST_YEAR) },
+        (DATE, 1) => moment.date.month = step(moment.date.month, up, 1, 12),
+        (DATE, _) => moment.date.day = step(moment.date.day, up, 1, calendar::days_in_month(moment.date.year, moment.date.month)),
+        (_, 0) => moment.hour = step(moment.hour, up, 0, 23),
+        _ => moment.minute = step(moment.minute, up, 0, 59),
+    }
+    moment.date.day = moment.date.day.min(calendar::days_in_month(moment.date.year, moment.date.month));
+}
+
+// Wraps the field being edited in brackets.
+fn push_field<T: core::fmt::Display>(label: &mut String<32>, value: T, width: usize, editing: bool) {
+    if editing {
+        let _ = write!(label, "[{:0width$}]", value, width = width);
+    } else {
+        let _ = write!(label, "{:0width$}", value, width = width);
+    }
+}
+
+pub fn draw_settings_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &Clock,
+    settings: &mut Settings,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let now = clock.now();
+    settings.shown = now.map(|now| now.minute);
+    let editing = |row: usize, field: usize| settings.row == row && settings.field == Some(field);
+
+    let mut date: String<32> = String::from("Date ");
+    let mut time: String<32> = String::from("Time ");
+    match settings.draft.or(now) {
+        Some(moment) => {
+            push_field(&mut date, moment.date.year, 4, editing(DATE, 0));
+            let _ = date.push('-');
+            push_field(&mut date, moment.date.month, 2, editing(DATE, 1));
+            let _ = date.push('-');
+            push_field(&mut date, moment.date.day, 2, editing(DATE, 2));
+
+            push_field(&mut time, moment.hour, 2, editing(TIME, 0));
+            let _ = time.push(':');
+            push_field(&mut time, moment.minute, 2, editing(TIME, 1));
+        }
+        None => {
+            let _ = date.push_str("not set");
+            let _ = time.push_str("not set");
+        }
+    }
+
+    let mut zone: String<32> = String::from("UTC offset ");
+    push_field(&mut zone, clock.offset, 0, editing(ZONE, 0));
+
+    let mut daylight: String<32> = String::from("DST ");
+    let _ = daylight.push_str(clock.dst.name());
+
+    let _ = display.clear(BinaryColor::On);
+    layout::draw(display, &Layout::List {
+        icon: &assets::OPTIONS,
+        title: "Settings",
+        items: &[&date, &time, &zone, &daylight].map(|row| row.as_str()),
+        selected: settings.row,
+        footer: if settings.field.is_some() { "[A] next" } else { "[A] change" },
+    });
+    layout::status(display, &clock::status(now.as_ref()));
+    let _ = display.update();
+}
+
+pub fn handle_settings_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    clock: &mut Clock,
+    settings: &mut Settings,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    match settings.field {
+        None if btn_up_pressed => settings.row = (settings.row + ROWS - 1) % ROWS,
+        None if btn_down_pressed => settings.row = (settings.row + 1) % ROWS,
+        None if btn_a_pressed => match settings.row {
+            DATE | TIME => {
+                settings.draft = Some(clock.now().unwrap_or(START));
+                settings.field = Some(0);
+            }
+            ZONE => settings.field = Some(0),
+            DAYLIGHT => {
+                let next = Dst::ALL.iter().position(|&dst| dst == clock.dst).map_or(0, |i| (i + 1) % Dst::ALL.len());
+                clock.dst = Dst::ALL[next];
+            }
+            _ => {}
+        },
+        None => {
+            // Keep the time on screen current.
+            if clock.now().map(|now| now.minute) == settings.shown {
+                return;
+            }
+        }
+        Some(field) if btn_up_pressed || btn_down_pressed => {
+            if settings.row == ZONE {
+                let delta = if btn_up_pressed { 30 } else { -30 };
+                clock.offset = UtcOffset((clock.offset.0 + delta).clamp(UtcOffset::MIN, UtcOffset::MAX));
+            } else if let Some(draft) = settings.draft.as_mut() {
+                adjust(draft, settings.row, field, btn_up_pressed);
+            }
+        }
+        Some(field) if btn_a_pressed => {
+            if field + 1 < fields(settings.row) {
+                settings.field = Some(field + 1);
+            } else {
+                if let Some(draft) = settings.draft.take() {
+                    clock.set(&Moment { second: 0, ..draft });
+                }
+                settings.field = None;
+            }
+        }
+        Some(_) => return,
+    }
+
+    draw_settings_screen(display, clock, settings);
+}
@@ -1,31 +1,34 @@
 use embedded_hal::blocking::spi::Write;
 use embedded_hal::digital::v2::{InputPin, OutputPin};
 
-// Graphics
-use embedded_graphics::{
-    pixelcolor::BinaryColor,
-    prelude::*,
-};
-use embedded_text::{
-    alignment::HorizontalAlignment,
-};
-use profont::*;
-use uc8151::WIDTH;
+use crate::assets;
+use crate::profile;
+use crate::layout::{self, Layout};
 
-use crate::draw;
-
-pub fn draw_socials_screen<SPI, CS, DC, BUSY, RESET>(display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>) where
+pub fn draw_socials_screen<SPI, CS, DC, BUSY, RESET>(display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>, hidden: bool) where
     SPI: Write<u8>,
     CS: OutputPin,
     DC: OutputPin,
     BUSY: InputPin,
     RESET: OutputPin,
 {
-    // Draw Initial Screen
-    draw::draw_image(display, include_bytes!("../../assets/qr.bmp"), 0, 0);
-    draw::draw_textbox(display, "Socials", PROFONT_18_POINT, BinaryColor::Off, HorizontalAlignment::Left, 130, 5, (WIDTH - 130), 0);
-    draw::draw_textbox(display, "Discord: @lynix.ca\nTelegram: @cyberlynix", PROFONT_14_POINT, BinaryColor::Off, HorizontalAlignment::Left, 130, 37, (WIDTH - 130), 0);
+    // In privacy mode nothing shows until it's revealed, see `programs::privacy`
+    if hidden {
+        layout::draw(display, &Layout::App {
+            icon: &assets::LOCK,
+            title: "Socials",
+            body: "Hidden.\nPress Up and Down together to show.",
+        });
+        let _ = display.update();
+        return;
+    }
 
+    // Draw Initial Screen
+    layout::draw(display, &Layout::Qr {
+        code: profile::SOCIALS_QR,
+        heading: "Socials",
+        caption: profile::SOCIALS,
+    });
 
     let _ = display.update();
 }
\ No newline at end of file
@@ -0,0 +1,246 @@
+// Workbench: classical ciphers and encodings chained into a recipe, for CTF warmups.
+//
+// Rows are the text, a key, the recipe and "Run". Text and key are typed with the button keyboard.
+// A on the recipe row opens a picker: Up/Down choose a step and A adds it to the end, steps that
+// take a key get the key row's text. "del" in the picker removes the last step. Run shows the
+// result, as hex if it isn't printable, and any button goes back.
+//
+//   bake rot13|to-base64 some text    sets the recipe and text, prints and shows the result
+//
+// Steps and recipe syntax are in `badge_crypto::cipher`.
+
+use core::fmt::Write as FmtWrite;
+
+use badge_crypto::cipher::{self, STEPS};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use heapless::String;
+use profont::*;
+use uc8151::WIDTH;
+
+use crate::assets;
+use crate::draw;
+use crate::entry::Entry;
+use crate::layout::{self, Layout};
+use crate::usb;
+
+const TEXT: usize = 0;
+const KEY: usize = 1;
+const RECIPE: usize = 2;
+const RUN: usize = 3;
+const ROWS: usize = 4;
+
+// Largest intermediate result.
+const BUFFER: usize = 256;
+
+// Longest part of the recipe shown on its row.
+const RECIPE_TAIL: usize = 18;
+
+#[derive(Default)]
+pub struct Workbench {
+    text: Entry<96>,
+    key: Entry<32>,
+    recipe: String<96>,
+    row: usize,
+    typing: bool,
+    // Step showing in the recipe picker, `STEPS.len()` is "del".
+    picking: Option<usize>,
+    showing: bool,
+    // Changed over serial, the screen needs redrawing.
+    stale: bool,
+}
+
+impl Workbench {
+    fn add_step(&mut self, step: usize) {
+        let Some(&name) = STEPS.get(step) else {
+            // "del": drop the last step.
+            let end = self.recipe.rfind('|').unwrap_or(0);
+            self.recipe.truncate(end);
+            return;
+        };
+
+        let mut recipe = self.recipe.clone();
+        let separator = if recipe.is_empty() { "" } else { "|" };
+        let key = if name.ends_with(':') { self.key.text.as_str() } else { "" };
+        // Leave the recipe alone if the step doesn't fit.
+        if write!(recipe, "{}{}{}", separator, name, key).is_ok() {
+            self.recipe = recipe;
+        }
+    }
+
+    /// Handles `bake RECIPE TEXT` typed on the serial console, `line` is the whole line.
+    pub fn command(&mut self, line: &str) {
+        let mut parts = line.trim_start().splitn(3, ' ');
+        let _ = parts.next();
+        let (Some(recipe), text) = (parts.next(), parts.next().unwrap_or("")) else {
+            usb::write(b"usage: bake STEP|STEP|... TEXT\r\n");
+            return;
+        };
+        let (Ok(recipe), Ok(text)) = (recipe.parse(), text.parse()) else {
+            usb::write(b"recipes and text are limited to 96 characters\r\n");
+            return;
+        };
+
+        self.recipe = recipe;
+        self.text.text = text;
+        self.typing = false;
+        self.picking = None;
+        self.showing = true;
+        self.stale = true;
+
+        let mut buffers = [[0u8; BUFFER]; 2];
+        match cipher::bake(&self.recipe, self.text.text.as_bytes(), &mut buffers) {
+            Ok(output) => print_output(output),
+            Err(e) => {
+                let mut message: String<48> = String::new();
+                let _ = write!(message, "{}", e);
+                usb::write(message.as_bytes());
+            }
+        }
+        usb::write(b"\r\n");
+    }
+}
+
+fn is_printable(output: &[u8]) -> bool {
+    output.iter().all(|byte| (0x20..0x7f).contains(byte))
+}
+
+fn print_output(output: &[u8]) {
+    if is_printable(output) {
+        usb::write(output);
+        return;
+    }
+    for byte in output {
+        let mut hex: String<2> = String::new();
+        let _ = write!(hex, "{:02x}", byte);
+        usb::write(hex.as_bytes());
+    }
+}
+
+fn draw_output<D>(display: &mut D, recipe: &str, text: &str) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let top = layout::header(display, &assets::APP, "Output");
+    let mut buffers = [[0u8; BUFFER]; 2];
+
+    // Only as much as fits on screen.
+    let mut shown: String<160> = String::new();
+    match cipher::bake(recipe, text.as_bytes(), &mut buffers) {
+        Ok(output) if is_printable(output) => {
+            let _ = shown.push_str(core::str::from_utf8(output).unwrap_or(""));
+        }
+        Ok(output) => {
+            let _ = shown.push_str("hex ");
+            for byte in output {
+                if write!(shown, "{:02x}", byte).is_err() {
+                    break;
+                }
+            }
+        }
+        Err(e) => {
+            let _ = write!(shown, "Error: {}", e);
+        }
+    }
+    draw::draw_textbox(display, &shown, PROFONT_12_POINT, BinaryColor::Off, HorizontalAlignment::Left, 0, top, WIDTH, 0);
+}
+
+pub fn draw_workbench_screen<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    workbench: &mut Workbench,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    workbench.stale = false;
+    let _ = display.clear(BinaryColor::On);
+
+    if workbench.showing {
+        draw_output(display, &workbench.recipe, &workbench.text.text);
+    } else {
+        let typing = |row| workbench.typing && workbench.row == row;
+
+        let mut text: String<48> = String::from("Text ");
+        let _ = text.push_str(&workbench.text.label(typing(TEXT)));
+        let mut key: String<48> = String::from("Key ");
+        let _ = key.push_str(&workbench.key.label(typing(KEY)));
+
+        let mut recipe: String<64> = String::from("Recipe ");
+        let tail = workbench.recipe.char_indices().rev().nth(RECIPE_TAIL - 1).map_or(0, |(i, _)| i);
+        if tail > 0 {
+            let _ = recipe.push_str("..");
+        }
+        let _ = recipe.push_str(&workbench.recipe[tail..]);
+        if let Some(step) = workbench.picking {
+            let _ = write!(recipe, "[{}]", STEPS.get(step).copied().unwrap_or("del"));
+        }
+
+        layout::draw(display, &Layout::List {
+            icon: &assets::APP,
+            title: "Workbench",
+            items: &[text.as_str(), key.as_str(), recipe.as_str(), "Run"],
+            selected: workbench.row,
+            footer: match (workbench.typing, workbench.picking) {
+                (true, _) => "[A] type",
+                (_, Some(_)) => "[A] add step",
+                _ => "[A] change",
+            },
+        });
+    }
+    let _ = display.update();
+}
+
+pub fn handle_workbench_program<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    workbench: &mut Workbench,
+    btn_up_pressed: bool,
+    btn_down_pressed: bool,
+    btn_a_pressed: bool,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    let pressed = btn_up_pressed || btn_down_pressed || btn_a_pressed;
+    let choices = STEPS.len() + 1;
+
+    if workbench.showing && pressed {
+        workbench.showing = false;
+    } else if workbench.typing && pressed {
+        let entry_done = match workbench.row {
+            TEXT => workbench.text.handle(btn_up_pressed, btn_down_pressed, btn_a_pressed),
+            _ => workbench.key.handle(btn_up_pressed, btn_down_pressed, btn_a_pressed),
+        };
+        workbench.typing = !entry_done;
+    } else if let (Some(step), true) = (workbench.picking, pressed) {
+        if btn_up_pressed {
+            workbench.picking = Some((step + choices - 1) % choices);
+        } else if btn_down_pressed {
+            workbench.picking = Some((step + 1) % choices);
+        } else {
+            workbench.add_step(step);
+            workbench.picking = None;
+        }
+    } else if btn_up_pressed {
+        workbench.row = (workbench.row + ROWS - 1) % ROWS;
+    } else if btn_down_pressed {
+        workbench.row = (workbench.row + 1) % ROWS;
+    } else if btn_a_pressed {
+        match workbench.row {
+            TEXT | KEY => workbench.typing = true,
+            RECIPE => workbench.picking = Some(0),
+            RUN => workbench.showing = true,
+            _ => {}
+        }
+    } else if !workbench.stale {
+        return;
+    }
+
+    draw_workbench_screen(display, workbench);
+}
@@ -0,0 +1,201 @@
+// Schedule rules pick what "Lynix Badge" shows from the date and time of day.
+//
+// A rule is a few space separated conditions followed by what to show:
+//
+//   mon-fri 08:30-16:30 ccnb
+//   2024-08-09..2024-08-11 card
+//   sat,sun playlist
+//
+// Days are `mon`..`sun`, ranges (`mon-fri`, `fri-mon`), lists (`sat,sun`) or `daily`. A time
+// window ends just before its second time and may wrap past midnight. Date ranges are inclusive,
+// a single date is one day. A condition that isn't given always matches.
+//
+// Rules come from the profile and are checked in order; the first match wins. `playlist` is the
+// rotation, which also runs when nothing matches or the clock hasn't been set.
+
+use crate::calendar::{self, Date, Moment};
+use crate::slide::Slide;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Show {
+    Playlist,
+    Slide(Slide),
+}
+
+pub struct Rule {
+    // Bit 0 is Monday.
+    days: u8,
+    // Minutes of the day, start inclusive, end exclusive.
+    window: Option<(u16, u16)>,
+    dates: Option<(Date, Date)>,
+    pub show: Show,
+}
+
+impl Rule {
+    pub fn parse(line: &str) -> Result<Rule, &'static str> {
+        let line = line.trim();
+        let (conditions, target) = line.rsplit_once(char::is_whitespace).unwrap_or(("", line));
+        let show = match target {
+            "" => return Err("empty rule"),
+            "playlist" => Show::Playlist,
+            _ => Show::Slide(Slide::parse(target).ok_or("a rule ends with card, ccnb, socials, info, screenN or playlist")?),
+        };
+
+        let mut rule = Rule { days: 0x7f, window: None, dates: None, show };
+        for word in conditions.split_whitespace() {
+            if word.contains(':') {
+                let (from, until) = word.split_once('-').ok_or("time windows look like 08:30-16:30")?;
+                let minutes = |time| calendar::parse_time(time).map(|(hour, minute, _)| hour as u16 * 60 + minute as u16);
+                rule.window = Some((minutes(from).ok_or("bad start time")?, minutes(until).ok_or("bad end time")?));
+            } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+                let (from, until) = word.split_once("..").unwrap_or((word, word));
+                let from = Date::parse(from).ok_or("dates look like 2024-08-09")?;
+                let until = Date::parse(until).ok_or("dates look like 2024-08-09")?;
+                if until < from {
+                    return Err("date range ends before it starts");
+                }
+                rule.dates = Some((from, until));
+            } else {
+                rule.days = parse_days(word).ok_or("days look like mon-fri, sat,sun or daily")?;
+            }
+        }
+        Ok(rule)
+    }
+
+    pub fn matches(&self, now: &Moment) -> bool {
+        let day = self.days & (1 << now.date.weekday()) != 0;
+        let minute = now.minute_of_day();
+        let time = match self.window {
+            None => true,
+            Some((from, until)) if from <= until => (from..until).contains(&minute),
+            Some((from, until)) => minute >= from || minute < until,
+        };
+        let date = match self.dates {
+            None => true,
+            Some((from, until)) => (from..=until).contains(&now.date),
+        };
+        day && time && date
+    }
+}
+
+fn parse_days(word: &str) -> Option<u8> {
+    if word == "daily" {
+        return Some(0x7f);
+    }
+    let day = |name: &str| calendar::WEEKDAYS.iter().position(|&day| day == name);
+
+    let mut days = 0u8;
+    for part in word.split(',') {
+        match part.split_once('-') {
+            Some((from, until)) => {
+                let (mut day, until) = (day(from)?, day(until)?);
+                loop {
+                    days |= 1 << day;
+                    if day == until {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days |= 1 << day(part)?,
+        }
+    }
+    Some(days)
+}
+
+/// What to show at `now`, from the first rule that matches it.
+pub fn active(rules: &[&str], now: Option<&Moment>) -> Show {
+    let Some(now) = now else {
+        return Show::Playlist;
+    };
+    rules
+        .iter()
+        .filter_map(|line| Rule::parse(line).ok())
+        .find(|rule| rule.matches(now))
+        .map_or(Show::Playlist, |rule| rule.show)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> Moment {
+        let (date, time) = text.split_once(' ').unwrap();
+        let (hour, minute, second) = calendar::parse_time(time).unwrap();
+        Moment { date: Date::parse(date).unwrap(), hour, minute, second }
+    }
+
+    fn matches(rule: &str, now: &str) -> bool {
+        Rule::parse(rule).unwrap().matches(&at(now))
+    }
+
+    #[test]
+    fn windows_end_before_their_second_time() {
+        // 2024-08-09 is a Friday.
+        let rule = "08:30-16:30 ccnb";
+        assert!(!matches(rule, "2024-08-09 08:29:59"));
+        assert!(matches(rule, "2024-08-09 08:30"));
+        assert!(matches(rule, "2024-08-09 16:29:59"));
+        assert!(!matches(rule, "2024-08-09 16:30"));
+
+        let rule = "00:00-08:00 ccnb";
+        assert!(matches(rule, "2024-08-09 00:00"));
+        assert!(!matches(rule, "2024-08-09 08:00"));
+        assert!(!matches(rule, "2024-08-09 23:59:59"));
+    }
+
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let rule = "22:00-02:00 socials";
+        assert!(!matches(rule, "2024-08-09 21:59:59"));
+        assert!(matches(rule, "2024-08-09 22:00"));
+        assert!(matches(rule, "2024-08-09 23:59:59"));
+        assert!(matches(rule, "2024-08-10 00:00"));
+        assert!(matches(rule, "2024-08-10 01:59:59"));
+        assert!(!matches(rule, "2024-08-10 02:00"));
+    }
+
+    #[test]
+    fn days_and_dates() {
+        // Friday to Monday wraps through the weekend.
+        let rule = "fri-mon card";
+        assert!(!matches(rule, "2024-08-08 12:00"));
+        assert!(matches(rule, "2024-08-09 12:00"));
+        assert!(matches(rule, "2024-08-11 12:00"));
+        assert!(matches(rule, "2024-08-12 12:00"));
+        assert!(!matches(rule, "2024-08-13 12:00"));
+
+        // Date ranges take in the whole of their last day, and only that.
+        let rule = "2024-08-09..2024-08-11 card";
+        assert!(!matches(rule, "2024-08-08 23:59:59"));
+        assert!(matches(rule, "2024-08-09 00:00"));
+        assert!(matches(rule, "2024-08-11 23:59:59"));
+        assert!(!matches(rule, "2024-08-12 00:00"));
+
+        // Over the new year.
+        let rule = "2024-12-31..2025-01-01 sat,sun,tue info";
+        assert!(matches(rule, "2024-12-31 23:59:59"));
+        assert!(!matches(rule, "2025-01-01 00:00"));
+    }
+
+    #[test]
+    fn first_match_wins() {
+        let rules = ["sat,sun playlist", "mon-fri 08:30-16:30 ccnb", "daily card"];
+        assert!(active(&rules, Some(&at("2024-08-09 09:00"))) == Show::Slide(Slide::Ccnb));
+        assert!(active(&rules, Some(&at("2024-08-09 17:00"))) == Show::Slide(Slide::Card));
+        assert!(active(&rules, Some(&at("2024-08-10 09:00"))) == Show::Playlist);
+        // No clock, no rules.
+        assert!(active(&rules, None) == Show::Playlist);
+        assert!(active(&[], Some(&at("2024-08-09 09:00"))) == Show::Playlist);
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert!(Rule::parse("").is_err());
+        assert!(Rule::parse("mon-fri lobby").is_err());
+        assert!(Rule::parse("08:30 ccnb").is_err());
+        assert!(Rule::parse("24:00-25:00 ccnb").is_err());
+        assert!(Rule::parse("2024-08-11..2024-08-09 card").is_err());
+        assert!(Rule::parse("someday card").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+// Badge screens stored in flash.
+//
+// `tools/screenc` compiles a text description of one or more screens into an LXSC blob (the
+// format is in `crates/badge-screens`) and wraps it in a UF2 for the reserved region at the end
+// of flash, so the same firmware can show a different person's badge. When nothing valid is
+// stored the built-in screens are used.
+
+use badge_screens::{Align, Element, Fill, Screen, Screens};
+use embedded_graphics::{
+    image::Image,
+    mono_font::MonoFont,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyleBuilder, Rectangle},
+};
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_text::alignment::HorizontalAlignment;
+use profont::*;
+
+use crate::assets;
+use crate::barcode::{self, Symbology};
+use crate::draw;
+use crate::gray::{self, GrayFrame};
+
+// Must match SCREENS in memory.x.
+const REGION_ADDRESS: usize = 0x101F_0000;
+const REGION_SIZE: usize = 64 * 1024;
+
+/// The screens in the reserved flash region, if a valid set has been flashed.
+pub fn stored() -> Option<Screens<'static>> {
+    // SAFETY: flash is memory mapped through XIP and the region is never written by the firmware.
+    let region = unsafe { core::slice::from_raw_parts(REGION_ADDRESS as *const u8, REGION_SIZE) };
+    badge_screens::parse(region)
+}
+
+// Screens with a grayscale photo go through the gray refresh.
+fn has_gray(screen: &Screen) -> bool {
+    screen.elements().any(|element| match element {
+        Ok(Element::Image { name, .. }) => assets::find(name).is_some_and(|entry| entry.gray.is_some()),
+        _ => false,
+    })
+}
+
+// `parse` only lets through sizes in `badge_screens::FONTS`.
+fn font(points: u8) -> MonoFont<'static> {
+    match points {
+        7 => PROFONT_7_POINT,
+        9 => PROFONT_9_POINT,
+        10 => PROFONT_10_POINT,
+        12 => PROFONT_12_POINT,
+        14 => PROFONT_14_POINT,
+        18 => PROFONT_18_POINT,
+        _ => PROFONT_24_POINT,
+    }
+}
+
+/// Draws every element of `screen` 1-bit, in order.
+pub fn draw<D>(display: &mut D, screen: &Screen) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    for element in screen.elements().flatten() {
+        match element {
+            Element::Image { x, y, name } => {
+                if let Some(entry) = assets::find(name) {
+                    draw::draw_image(display, entry.image, x as i32, y as i32);
+                }
+            }
+            Element::Text { x, y, width, font: points, align, text } => {
+                let align = match align {
+                    Align::Left => HorizontalAlignment::Left,
+                    Align::Center => HorizontalAlignment::Center,
+                    Align::Right => HorizontalAlignment::Right,
+                };
+                draw::draw_textbox(display, text, font(points), BinaryColor::Off, align, x as i32, y as i32, width as u32, 0);
+            }
+            Element::Qr { x, y, scale, payload } => {
+                if let Ok(symbol) = barcode::encode(Symbology::Qr, payload) {
+                    barcode::draw_symbol(display, &symbol, x as i32, y as i32, scale as u32, scale as u32);
+                }
+            }
+            Element::Rect { x, y, width, height, fill, stroke } => {
+                let mut style = PrimitiveStyleBuilder::new();
+                match fill {
+                    Fill::None => {}
+                    Fill::Black => style = style.fill_color(BinaryColor::Off),
+                    Fill::White => style = style.fill_color(BinaryColor::On),
+                }
+                if stroke > 0 {
+                    style = style.stroke_color(BinaryColor::Off).stroke_width(stroke as u32);
+                }
+                let _ = Rectangle::new(Point::new(x as i32, y as i32), Size::new(width as u32, height as u32))
+                    .into_styled(style.build())
+                    .draw(display);
+            }
+        }
+    }
+}
+
+/// Draws `screen` and refreshes the panel, in grayscale if it has a grayscale photo on it.
+pub fn show<SPI, CS, DC, BUSY, RESET>(
+    display: &mut uc8151::Uc8151<SPI, CS, DC, BUSY, RESET>,
+    delay: &mut cortex_m::delay::Delay,
+    screen: &Screen,
+) where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    DC: OutputPin,
+    BUSY: InputPin,
+    RESET: OutputPin,
+{
+    if !has_gray(screen) {
+        draw(display, screen);
+        let _ = display.update();
+        return;
+    }
+
+    let mut frame = GrayFrame::new();
+    draw(&mut frame.binary(), screen);
+    for element in screen.elements().flatten() {
+        if let Element::Image { x, y, name } = element {
+            if let Some(gray) = assets::find(name).and_then(|entry| entry.gray) {
+                let _ = Image::new(gray, Point::new(x as i32, y as i32)).draw(&mut frame);
+            }
+        }
+    }
+    gray::show(display, delay, &frame);
+}
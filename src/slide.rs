@@ -0,0 +1,38 @@
+// The badge screens the playlist and the schedule can show, by the names used in profiles and on
+// the serial console.
+
+use core::fmt;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Slide {
+    Card,
+    Ccnb,
+    Socials,
+    Info,
+    /// A screen flashed with tools/screenc, by index.
+    Stored(u8),
+}
+
+impl Slide {
+    pub fn parse(name: &str) -> Option<Slide> {
+        Some(match name {
+            "card" => Slide::Card,
+            "ccnb" => Slide::Ccnb,
+            "socials" => Slide::Socials,
+            "info" => Slide::Info,
+            _ => Slide::Stored(name.strip_prefix("screen")?.parse().ok()?),
+        })
+    }
+}
+
+impl fmt::Display for Slide {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Slide::Card => f.write_str("card"),
+            Slide::Ccnb => f.write_str("ccnb"),
+            Slide::Socials => f.write_str("socials"),
+            Slide::Info => f.write_str("info"),
+            Slide::Stored(index) => write!(f, "screen{}", index),
+        }
+    }
+}
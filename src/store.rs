@@ -0,0 +1,109 @@
+// Data the firmware writes to flash itself, so it survives power off.
+//
+// The STORE region in memory.x is split into 4K sectors, one per kind of data. A sector holds a
+// single record and saving rewrites the whole sector. All integers are little-endian.
+//
+//   header  "LXST"  sector: u8  version: u8 (1)  length: u16  crc32: u32
+//   body    `length` bytes, CRC-32 (IEEE) in the header covers them
+//
+// A sector that was never written, or was cut off by power loss mid-write, reads as empty.
+// `erased` tells the first apart from the second for data that mustn't be silently replaced.
+
+use badge_crypto::hash::{self, Algorithm};
+
+// Must match STORE in memory.x.
+const REGION_ADDRESS: usize = 0x101E_0000;
+const FLASH_ADDRESS: usize = 0x1000_0000;
+const SECTOR_SIZE: usize = 4096;
+
+const MAGIC: &[u8; 4] = b"LXST";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 12;
+
+/// Longest record a sector holds.
+pub const MAX_RECORD: usize = SECTOR_SIZE - HEADER_LEN;
+
+#[derive(Clone, Copy)]
+pub enum Sector {
+    /// Authenticator secrets and PIN, see `programs::authenticator`.
+    Otp = 0,
+    /// The badge's Ed25519 seed, see `programs::identity`.
+    Identity = 1,
+    /// The encrypted notes and wrong PIN count, see `programs::notes`.
+    Vault = 2,
+    /// Privacy mode settings, see `programs::privacy`.
+    Privacy = 3,
+    /// The security key's secrets, see `programs::security_key`.
+    SecurityKey = 4,
+    /// The security key's signature counter when even, written in turn with the next sector.
+    SecurityKeyCounterEven = 5,
+    /// The security key's signature counter when odd.
+    SecurityKeyCounterOdd = 6,
+}
+
+fn address(sector: Sector) -> usize {
+    REGION_ADDRESS + sector as usize * SECTOR_SIZE
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let output = hash::hash(Algorithm::Crc32, data);
+    let bytes = output.as_bytes();
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn contents(sector: Sector) -> &'static [u8] {
+    // SAFETY: flash is memory mapped through XIP. The sector is only written by `save`, which
+    // can't run while the returned slice is in use because both need the main loop.
+    unsafe { core::slice::from_raw_parts(address(sector) as *const u8, SECTOR_SIZE) }
+}
+
+/// The record in `sector`, if one has been saved.
+pub fn load(sector: Sector) -> Option<&'static [u8]> {
+    let data = contents(sector);
+    if &data[..4] != MAGIC || data[4] != sector as u8 || data[5] != VERSION {
+        return None;
+    }
+    let length = u16::from_le_bytes([data[6], data[7]]) as usize;
+    let crc = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
+
+    let body = data.get(HEADER_LEN..HEADER_LEN + length)?;
+    (crc32(body) == crc).then_some(body)
+}
+
+/// Whether `sector` has never been written, as opposed to holding a damaged record.
+pub fn erased(sector: Sector) -> bool {
+    contents(sector).iter().all(|&byte| byte == 0xff)
+}
+
+/// The flash chip's 64-bit unique ID, different on every badge.
+pub fn unique_id() -> [u8; 8] {
+    let mut id = [0; 8];
+    // Reading it takes the flash out of XIP mode, the same as a write.
+    cortex_m::interrupt::free(|_| unsafe {
+        rp2040_flash::flash::flash_unique_id(&mut id, true);
+    });
+    id
+}
+
+/// Replaces the record in `sector`. Fails if `body` is longer than `MAX_RECORD`.
+pub fn save(sector: Sector, body: &[u8]) -> Result<(), ()> {
+    if body.len() > MAX_RECORD {
+        return Err(());
+    }
+
+    let mut data = [0xff; SECTOR_SIZE];
+    data[..4].copy_from_slice(MAGIC);
+    data[4] = sector as u8;
+    data[5] = VERSION;
+    data[6..8].copy_from_slice(&(body.len() as u16).to_le_bytes());
+    data[8..12].copy_from_slice(&crc32(body).to_le_bytes());
+    data[HEADER_LEN..HEADER_LEN + body.len()].copy_from_slice(body);
+
+    // Code runs from flash, so nothing may touch it while it's erased and programmed. The
+    // routines run from RAM and flush the XIP cache afterwards.
+    let offset = (address(sector) - FLASH_ADDRESS) as u32;
+    cortex_m::interrupt::free(|_| unsafe {
+        rp2040_flash::flash::flash_range_erase_and_program(offset, &data, true);
+    });
+    Ok(())
+}
@@ -1,42 +1,172 @@
+use core::cell::RefCell;
+
+use cortex_m::interrupt::Mutex;
+use heapless::{Deque, String};
 use rp2040_hal::pac::interrupt;
-use crate::ProgramState;
+
+// Longest command line we accept, room for an `otp add` with a long otpauth:// URI. Longer lines
+// are dropped with a message.
+pub const LINE_LENGTH: usize = 512;
+
+/// HID reports of a FIDO security key: 64 bytes each way, see `badge_crypto::ctaphid`.
+pub const FIDO_REPORT_DESCRIPTOR: &[u8] = &[
+    0x06, 0xd0, 0xf1, // Usage Page (FIDO Alliance)
+    0x09, 0x01, // Usage (U2F Authenticator Device)
+    0xa1, 0x01, // Collection (Application)
+    0x09, 0x20, //   Usage (Input Report Data)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x26, 0xff, 0x00, //   Logical Maximum (255)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x40, //   Report Count (64)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x09, 0x21, //   Usage (Output Report Data)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x26, 0xff, 0x00, //   Logical Maximum (255)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x40, //   Report Count (64)
+    0x91, 0x02, //   Output (Data, Variable, Absolute)
+    0xc0, // End Collection
+];
+
+pub type Packet = [u8; 64];
+
+/// Complete lines received over serial, waiting for the main loop.
+static COMMANDS: Mutex<RefCell<Deque<String<LINE_LENGTH>, 4>>> = Mutex::new(RefCell::new(Deque::new()));
+
+/// Security key packets received over HID, waiting for the main loop.
+static PACKETS: Mutex<RefCell<Deque<Packet, 8>>> = Mutex::new(RefCell::new(Deque::new()));
+
+/// Next command line typed on the serial console, without the line ending.
+pub fn take_command() -> Option<String<LINE_LENGTH>> {
+    cortex_m::interrupt::free(|cs| COMMANDS.borrow(cs).borrow_mut().pop_front())
+}
+
+/// Sends `bytes` to the serial console. Gives up on whatever doesn't fit if nobody is reading,
+/// but keeps going for as long as the host takes data, so long outputs arrive whole.
+pub fn write(bytes: &[u8]) {
+    let mut rest = bytes;
+    let mut attempts = 0;
+    while !rest.is_empty() && attempts < 1000 {
+        let written = cortex_m::interrupt::free(|_| {
+            // SAFETY: the interrupt is the only other user and it can't run in here.
+            match unsafe { crate::USB_SERIAL.as_mut() } {
+                Some(serial) => serial.write(rest).unwrap_or(0),
+                None => rest.len(),
+            }
+        });
+        rest = &rest[written..];
+        attempts = if written > 0 { 0 } else { attempts + 1 };
+    }
+}
+
+/// Next packet the host sent to the security key.
+pub fn take_packet() -> Option<Packet> {
+    cortex_m::interrupt::free(|cs| PACKETS.borrow(cs).borrow_mut().pop_front())
+}
+
+/// Sends a packet from the security key, waiting a while for the host to take the last one,
+/// which it asks for every few milliseconds.
+pub fn send_packet(packet: &Packet) {
+    for _ in 0..100_000 {
+        let sent = cortex_m::interrupt::free(|_| {
+            // SAFETY: the interrupt is the only other user and it can't run in here.
+            match unsafe { crate::USB_HID.as_mut() } {
+                Some(hid) => hid.push_raw_input(packet).is_ok(),
+                None => true,
+            }
+        });
+        if sent {
+            return;
+        }
+    }
+}
+
+/// Sends a keyboard report, waiting a while for the host to take the last one. Returns false if
+/// it never does, when USB isn't plugged in or the host doesn't use the keyboard.
+#[cfg(feature = "keyboard-macros")]
+pub fn send_key(modifiers: u8, key: u8) -> bool {
+    use usbd_hid::descriptor::KeyboardReport;
+
+    let report = KeyboardReport { modifier: modifiers, reserved: 0, leds: 0, keycodes: [key, 0, 0, 0, 0, 0] };
+    for _ in 0..100_000 {
+        let sent = cortex_m::interrupt::free(|_| {
+            // SAFETY: the interrupt is the only other user and it can't run in here.
+            match unsafe { crate::USB_KEYBOARD.as_mut() } {
+                Some(keyboard) => keyboard.push_input(&report).is_ok(),
+                None => false,
+            }
+        });
+        if sent {
+            return true;
+        }
+    }
+    false
+}
 
 #[allow(non_snake_case)]
 #[interrupt]
 unsafe fn USBCTRL_IRQ() {
     use core::sync::atomic::{AtomicBool, Ordering};
     static INIT_TEXT: AtomicBool = AtomicBool::new(false);
+    static mut LINE: String<LINE_LENGTH> = String::new();
+    static mut OVERFLOW: bool = false;
 
     // Grab the global objects. This is OK as we only access them under interrupt.
     let usb_dev = crate::USB_DEVICE.as_mut().unwrap();
-     let serial = crate::USB_SERIAL.as_mut().unwrap();
+    let serial = crate::USB_SERIAL.as_mut().unwrap();
+    let hid = crate::USB_HID.as_mut().unwrap();
 
     if !INIT_TEXT.load(Ordering::Relaxed) {
         INIT_TEXT.store(true, Ordering::Relaxed);
         let _ = serial.write(b"LYNIXFW READY\r\n");
     }
 
-// Poll the USB driver with all of our supported USB Classes
-    if usb_dev.poll(&mut [serial]) {
+    // Poll the USB driver with all of our supported USB Classes
+    #[cfg(not(feature = "keyboard-macros"))]
+    let polled = usb_dev.poll(&mut [serial, hid]);
+    #[cfg(feature = "keyboard-macros")]
+    let polled = {
+        let keyboard = crate::USB_KEYBOARD.as_mut().unwrap();
+        let polled = usb_dev.poll(&mut [serial, hid, keyboard]);
+        // Caps Lock and the other lights, which the badge doesn't have.
+        let _ = keyboard.pull_raw_output(&mut [0; 8]);
+        polled
+    };
+    if polled {
+        let mut packet = [0u8; 64];
+        if let Ok(64) = hid.pull_raw_output(&mut packet) {
+            cortex_m::interrupt::free(|cs| {
+                let _ = PACKETS.borrow(cs).borrow_mut().push_back(packet);
+            });
+        }
+
         let mut buf = [0u8; 64];
-        match serial.read(&mut buf) {
-            Err(_e) => {
-                // Do nothing or handle error
-            }
-            Ok(0) => {
-                // Do nothing or handle empty data
-            }
-            Ok(cmd) => {
-                // Handle received data
-                let mut wr_ptr = &buf[..cmd];
-                serial.write(wr_ptr).unwrap();
-
-                if let Ok(received_text) = core::str::from_utf8(wr_ptr) {
-                    if received_text == "test\n" {
-                        serial.write("lynix is cute.".as_bytes()).unwrap();
+        if let Ok(count) = serial.read(&mut buf) {
+            // Echo so terminals show what's typed.
+            let _ = serial.write(&buf[..count]);
+
+            for &byte in &buf[..count] {
+                match byte {
+                    b'\r' | b'\n' => {
+                        if *OVERFLOW {
+                            let _ = serial.write(b"\r\nline too long\r\n");
+                        } else if !LINE.is_empty() {
+                            let _ = serial.write(b"\r\n");
+                            cortex_m::interrupt::free(|cs| {
+                                let _ = COMMANDS.borrow(cs).borrow_mut().push_back(LINE.clone());
+                            });
+                        }
+                        LINE.clear();
+                        *OVERFLOW = false;
+                    }
+                    byte if byte.is_ascii() => {
+                        if LINE.push(byte as char).is_err() {
+                            *OVERFLOW = true;
+                        }
                     }
+                    _ => {}
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
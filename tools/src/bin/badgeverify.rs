@@ -0,0 +1,91 @@
+//! Checks that a message was signed by a particular badge, to prove ownership at an event.
+//!
+//! ```text
+//! cargo run --bin badgeverify -- challenge
+//! cargo run --bin badgeverify -- verify --key PUBLIC_KEY --signature SIGNATURE --hex CHALLENGE
+//! cargo run --bin badgeverify -- verify --key PUBLIC_KEY --signature SIGNATURE "some message"
+//! ```
+//!
+//! The owner gets the public key from `identity` on the badge's serial console (or its Identity
+//! screen's QR code) and signs with `sign-hex CHALLENGE` or `sign MESSAGE`. A fresh challenge
+//! for every check stops an old signature from being replayed. The fingerprint printed with a
+//! good signature matches the one on the badge's Identity screen.
+
+use std::fs::File;
+use std::io::Read;
+
+use badge_crypto::sign::{self, Fingerprint, PUBLIC_KEY_LEN, SIGNATURE_LEN};
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(about = "Check signatures made with a badge's identity key")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a random 32-byte challenge for the badge to sign with `sign-hex`.
+    Challenge,
+    /// Check a signature from `sign` or `sign-hex`.
+    Verify {
+        /// The badge's public key in hex, from `identity`.
+        #[arg(long)]
+        key: String,
+        /// The signature in hex.
+        #[arg(long)]
+        signature: String,
+        /// The message is hex, as signed with `sign-hex`.
+        #[arg(long)]
+        hex: bool,
+        /// What was signed.
+        message: String,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("badgeverify: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    match &args.command {
+        Command::Challenge => {
+            let mut challenge = [0u8; 32];
+            File::open("/dev/urandom")
+                .and_then(|mut random| random.read_exact(&mut challenge))
+                .map_err(|e| format!("/dev/urandom: {}", e))?;
+            println!("{}", to_hex(&challenge));
+        }
+        Command::Verify { key, signature, hex, message } => {
+            let key: [u8; PUBLIC_KEY_LEN] = from_hex(key)?.try_into().map_err(|_| "the public key is 32 bytes")?;
+            let signature: [u8; SIGNATURE_LEN] = from_hex(signature)?.try_into().map_err(|_| "signatures are 64 bytes")?;
+            let message = if *hex { from_hex(message)? } else { message.as_bytes().to_vec() };
+
+            if !sign::verify(&key, &message, &signature) {
+                return Err("bad signature".into());
+            }
+            println!("good signature from badge {}", Fingerprint::of(&key));
+        }
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok().filter(|pair| pair.len() == 2);
+            pair.and_then(|pair| u8::from_str_radix(pair, 16).ok()).ok_or_else(|| format!("not hex: {}", text))
+        })
+        .collect()
+}
@@ -0,0 +1,434 @@
+//! Converts a photo or logo into a 1-bit BMP for `assets/`.
+//!
+//! The image is cropped or padded to the target box, tone-adjusted, then
+//! dithered down to black and white. `--preview` writes an enlarged PNG of the
+//! result, and `--compare` writes one preview per dithering method so the
+//! best-looking one can be picked before committing the asset.
+//!
+//! ```text
+//! cargo run --bin img2badge -- photo.jpg -o ../assets/lynix.bmp --size 125x125 --dither atkinson --contrast 1.2
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, ValueEnum};
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, Luma};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Dither {
+    /// Hard cut at `--threshold`.
+    Threshold,
+    /// Ordered 8x8 Bayer matrix, a regular pattern that suits flat artwork.
+    Bayer,
+    /// Floyd–Steinberg error diffusion.
+    FloydSteinberg,
+    /// Atkinson error diffusion, higher contrast with less noise in the shadows.
+    Atkinson,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Fit {
+    /// Scale to cover the box and crop the overflow from the centre.
+    Crop,
+    /// Scale to fit inside the box and pad with white.
+    Pad,
+    /// Scale to the box exactly, ignoring aspect ratio.
+    Stretch,
+}
+
+#[derive(Parser)]
+#[command(about = "Convert PNG/JPEG images into 1-bit badge assets")]
+struct Args {
+    /// Source image (PNG, JPEG or BMP).
+    input: PathBuf,
+
+    /// Where to write the 1-bit BMP.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Target box in pixels, e.g. 125x125.
+    #[arg(long, value_parser = parse_size, default_value = "125x125")]
+    size: (u32, u32),
+
+    /// Crop the source to `x,y,w,h` before scaling.
+    #[arg(long, value_parser = parse_rect)]
+    crop: Option<(u32, u32, u32, u32)>,
+
+    /// How to fit the source into the target box.
+    #[arg(long, value_enum, default_value = "crop")]
+    fit: Fit,
+
+    #[arg(long, value_enum, default_value = "floyd-steinberg")]
+    dither: Dither,
+
+    /// Contrast multiplier around mid grey (1.0 leaves the image alone).
+    #[arg(long, default_value_t = 1.0)]
+    contrast: f32,
+
+    /// Brightness offset, -255 to 255.
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    brightness: f32,
+
+    /// Gamma correction applied before dithering, above 0.
+    #[arg(long, value_parser = parse_gamma, default_value_t = 1.0)]
+    gamma: f32,
+
+    /// Grey level at which `threshold` dithering switches to white.
+    #[arg(long, default_value_t = 128)]
+    threshold: u8,
+
+    /// Swap black and white.
+    #[arg(long)]
+    invert: bool,
+
+    /// Write an enlarged PNG preview of the result.
+    #[arg(long)]
+    preview: Option<PathBuf>,
+
+    /// Write a preview for every dithering method into this directory.
+    #[arg(long)]
+    compare: Option<PathBuf>,
+
+    /// Scale factor for previews.
+    #[arg(long, default_value_t = 4)]
+    zoom: u32,
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let (w, h) = s.split_once('x').ok_or("expected WxH")?;
+    let (w, h): (u32, u32) = (w.parse().map_err(|_| "bad width")?, h.parse().map_err(|_| "bad height")?);
+    if w == 0 || h == 0 {
+        return Err("width and height must be at least 1".into());
+    }
+    Ok((w, h))
+}
+
+fn parse_gamma(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(gamma) if gamma > 0.0 && gamma.is_finite() => Ok(gamma),
+        _ => Err("expected a number above 0".into()),
+    }
+}
+
+fn parse_rect(s: &str) -> Result<(u32, u32, u32, u32), String> {
+    let parts: Vec<u32> = s
+        .split(',')
+        .map(|p| p.trim().parse().map_err(|_| format!("bad number `{}`", p)))
+        .collect::<Result<_, _>>()?;
+    match parts[..] {
+        [_, _, 0, _] | [_, _, _, 0] => Err("crop width and height must be at least 1".into()),
+        [x, y, w, h] => Ok((x, y, w, h)),
+        _ => Err("expected x,y,w,h".into()),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("img2badge: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let source = image::open(&args.input).map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    let grey = prepare(source, args);
+
+    if let Some(dir) = &args.compare {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        for method in Dither::value_variants() {
+            let name = method.to_possible_value().unwrap().get_name().to_string();
+            let path = dir.join(format!("{}.png", name));
+            write_preview(&dither(&grey, *method, args.threshold), args.zoom, &path)?;
+            println!("wrote {}", path.display());
+        }
+    }
+
+    let bits = dither(&grey, args.dither, args.threshold);
+
+    if let Some(path) = &args.preview {
+        write_preview(&bits, args.zoom, path)?;
+        println!("wrote {}", path.display());
+    }
+
+    if let Some(path) = &args.output {
+        fs::write(path, encode_bmp(&bits)).map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!("wrote {}", path.display());
+        println!(
+            "manifest: {:<10} {:<14} {}x{}   x,y",
+            path.file_stem().unwrap().to_string_lossy().to_uppercase(),
+            path.file_name().unwrap().to_string_lossy(),
+            bits.width(),
+            bits.height()
+        );
+    }
+
+    if args.output.is_none() && args.preview.is_none() && args.compare.is_none() {
+        return Err("nothing to do, pass --output, --preview or --compare".into());
+    }
+    Ok(())
+}
+
+/// Crops, scales and tone-maps the source into a greyscale image of the target size.
+fn prepare(mut source: DynamicImage, args: &Args) -> GrayImage {
+    if let Some((x, y, w, h)) = args.crop {
+        source = source.crop_imm(x, y, w, h);
+    }
+
+    // Flatten transparency onto white before dropping to grey.
+    let rgba = source.to_rgba8();
+    let mut grey = GrayImage::new(rgba.width(), rgba.height());
+    for (x, y, p) in rgba.enumerate_pixels() {
+        let alpha = p[3] as f32 / 255.0;
+        let luma = 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32;
+        grey.put_pixel(x, y, Luma([(luma * alpha + 255.0 * (1.0 - alpha)) as u8]));
+    }
+    let mut out = fit(DynamicImage::ImageLuma8(grey), args.size, args.fit);
+    for p in out.pixels_mut() {
+        let mut v = p[0] as f32;
+        v = (v - 128.0) * args.contrast + 128.0 + args.brightness;
+        v = 255.0 * (v.clamp(0.0, 255.0) / 255.0).powf(1.0 / args.gamma);
+        if args.invert {
+            v = 255.0 - v;
+        }
+        p[0] = v.round().clamp(0.0, 255.0) as u8;
+    }
+    out
+}
+
+/// Scales a greyscale image into a `w`x`h` box.
+fn fit(grey: DynamicImage, (w, h): (u32, u32), fit: Fit) -> GrayImage {
+    match fit {
+        Fit::Crop => grey.resize_to_fill(w, h, FilterType::Lanczos3).to_luma8(),
+        Fit::Stretch => grey.resize_exact(w, h, FilterType::Lanczos3).to_luma8(),
+        Fit::Pad => {
+            let inner = grey.resize(w, h, FilterType::Lanczos3).to_luma8();
+            let mut padded = GrayImage::from_pixel(w, h, Luma([255]));
+            let (x, y) = ((w - inner.width()) / 2, (h - inner.height()) / 2);
+            image::imageops::replace(&mut padded, &inner, x as i64, y as i64);
+            padded
+        }
+    }
+}
+
+/// A 1-bit image, `true` for white.
+struct Bits {
+    width: u32,
+    pixels: Vec<bool>,
+}
+
+impl Bits {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.pixels.len() as u32 / self.width
+    }
+
+    fn get(&self, x: u32, y: u32) -> bool {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+fn dither(grey: &GrayImage, method: Dither, threshold: u8) -> Bits {
+    let (w, h) = grey.dimensions();
+    let mut pixels = vec![false; (w * h) as usize];
+
+    match method {
+        Dither::Threshold => {
+            for (x, y, p) in grey.enumerate_pixels() {
+                pixels[(y * w + x) as usize] = p[0] >= threshold;
+            }
+        }
+        Dither::Bayer => {
+            for (x, y, p) in grey.enumerate_pixels() {
+                let limit = (BAYER_8X8[(y % 8) as usize][(x % 8) as usize] as u32 * 4 + 2) as u8;
+                pixels[(y * w + x) as usize] = p[0] > limit;
+            }
+        }
+        Dither::FloydSteinberg | Dither::Atkinson => {
+            // (dx, dy, weight) with weights over `divisor`.
+            let (kernel, divisor): (&[(i32, i32, f32)], f32) = if method == Dither::Atkinson {
+                (&[(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)], 8.0)
+            } else {
+                (&[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)], 16.0)
+            };
+
+            let mut work: Vec<f32> = grey.pixels().map(|p| p[0] as f32).collect();
+            for y in 0..h as i32 {
+                // Serpentine scan keeps the error from streaking in one direction.
+                let reverse = y % 2 == 1;
+                for i in 0..w as i32 {
+                    let x = if reverse { w as i32 - 1 - i } else { i };
+                    let index = (y as u32 * w + x as u32) as usize;
+                    let old = work[index];
+                    let white = old >= threshold as f32;
+                    pixels[index] = white;
+                    let error = old - if white { 255.0 } else { 0.0 };
+
+                    for &(dx, dy, weight) in kernel {
+                        let nx = if reverse { x - dx } else { x + dx };
+                        let ny = y + dy;
+                        if nx >= 0 && nx < w as i32 && ny < h as i32 {
+                            work[(ny as u32 * w + nx as u32) as usize] += error * weight / divisor;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Bits { width: w, pixels }
+}
+
+fn write_preview(bits: &Bits, zoom: u32, path: &Path) -> Result<(), String> {
+    let zoom = zoom.max(1);
+    let preview = GrayImage::from_fn(bits.width() * zoom, bits.height() * zoom, |x, y| {
+        Luma([if bits.get(x / zoom, y / zoom) { 255 } else { 0 }])
+    });
+    preview.save(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// A bottom-up 1 bit per pixel BMP with a black/white palette, the layout `build.rs` expects.
+fn encode_bmp(bits: &Bits) -> Vec<u8> {
+    let (w, h) = (bits.width(), bits.height());
+    let stride = w.div_ceil(32) * 4;
+    let pixel_offset = 14 + 40 + 8;
+    let file_size = pixel_offset + stride * h;
+
+    let mut out = Vec::with_capacity(file_size as usize);
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&file_size.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&pixel_offset.to_le_bytes());
+
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(w as i32).to_le_bytes());
+    out.extend_from_slice(&(h as i32).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // planes
+    out.extend_from_slice(&1u16.to_le_bytes()); // bits per pixel
+    out.extend_from_slice(&0u32.to_le_bytes()); // no compression
+    out.extend_from_slice(&(stride * h).to_le_bytes());
+    out.extend_from_slice(&2835u32.to_le_bytes()); // 72 dpi
+    out.extend_from_slice(&2835u32.to_le_bytes());
+    out.extend_from_slice(&2u32.to_le_bytes()); // palette entries
+    out.extend_from_slice(&0u32.to_le_bytes());
+
+    // Index 0 is black (`BinaryColor::Off`), index 1 white.
+    out.extend_from_slice(&[0, 0, 0, 0, 255, 255, 255, 0]);
+
+    for y in (0..h).rev() {
+        let mut row = vec![0u8; stride as usize];
+        for x in 0..w {
+            if bits.get(x, y) {
+                row[(x / 8) as usize] |= 0x80 >> (x % 8);
+            }
+        }
+        out.extend_from_slice(&row);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(w: u32, h: u32, level: u8) -> GrayImage {
+        GrayImage::from_pixel(w, h, Luma([level]))
+    }
+
+    fn white_fraction(bits: &Bits) -> f32 {
+        bits.pixels.iter().filter(|&&white| white).count() as f32 / bits.pixels.len() as f32
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(parse_size("125x64"), Ok((125, 64)));
+        assert!(parse_size("0x64").is_err());
+        assert!(parse_size("125x0").is_err());
+        assert!(parse_size("125").is_err());
+        assert_eq!(parse_rect("1, 2,3,4"), Ok((1, 2, 3, 4)));
+        assert!(parse_rect("1,2,0,4").is_err());
+        assert!(parse_rect("1,2,3").is_err());
+        assert_eq!(parse_gamma("2.2"), Ok(2.2));
+        for bad in ["0", "-1", "inf", "NaN", "x"] {
+            assert!(parse_gamma(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn solid_colours_stay_solid() {
+        for method in Dither::value_variants() {
+            assert_eq!(white_fraction(&dither(&flat(16, 16, 255), *method, 128)), 1.0);
+            assert_eq!(white_fraction(&dither(&flat(16, 16, 0), *method, 128)), 0.0);
+        }
+    }
+
+    #[test]
+    fn threshold() {
+        let gradient = GrayImage::from_fn(256, 1, |x, _| Luma([x as u8]));
+        let bits = dither(&gradient, Dither::Threshold, 100);
+        assert!((0..256).all(|x| bits.get(x, 0) == (x >= 100)));
+    }
+
+    #[test]
+    fn greys_keep_their_level() {
+        // A quarter grey comes out about a quarter white.
+        for method in [Dither::Bayer, Dither::FloydSteinberg] {
+            let white = white_fraction(&dither(&flat(64, 64, 64), method, 128));
+            assert!((0.23..0.27).contains(&white), "{}", white);
+        }
+        // Atkinson only passes on three quarters of the error, so shadows come out darker.
+        let white = white_fraction(&dither(&flat(64, 64, 64), Dither::Atkinson, 128));
+        assert!((0.1..0.23).contains(&white), "{}", white);
+        // Bayer is the same pattern every 8 pixels.
+        let bits = dither(&flat(16, 16, 128), Dither::Bayer, 128);
+        assert!((0..8).all(|y| (0..8).all(|x| bits.get(x, y) == bits.get(x + 8, y + 8))));
+    }
+
+    #[test]
+    fn fit_modes() {
+        // A 40x20 image: black left half, white right half.
+        let source = DynamicImage::ImageLuma8(GrayImage::from_fn(40, 20, |x, _| Luma([if x < 20 { 0 } else { 255 }])));
+
+        let stretched = fit(source.clone(), (10, 10), Fit::Stretch);
+        assert_eq!(stretched.dimensions(), (10, 10));
+        assert!(stretched.get_pixel(1, 5)[0] < 64 && stretched.get_pixel(8, 5)[0] > 192);
+
+        // Cropping keeps the middle 20x20, half black, half white.
+        let cropped = fit(source.clone(), (10, 10), Fit::Crop);
+        assert_eq!(cropped.dimensions(), (10, 10));
+        assert!(cropped.get_pixel(1, 5)[0] < 64 && cropped.get_pixel(8, 5)[0] > 192);
+
+        // Padding fits all of it in 10x5 and fills above and below with white.
+        let padded = fit(source, (10, 10), Fit::Pad);
+        assert_eq!(padded.dimensions(), (10, 10));
+        assert_eq!(padded.get_pixel(1, 0)[0], 255);
+        assert!(padded.get_pixel(1, 5)[0] < 64);
+        assert_eq!(padded.get_pixel(1, 9)[0], 255);
+    }
+
+    #[test]
+    fn bmp_rows() {
+        let bits = Bits { width: 9, pixels: (0..18).map(|i| i % 9 == 0 || i == 17).collect() };
+        let bmp = encode_bmp(&bits);
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(bmp.len(), 62 + 2 * 4);
+        // Bottom-up, most significant bit first, rows padded to 4 bytes.
+        assert_eq!(bmp[62..], [0x80, 0x80, 0, 0, 0x80, 0, 0, 0]);
+    }
+}
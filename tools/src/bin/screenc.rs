@@ -0,0 +1,418 @@
+//! Compiles a badge screen description into the LXSC format from
+//! `crates/badge-screens`, which `src/screens.rs` reads with the same code, so a
+//! badge can be personalised without rebuilding the firmware.
+//!
+//! ```text
+//! cargo run --bin screenc -- ../screens/lynix.txt -o lynix.uf2
+//! ```
+//!
+//! Copy the `.uf2` onto the badge in BOOTSEL mode: it only covers the screens
+//! region at the end of flash and leaves the firmware alone. `-o x.bin` writes
+//! the raw blob instead.
+//!
+//! The source is line based. `#` starts a comment and `screen` starts a new
+//! screen; every other line is one element, drawn in order:
+//!
+//! ```text
+//! screen
+//! image  LYNIX   0,0
+//! text   140,0   width=156 font=24          Lynix
+//! text   140,32  width=156 align=left       Cybersecurity Student\nCanadian
+//! qr     230,62  scale=2                    https://lynix.ca
+//! rect   0,0     296x128 stroke=2 fill=none
+//! ```
+//!
+//! Text runs to the end of the line, optionally in double quotes, with `\n`
+//! for line breaks. Images are manifest handles from `assets/manifest.txt`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use badge_screens::{Align, Element, Fill, FONTS};
+use clap::Parser;
+
+const DISPLAY_WIDTH: i32 = 296;
+const DISPLAY_HEIGHT: i32 = 128;
+
+// Must match SCREENS in memory.x and src/screens.rs.
+const REGION_ADDRESS: u32 = 0x101F_0000;
+const REGION_SIZE: usize = 64 * 1024;
+
+// Byte-mode capacity of QR versions 1-10 at medium error correction, the most the firmware
+// encodes.
+const QR_CAPACITY: [usize; 10] = [14, 26, 42, 62, 84, 106, 122, 152, 180, 213];
+
+#[derive(Parser)]
+#[command(about = "Compile badge screen descriptions for the screens flash region")]
+struct Args {
+    /// Screen description to compile.
+    input: PathBuf,
+
+    /// Where to write the result, `.uf2` for drag-and-drop flashing, anything else for the raw blob.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Asset manifest used to check image names and placement.
+    #[arg(long, default_value = "../assets/manifest.txt")]
+    manifest: PathBuf,
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("screenc: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let source = fs::read_to_string(&args.input).map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    let images = read_manifest(&args.manifest)?;
+
+    let (count, body) = compile(&source, &images).map_err(|errors| {
+        let lines: Vec<String> = errors.iter().map(|(line, e)| format!("{}:{}: {}", args.input.display(), line, e)).collect();
+        lines.join("\n")
+    })?;
+
+    let blob = blob(count, &body)?;
+    println!("{} screen(s), {} bytes", count, blob.len());
+
+    if let Some(path) = &args.output {
+        let bytes = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("uf2")) {
+            uf2(&blob, REGION_ADDRESS)
+        } else {
+            blob
+        };
+        fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Image handles and sizes from the asset manifest.
+fn read_manifest(path: &Path) -> Result<Vec<(String, i32, i32)>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut images = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let size = fields.get(2).and_then(|s| s.split_once('x'));
+        match (fields.first(), size) {
+            (Some(handle), Some((w, h))) => {
+                let w = w.parse().map_err(|_| format!("{}: bad size in `{}`", path.display(), line))?;
+                let h = h.parse().map_err(|_| format!("{}: bad size in `{}`", path.display(), line))?;
+                images.push((handle.to_string(), w, h));
+            }
+            _ => return Err(format!("{}: can't read `{}`", path.display(), line)),
+        }
+    }
+    Ok(images)
+}
+
+type Errors = Vec<(usize, String)>;
+
+/// Compiles every screen, collecting all errors rather than stopping at the first.
+fn compile(source: &str, images: &[(String, i32, i32)]) -> Result<(u8, Vec<u8>), Errors> {
+    let mut errors = Errors::new();
+    let mut screens: Vec<(u8, Vec<u8>)> = Vec::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "screen" {
+            screens.push((0, Vec::new()));
+            continue;
+        }
+        let Some((count, out)) = screens.last_mut() else {
+            errors.push((number, "element before the first `screen`".into()));
+            continue;
+        };
+        if *count == u8::MAX {
+            errors.push((number, "too many elements on one screen".into()));
+            continue;
+        }
+        match element(line, images, out) {
+            Ok(()) => *count += 1,
+            Err(e) => errors.push((number, e)),
+        }
+    }
+
+    if screens.is_empty() {
+        errors.push((1, "no screens".into()));
+    }
+    if screens.len() > u8::MAX as usize {
+        errors.push((1, "too many screens".into()));
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut body = Vec::new();
+    for (count, elements) in &screens {
+        body.push(*count);
+        body.extend_from_slice(elements);
+    }
+    Ok((screens.len() as u8, body))
+}
+
+fn element(line: &str, images: &[(String, i32, i32)], out: &mut Vec<u8>) -> Result<(), String> {
+    let mut cursor = Cursor(line);
+    let kind = cursor.token().unwrap_or_default();
+    // Images name the picture before saying where it goes, like the manifest.
+    let name = if kind == "image" { cursor.token().ok_or("missing image name")? } else { "" };
+    let (x, y) = cursor.token().ok_or("missing x,y")?.split_once(',').ok_or("expected x,y")?;
+    let x: i32 = x.parse().map_err(|_| "bad x")?;
+    let y: i32 = y.parse().map_err(|_| "bad y")?;
+    if !(0..DISPLAY_WIDTH).contains(&x) || !(0..DISPLAY_HEIGHT).contains(&y) {
+        return Err(format!("{},{} is off the {}x{} display", x, y, DISPLAY_WIDTH, DISPLAY_HEIGHT));
+    }
+
+    match kind {
+        "image" => {
+            let (_, w, h) = images
+                .iter()
+                .find(|(handle, _, _)| handle == name)
+                .ok_or_else(|| format!("no image `{}` in the manifest", name))?;
+            if x + w > DISPLAY_WIDTH || y + h > DISPLAY_HEIGHT {
+                return Err(format!("{}x{} image at {},{} runs off the display", w, h, x, y));
+            }
+            encode(out, Element::Image { x: x as i16, y: y as i16, name })?;
+        }
+        "text" => {
+            let options = cursor.options(&["width", "font", "align"])?;
+            let width = option(&options, "width")?.unwrap_or(DISPLAY_WIDTH - x);
+            let font = option(&options, "font")?.unwrap_or(14);
+            let align = match options.iter().find(|(k, _)| *k == "align").map(|(_, v)| *v) {
+                None | Some("left") => Align::Left,
+                Some("center") => Align::Center,
+                Some("right") => Align::Right,
+                Some(other) => return Err(format!("unknown alignment `{}`", other)),
+            };
+            if width <= 0 || x + width > DISPLAY_WIDTH {
+                return Err(format!("text box {} wide at x={} runs off the display", width, x));
+            }
+            if !u8::try_from(font).is_ok_and(|font| FONTS.contains(&font)) {
+                return Err(format!("no {}pt font, use one of {:?}", font, FONTS));
+            }
+            let text = cursor.text()?;
+            encode(out, Element::Text { x: x as i16, y: y as i16, width: width as u16, font: font as u8, align, text: &text })?;
+        }
+        "qr" => {
+            let options = cursor.options(&["scale"])?;
+            let scale = option(&options, "scale")?.unwrap_or(2);
+            let payload = cursor.text()?;
+            let version = QR_CAPACITY
+                .iter()
+                .position(|&capacity| payload.len() <= capacity)
+                .ok_or_else(|| format!("QR payload is {} bytes, at most {} fit", payload.len(), QR_CAPACITY[9]))?;
+            // Error correction can get bumped up but the version never grows past this.
+            let size = (17 + 4 * (version as i32 + 1)) * scale;
+            if !(1..=255).contains(&scale) || x + size > DISPLAY_WIDTH || y + size > DISPLAY_HEIGHT {
+                return Err(format!("{}px QR code at {},{} runs off the display", size, x, y));
+            }
+            encode(out, Element::Qr { x: x as i16, y: y as i16, scale: scale as u8, payload: &payload })?;
+        }
+        "rect" => {
+            let (w, h) = cursor.token().ok_or("missing WxH")?.split_once('x').ok_or("expected WxH")?;
+            let w: i32 = w.parse().map_err(|_| "bad width")?;
+            let h: i32 = h.parse().map_err(|_| "bad height")?;
+            let options = cursor.options(&["fill", "stroke"])?;
+            let fill = match options.iter().find(|(k, _)| *k == "fill").map(|(_, v)| *v) {
+                None | Some("none") => Fill::None,
+                Some("black") => Fill::Black,
+                Some("white") => Fill::White,
+                Some(other) => return Err(format!("unknown fill `{}`", other)),
+            };
+            let stroke = option(&options, "stroke")?.unwrap_or(0);
+            if w <= 0 || h <= 0 || x + w > DISPLAY_WIDTH || y + h > DISPLAY_HEIGHT {
+                return Err(format!("{}x{} rectangle at {},{} runs off the display", w, h, x, y));
+            }
+            if !(0..=255).contains(&stroke) {
+                return Err("stroke must be 0-255".into());
+            }
+            if !cursor.0.trim().is_empty() {
+                return Err(format!("unexpected `{}`", cursor.0.trim()));
+            }
+            encode(out, Element::Rect { x: x as i16, y: y as i16, width: w as u16, height: h as u16, fill, stroke: stroke as u8 })?;
+        }
+        other => return Err(format!("unknown element `{}`", other)),
+    }
+    Ok(())
+}
+
+struct Cursor<'a>(&'a str);
+
+impl<'a> Cursor<'a> {
+    fn token(&mut self) -> Option<&'a str> {
+        let rest = self.0.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        self.0 = &rest[end..];
+        Some(&rest[..end])
+    }
+
+    /// Leading `key=value` tokens with one of `keys`.
+    fn options(&mut self, keys: &[&str]) -> Result<Vec<(&'a str, &'a str)>, String> {
+        let mut options = Vec::new();
+        loop {
+            let before = self.0;
+            match self.token().and_then(|token| token.split_once('=')) {
+                Some((key, value)) if keys.contains(&key) => options.push((key, value)),
+                Some((key, _)) if !key.contains(char::is_whitespace) && key.chars().all(|c| c.is_ascii_lowercase()) => {
+                    return Err(format!("unknown option `{}`", key));
+                }
+                _ => {
+                    self.0 = before;
+                    return Ok(options);
+                }
+            }
+        }
+    }
+
+    /// The rest of the line, unquoted and unescaped.
+    fn text(&mut self) -> Result<String, String> {
+        let mut raw = self.0.trim();
+        if raw.len() >= 2 && raw.starts_with('"') && raw.ends_with('"') {
+            raw = &raw[1..raw.len() - 1];
+        }
+        if raw.is_empty() {
+            return Err("missing text".into());
+        }
+
+        let mut text = String::new();
+        let mut chars = raw.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                text.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('\\') => text.push('\\'),
+                Some('"') => text.push('"'),
+                other => return Err(format!("unknown escape `\\{}`", other.map(String::from).unwrap_or_default())),
+            }
+        }
+        Ok(text)
+    }
+}
+
+fn option(options: &[(&str, &str)], key: &str) -> Result<Option<i32>, String> {
+    match options.iter().find(|(k, _)| *k == key) {
+        Some((_, value)) => value.parse().map(Some).map_err(|_| format!("bad {} `{}`", key, value)),
+        None => Ok(None),
+    }
+}
+
+fn encode(out: &mut Vec<u8>, element: Element) -> Result<(), String> {
+    element.encode(out).map_err(|_| "text too long".into())
+}
+
+/// Header plus body, as laid out in flash.
+fn blob(count: u8, body: &[u8]) -> Result<Vec<u8>, String> {
+    let header = badge_screens::header(count, body).map_err(|_| "screens don't fit in 64 KB")?;
+    let mut blob = header.to_vec();
+    blob.extend_from_slice(body);
+    if blob.len() > REGION_SIZE {
+        return Err("screens don't fit in the 64 KB region".into());
+    }
+    Ok(blob)
+}
+
+/// Wraps `data` in UF2 blocks for the RP2040 bootloader, starting at `address`.
+fn uf2(data: &[u8], address: u32) -> Vec<u8> {
+    const FAMILY_RP2040: u32 = 0xe48b_ff56;
+    const PAYLOAD: usize = 256;
+
+    let blocks = data.chunks(PAYLOAD).count() as u32;
+    let mut out = Vec::with_capacity(blocks as usize * 512);
+    for (number, chunk) in data.chunks(PAYLOAD).enumerate() {
+        let mut block = Vec::with_capacity(512);
+        for word in [
+            0x0a32_4655,
+            0x9e5d_5157,
+            0x0000_2000, // family ID present
+            address + (number * PAYLOAD) as u32,
+            PAYLOAD as u32,
+            number as u32,
+            blocks,
+            FAMILY_RP2040,
+        ] {
+            block.extend_from_slice(&u32::to_le_bytes(word));
+        }
+        block.extend_from_slice(chunk);
+        block.resize(512 - 4, 0);
+        block.extend_from_slice(&0x0ab1_6f30u32.to_le_bytes());
+        out.extend_from_slice(&block);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn images() -> Vec<(String, i32, i32)> {
+        vec![("LYNIX".into(), 125, 125)]
+    }
+
+    // What screenc writes has to be what the firmware reads back.
+    #[test]
+    fn firmware_reads_what_screenc_writes() {
+        let source = "\
+# a comment
+screen
+image  LYNIX   0,0
+text   140,0   width=156 font=24          Lynix
+text   140,32  align=right                \"Cybersecurity Student\\nCanadian\"
+qr     230,62  scale=2                    https://lynix.ca
+rect   0,0     296x128 stroke=2 fill=none
+
+screen
+rect   10,10   20x20 fill=black
+";
+        let (count, body) = compile(source, &images()).unwrap();
+        let blob = blob(count, &body).unwrap();
+        let screens = badge_screens::parse(&blob).unwrap();
+        assert_eq!(screens.len(), 2);
+
+        let card: Vec<_> = screens.get(0).unwrap().elements().map(Result::unwrap).collect();
+        assert_eq!(
+            card,
+            [
+                Element::Image { x: 0, y: 0, name: "LYNIX" },
+                Element::Text { x: 140, y: 0, width: 156, font: 24, align: Align::Left, text: "Lynix" },
+                Element::Text { x: 140, y: 32, width: 156, font: 14, align: Align::Right, text: "Cybersecurity Student\nCanadian" },
+                Element::Qr { x: 230, y: 62, scale: 2, payload: "https://lynix.ca" },
+                Element::Rect { x: 0, y: 0, width: 296, height: 128, fill: Fill::None, stroke: 2 },
+            ]
+        );
+        let square: Vec<_> = screens.get(1).unwrap().elements().map(Result::unwrap).collect();
+        assert_eq!(square, [Element::Rect { x: 10, y: 10, width: 20, height: 20, fill: Fill::Black, stroke: 0 }]);
+    }
+
+    #[test]
+    fn bundled_screens_compile() {
+        let source = fs::read_to_string("../screens/lynix.txt").unwrap();
+        let images = read_manifest(Path::new("../assets/manifest.txt")).unwrap();
+        let (count, body) = compile(&source, &images).unwrap();
+        let blob = blob(count, &body).unwrap();
+        assert_eq!(badge_screens::parse(&blob).map(|screens| screens.len()), Some(2));
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let source = "screen\nimage NOPE 0,0\ntext 0,0 font=263 x\nrect 290,0 10x10\n";
+        let lines: Vec<usize> = compile(source, &images()).unwrap_err().iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [2, 3, 4]);
+        assert!(compile("text 0,0 x\n", &images()).is_err());
+    }
+}